@@ -0,0 +1,562 @@
+//! Anthropic Admin API を使った組織全体の利用状況の集計。
+//!
+//! `AppConfig.admin_api` に Admin API キー (`sk-ant-admin...`) を設定した場合のみ使う。
+//! 当月分の料金をワークスペースごとに、トークン数を API キーごとに集計し、
+//! どのワークスペース・キーが予算を消費しているかを確認できるようにする。
+//!
+//! Team / Enterprise プランでは、メンバーごとの直近 7 日間の Claude Code の利用量も
+//! 集計する。個人の利用上限に対する使用率は API から取得できないため、チーム全体に
+//! 占める割合で上限に近いメンバーの目安とする。
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const API_BASE: &str = "https://api.anthropic.com/v1/organizations";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+/// 日単位のバケットは 1 ページ最大 31 件
+const BUCKET_LIMIT: &str = "31";
+/// メンバーごとの利用量を集計する日数 (週間枠に合わせる)
+const TEAM_USAGE_DAYS: i64 = 7;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceCost {
+    /// 既定のワークスペースは `None`
+    pub workspace_id: Option<String>,
+    pub name: Option<String>,
+    pub cost_usd: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiKeyUsage {
+    pub api_key_id: Option<String>,
+    pub name: Option<String>,
+    pub workspace_id: Option<String>,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OrgBreakdown {
+    pub since: DateTime<Utc>,
+    pub total_cost_usd: f64,
+    /// 料金の多い順
+    pub workspaces: Vec<WorkspaceCost>,
+    /// トークン数の多い順
+    pub api_keys: Vec<ApiKeyUsage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TeamMember {
+    /// 組織のメンバー一覧にない利用者 (削除済みなど) は `None`
+    pub user_id: Option<String>,
+    pub email: String,
+    pub name: Option<String>,
+    pub role: Option<String>,
+    pub sessions: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+    /// チーム全体のトークン数に占める割合 (%)
+    pub share: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TeamUsage {
+    pub since: DateTime<Utc>,
+    pub total_cost_usd: f64,
+    /// トークン数の多い順。利用のないメンバーも含める
+    pub members: Vec<TeamMember>,
+}
+
+/// レポート系エンドポイントの 1 ページ分の応答
+#[derive(Debug, Deserialize)]
+struct ReportPage<T> {
+    data: Vec<ReportBucket<T>>,
+    #[serde(default)]
+    has_more: bool,
+    #[serde(default)]
+    next_page: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReportBucket<T> {
+    results: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CostResult {
+    /// 最小通貨単位 (セント) の小数表記
+    amount: String,
+    #[serde(default)]
+    workspace_id: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CacheCreation {
+    #[serde(default)]
+    ephemeral_1h_input_tokens: u64,
+    #[serde(default)]
+    ephemeral_5m_input_tokens: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct UsageResult {
+    #[serde(default)]
+    api_key_id: Option<String>,
+    #[serde(default)]
+    workspace_id: Option<String>,
+    #[serde(default)]
+    uncached_input_tokens: u64,
+    #[serde(default)]
+    cache_read_input_tokens: u64,
+    #[serde(default)]
+    cache_creation: CacheCreation,
+    #[serde(default)]
+    output_tokens: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct NamedList {
+    data: Vec<NamedItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NamedItem {
+    id: String,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserList {
+    data: Vec<OrgUser>,
+    #[serde(default)]
+    has_more: bool,
+    #[serde(default)]
+    last_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OrgUser {
+    id: String,
+    email: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    role: Option<String>,
+}
+
+/// Claude Code の利用レポート (1 日分) の応答
+#[derive(Debug, Deserialize)]
+struct ClaudeCodePage {
+    data: Vec<ClaudeCodeRecord>,
+    #[serde(default)]
+    has_more: bool,
+    #[serde(default)]
+    next_page: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClaudeCodeRecord {
+    actor: Actor,
+    #[serde(default)]
+    core_metrics: CoreMetrics,
+    #[serde(default)]
+    model_breakdown: Vec<ModelUsage>,
+}
+
+/// API キーからの利用 (`api_actor`) はメンバーに紐付かないので集計しない
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Actor {
+    UserActor {
+        email_address: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CoreMetrics {
+    #[serde(default)]
+    num_sessions: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelUsage {
+    #[serde(default)]
+    tokens: ModelTokens,
+    #[serde(default)]
+    estimated_cost: Option<EstimatedCost>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ModelTokens {
+    #[serde(default)]
+    input: u64,
+    #[serde(default)]
+    output: u64,
+    #[serde(default)]
+    cache_read: u64,
+    #[serde(default)]
+    cache_creation: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct EstimatedCost {
+    /// セント単位
+    amount: f64,
+}
+
+async fn get_json<T: serde::de::DeserializeOwned>(
+    client: &reqwest::Client,
+    api_key: &str,
+    url: &str,
+    query: &[(&str, &str)],
+) -> Result<T, String> {
+    let resp = client
+        .get(url)
+        .header("x-api-key", api_key)
+        .header("anthropic-version", ANTHROPIC_VERSION)
+        .query(query)
+        .send()
+        .await
+        .map_err(|e| format!("Admin API request failed: {}", e))?;
+
+    let status = resp.status();
+    if !status.is_success() {
+        let body = resp.text().await.unwrap_or_default();
+        return Err(format!("Admin API returned status {}: {}", status, body));
+    }
+
+    resp.json::<T>()
+        .await
+        .map_err(|e| format!("Failed to parse Admin API response: {}", e))
+}
+
+/// すべてのページの結果を集める
+async fn get_report<T: serde::de::DeserializeOwned>(
+    client: &reqwest::Client,
+    api_key: &str,
+    path: &str,
+    query: &[(&str, &str)],
+) -> Result<Vec<T>, String> {
+    let url = format!("{}/{}", API_BASE, path);
+    let mut results = Vec::new();
+    let mut page: Option<String> = None;
+    loop {
+        let mut params = query.to_vec();
+        if let Some(p) = &page {
+            params.push(("page", p));
+        }
+        let resp: ReportPage<T> = get_json(client, api_key, &url, &params).await?;
+        results.extend(resp.data.into_iter().flat_map(|b| b.results));
+
+        match resp.next_page {
+            Some(next) if resp.has_more => page = Some(next),
+            _ => return Ok(results),
+        }
+    }
+}
+
+/// ID から名前への対応。名前は表示用なので取得できなくてもエラーにしない
+async fn names(client: &reqwest::Client, api_key: &str, path: &str) -> HashMap<String, String> {
+    let url = format!("{}/{}", API_BASE, path);
+    match get_json::<NamedList>(client, api_key, &url, &[("limit", "100")]).await {
+        Ok(list) => list.data.into_iter().map(|i| (i.id, i.name)).collect(),
+        Err(e) => {
+            tracing::warn!("Failed to fetch {} names: {}", path, e);
+            HashMap::new()
+        }
+    }
+}
+
+fn month_start(now: DateTime<Utc>) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+        .single()
+        .unwrap_or(now)
+}
+
+#[tracing::instrument(name = "fetch_org_breakdown", skip_all)]
+pub async fn fetch_breakdown(
+    client: &reqwest::Client,
+    api_key: &str,
+) -> Result<OrgBreakdown, String> {
+    let since = month_start(Utc::now());
+    let starting_at = since.to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+
+    let costs: Vec<CostResult> = get_report(
+        client,
+        api_key,
+        "cost_report",
+        &[
+            ("starting_at", &starting_at),
+            ("bucket_width", "1d"),
+            ("limit", BUCKET_LIMIT),
+            ("group_by[]", "workspace_id"),
+        ],
+    )
+    .await?;
+    let usage: Vec<UsageResult> = get_report(
+        client,
+        api_key,
+        "usage_report/messages",
+        &[
+            ("starting_at", &starting_at),
+            ("bucket_width", "1d"),
+            ("limit", BUCKET_LIMIT),
+            ("group_by[]", "api_key_id"),
+            ("group_by[]", "workspace_id"),
+        ],
+    )
+    .await?;
+
+    let workspace_names = names(client, api_key, "workspaces").await;
+    let api_key_names = names(client, api_key, "api_keys").await;
+
+    let mut workspace_costs: HashMap<Option<String>, f64> = HashMap::new();
+    for cost in costs {
+        let cents: f64 = cost.amount.parse().unwrap_or(0.0);
+        *workspace_costs.entry(cost.workspace_id).or_default() += cents / 100.0;
+    }
+    let mut workspaces: Vec<WorkspaceCost> = workspace_costs
+        .into_iter()
+        .map(|(workspace_id, cost_usd)| WorkspaceCost {
+            name: workspace_id
+                .as_ref()
+                .and_then(|id| workspace_names.get(id).cloned()),
+            workspace_id,
+            cost_usd,
+        })
+        .collect();
+    workspaces.sort_by(|a, b| b.cost_usd.total_cmp(&a.cost_usd));
+
+    let mut key_usage: HashMap<(Option<String>, Option<String>), (u64, u64)> = HashMap::new();
+    for u in usage {
+        let input = u.uncached_input_tokens
+            + u.cache_read_input_tokens
+            + u.cache_creation.ephemeral_1h_input_tokens
+            + u.cache_creation.ephemeral_5m_input_tokens;
+        let entry = key_usage.entry((u.api_key_id, u.workspace_id)).or_default();
+        entry.0 += input;
+        entry.1 += u.output_tokens;
+    }
+    let mut api_keys: Vec<ApiKeyUsage> = key_usage
+        .into_iter()
+        .map(
+            |((api_key_id, workspace_id), (input_tokens, output_tokens))| ApiKeyUsage {
+                name: api_key_id
+                    .as_ref()
+                    .and_then(|id| api_key_names.get(id).cloned()),
+                api_key_id,
+                workspace_id,
+                input_tokens,
+                output_tokens,
+            },
+        )
+        .collect();
+    api_keys.sort_by_key(|k| std::cmp::Reverse(k.input_tokens + k.output_tokens));
+
+    Ok(OrgBreakdown {
+        since,
+        total_cost_usd: workspaces.iter().map(|w| w.cost_usd).sum(),
+        workspaces,
+        api_keys,
+    })
+}
+
+async fn fetch_users(client: &reqwest::Client, api_key: &str) -> Result<Vec<OrgUser>, String> {
+    let url = format!("{}/users", API_BASE);
+    let mut users = Vec::new();
+    let mut after: Option<String> = None;
+    loop {
+        let mut params = vec![("limit", "100")];
+        if let Some(id) = &after {
+            params.push(("after_id", id));
+        }
+        let list: UserList = get_json(client, api_key, &url, &params).await?;
+        users.extend(list.data);
+
+        match list.last_id {
+            Some(last) if list.has_more => after = Some(last),
+            _ => return Ok(users),
+        }
+    }
+}
+
+/// Claude Code の利用レポートは 1 回の呼び出しで 1 日分しか返さないので、日ごとに取得する
+async fn fetch_claude_code_day(
+    client: &reqwest::Client,
+    api_key: &str,
+    day: NaiveDate,
+) -> Result<Vec<ClaudeCodeRecord>, String> {
+    let url = format!("{}/usage_report/claude_code", API_BASE);
+    let starting_at = day.format("%Y-%m-%d").to_string();
+    let mut records = Vec::new();
+    let mut page: Option<String> = None;
+    loop {
+        let mut params = vec![("starting_at", starting_at.as_str()), ("limit", "1000")];
+        if let Some(p) = &page {
+            params.push(("page", p));
+        }
+        let resp: ClaudeCodePage = get_json(client, api_key, &url, &params).await?;
+        records.extend(resp.data);
+
+        match resp.next_page {
+            Some(next) if resp.has_more => page = Some(next),
+            _ => return Ok(records),
+        }
+    }
+}
+
+/// メンバー一覧と利用レポートを突き合わせて、メンバーごとの利用量にまとめる
+fn roster(users: Vec<OrgUser>, records: Vec<ClaudeCodeRecord>) -> Vec<TeamMember> {
+    let mut members: Vec<TeamMember> = users
+        .into_iter()
+        .map(|u| TeamMember {
+            user_id: Some(u.id),
+            email: u.email,
+            name: u.name,
+            role: u.role,
+            sessions: 0,
+            input_tokens: 0,
+            output_tokens: 0,
+            cost_usd: 0.0,
+            share: 0.0,
+        })
+        .collect();
+
+    for record in records {
+        let Actor::UserActor { email_address } = record.actor else {
+            continue;
+        };
+        let index = match members
+            .iter()
+            .position(|m| m.email.eq_ignore_ascii_case(&email_address))
+        {
+            Some(i) => i,
+            None => {
+                members.push(TeamMember {
+                    user_id: None,
+                    email: email_address,
+                    name: None,
+                    role: None,
+                    sessions: 0,
+                    input_tokens: 0,
+                    output_tokens: 0,
+                    cost_usd: 0.0,
+                    share: 0.0,
+                });
+                members.len() - 1
+            }
+        };
+        let member = &mut members[index];
+        member.sessions += record.core_metrics.num_sessions;
+        for model in record.model_breakdown {
+            member.input_tokens +=
+                model.tokens.input + model.tokens.cache_read + model.tokens.cache_creation;
+            member.output_tokens += model.tokens.output;
+            member.cost_usd += model.estimated_cost.map_or(0.0, |c| c.amount / 100.0);
+        }
+    }
+
+    let total: u64 = members
+        .iter()
+        .map(|m| m.input_tokens + m.output_tokens)
+        .sum();
+    for member in &mut members {
+        if total > 0 {
+            member.share =
+                (member.input_tokens + member.output_tokens) as f64 / total as f64 * 100.0;
+        }
+    }
+    members.sort_by_key(|m| std::cmp::Reverse(m.input_tokens + m.output_tokens));
+    members
+}
+
+#[tracing::instrument(name = "fetch_team_usage", skip_all)]
+pub async fn fetch_team_usage(
+    client: &reqwest::Client,
+    api_key: &str,
+) -> Result<TeamUsage, String> {
+    let today = Utc::now().date_naive();
+    let first_day = today - Duration::days(TEAM_USAGE_DAYS - 1);
+
+    let users = fetch_users(client, api_key).await?;
+    let mut records = Vec::new();
+    for offset in 0..TEAM_USAGE_DAYS {
+        let day = first_day + Duration::days(offset);
+        records.extend(fetch_claude_code_day(client, api_key, day).await?);
+    }
+
+    let members = roster(users, records);
+    Ok(TeamUsage {
+        since: first_day.and_time(chrono::NaiveTime::MIN).and_utc(),
+        total_cost_usd: members.iter().map(|m| m.cost_usd).sum(),
+        members,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(json: serde_json::Value) -> ClaudeCodeRecord {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn aggregates_claude_code_usage_per_member() {
+        let users = vec![
+            OrgUser {
+                id: "user_1".to_string(),
+                email: "alice@example.com".to_string(),
+                name: Some("Alice".to_string()),
+                role: Some("developer".to_string()),
+            },
+            OrgUser {
+                id: "user_2".to_string(),
+                email: "bob@example.com".to_string(),
+                name: Some("Bob".to_string()),
+                role: Some("user".to_string()),
+            },
+        ];
+        let usage = |email: &str, input: u64, cents: f64| {
+            record(serde_json::json!({
+                "date": "2026-10-01T00:00:00Z",
+                "actor": { "type": "user_actor", "email_address": email },
+                "core_metrics": { "num_sessions": 2 },
+                "model_breakdown": [{
+                    "model": "claude-sonnet-4-5",
+                    "tokens": { "input": input, "output": 0, "cache_read": 0, "cache_creation": 0 },
+                    "estimated_cost": { "currency": "USD", "amount": cents }
+                }]
+            }))
+        };
+        let records = vec![
+            usage("Alice@example.com", 600, 150.0),
+            usage("alice@example.com", 200, 50.0),
+            usage("carol@example.com", 200, 25.0),
+            record(serde_json::json!({
+                "actor": { "type": "api_actor", "api_key_name": "ci" },
+                "model_breakdown": []
+            })),
+        ];
+
+        let members = roster(users, records);
+        let summary: Vec<(&str, u64, u64, f64)> = members
+            .iter()
+            .map(|m| (m.email.as_str(), m.sessions, m.input_tokens, m.share))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("alice@example.com", 4, 800, 80.0),
+                ("carol@example.com", 2, 200, 20.0),
+                ("bob@example.com", 0, 0, 0.0),
+            ]
+        );
+        assert_eq!(members[0].cost_usd, 2.0);
+        assert_eq!(members[1].user_id, None);
+    }
+}
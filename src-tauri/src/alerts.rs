@@ -0,0 +1,964 @@
+//! しきい値超過やトークン期限切れなどのアラートの検出と送信。
+//!
+//! 検出したアラートはデスクトップ通知として表示し、
+//! `AppConfig.alerts.webhook_urls` の各 URL にも POST する。
+//! Slack / Discord の Incoming Webhook にはそれぞれの形式で、それ以外の URL には
+//! 汎用の JSON で送る。
+//!
+//! 通知を控える時間帯 (`AppConfig.alerts.quiet_hours`) に検出したアラートは溜めておき、
+//! 時間帯が終わった時点でまとめて 1 件の通知として送る。同じ種類・メーターのアラートは
+//! 最新のものだけを残すので、長い時間帯でも件数は増え続けない。
+//!
+//! `AppConfig.alerts.sound` が有効な場合は、しきい値の超過とリセットの通知で効果音も鳴らす。
+//!
+//! スヌーズ中 (`AppConfig.alerts.snoozed_until`) はしきい値の通知だけを捨てる。
+//! 検出用の前回値はスヌーズ中も更新し続けるので、解除した直後にまとめて通知が届くことはない。
+//! しきい値の通知には「1 時間スヌーズ」のボタンを付け、押されたらトレイメニューと同じ
+//! [`snooze`] を呼ぶ。tauri-plugin-notification のアクションはモバイル専用なので、
+//! デスクトップ通知は notify-rust で直接送る。ボタンの応答を待つスレッドは 1 本だけにし、
+//! 待っている間に届いた通知にはボタンを付けない。
+
+use chrono::{DateTime, Datelike, Local, NaiveTime, Utc, Weekday};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tauri::{Emitter, Manager};
+use tokio::sync::Mutex;
+
+use crate::i18n::{self, Locale, Text};
+use crate::sound;
+use crate::{AlertConfig, AppState, CombinedUsageData, QuietHoursConfig};
+
+#[derive(Debug, Clone)]
+pub enum Alert {
+    ThresholdCrossed {
+        meter: &'static str,
+        threshold: f64,
+        utilization: f64,
+    },
+    TokenExpired,
+    /// 超過利用のクレジットが月間上限に対するしきい値を越えた
+    ExtraUsageCrossed {
+        threshold: f64,
+        used_credits: f64,
+        monthly_limit: f64,
+        estimated_cost: Option<f64>,
+    },
+    /// 全プロバイダー合計の利用額が月間予算に対するしきい値を越えた
+    BudgetCrossed {
+        threshold: f64,
+        spent: f64,
+        monthly_budget: f64,
+    },
+    /// 5 時間枠のリセット後、使用率が実際に下がったことを確認した
+    QuotaReset {
+        meter: &'static str,
+        utilization: f64,
+    },
+    /// 通知を控える時間帯に溜まったアラートのまとめ
+    QuietHoursSummary(Vec<Alert>),
+}
+
+impl Alert {
+    pub fn event(&self) -> &'static str {
+        match self {
+            Self::ThresholdCrossed { .. } => "threshold_crossed",
+            Self::ExtraUsageCrossed { .. } => "extra_usage_crossed",
+            Self::BudgetCrossed { .. } => "budget_crossed",
+            Self::TokenExpired => "token_expired",
+            Self::QuotaReset { .. } => "quota_reset",
+            Self::QuietHoursSummary(_) => "quiet_hours_summary",
+        }
+    }
+
+    pub fn message(&self, locale: Locale) -> String {
+        match (self, locale) {
+            (
+                Self::ThresholdCrossed {
+                    meter,
+                    threshold,
+                    utilization,
+                },
+                Locale::En,
+            ) => format!(
+                "{} usage crossed {:.0}% (now {:.1}%)",
+                meter_label(meter, locale),
+                threshold,
+                utilization
+            ),
+            (
+                Self::ThresholdCrossed {
+                    meter,
+                    threshold,
+                    utilization,
+                },
+                Locale::Ja,
+            ) => format!(
+                "{}の使用率が {:.0}% を超えました (現在 {:.1}%)",
+                meter_label(meter, locale),
+                threshold,
+                utilization
+            ),
+            (
+                Self::ExtraUsageCrossed {
+                    threshold,
+                    used_credits,
+                    monthly_limit,
+                    estimated_cost,
+                },
+                _,
+            ) => {
+                let mut message = match locale {
+                    Locale::En => format!(
+                        "Extra usage crossed {:.0}% of the monthly limit ({:.0} / {:.0} credits)",
+                        threshold, used_credits, monthly_limit
+                    ),
+                    Locale::Ja => format!(
+                        "超過利用が月間上限の {:.0}% を超えました ({:.0} / {:.0} クレジット)",
+                        threshold, used_credits, monthly_limit
+                    ),
+                };
+                if let Some(cost) = estimated_cost {
+                    message.push_str(&match locale {
+                        Locale::En => format!(", about ${:.2}", cost),
+                        Locale::Ja => format!("、約 ${:.2}", cost),
+                    });
+                }
+                message
+            }
+            (
+                Self::BudgetCrossed {
+                    threshold,
+                    spent,
+                    monthly_budget,
+                },
+                Locale::En,
+            ) => format!(
+                "Spending crossed {:.0}% of the monthly budget (${:.2} / ${:.2})",
+                threshold, spent, monthly_budget
+            ),
+            (
+                Self::BudgetCrossed {
+                    threshold,
+                    spent,
+                    monthly_budget,
+                },
+                Locale::Ja,
+            ) => format!(
+                "利用額が月間予算の {:.0}% を超えました (${:.2} / ${:.2})",
+                threshold, spent, monthly_budget
+            ),
+            (Self::TokenExpired, Locale::En) => {
+                "Claude access token expired. Run Claude Code to refresh it.".to_string()
+            }
+            (Self::TokenExpired, Locale::Ja) => {
+                "Claude のアクセストークンの有効期限が切れました。Claude Code を実行すると更新されます。"
+                    .to_string()
+            }
+            (Self::QuotaReset { meter, utilization }, Locale::En) => format!(
+                "{} quota refreshed (now {:.1}%). You can resume heavy usage.",
+                meter_label(meter, locale),
+                utilization
+            ),
+            (Self::QuotaReset { meter, utilization }, Locale::Ja) => format!(
+                "{}の枠がリセットされました (現在 {:.1}%)。",
+                meter_label(meter, locale),
+                utilization
+            ),
+            (Self::QuietHoursSummary(alerts), _) => {
+                let mut message = match locale {
+                    Locale::En => format!("{} alert(s) during quiet hours:", alerts.len()),
+                    Locale::Ja => format!("通知を控えていた間のアラート ({} 件):", alerts.len()),
+                };
+                for alert in alerts {
+                    message.push_str("\n- ");
+                    message.push_str(&alert.message(locale));
+                }
+                message
+            }
+        }
+    }
+}
+
+/// しきい値のうち `previous` 未満で `current` 以上のもの (上向きに越えたもの) の最大値
+fn highest_crossed(thresholds: &[f64], previous: f64, current: f64) -> Option<f64> {
+    thresholds
+        .iter()
+        .copied()
+        .filter(|t| previous < *t && current >= *t)
+        .reduce(f64::max)
+}
+
+/// メーター ID と使用率 (%) の一覧。値のないメーターは含めない。
+pub fn meter_utilizations(data: &CombinedUsageData) -> Vec<(&'static str, f64)> {
+    let claude = &data.claude;
+    let mut meters = vec![
+        ("five_hour", claude.five_hour.utilization),
+        ("seven_day", claude.seven_day.utilization),
+    ];
+    let optional = [
+        ("seven_day_opus", &claude.seven_day_opus),
+        ("seven_day_sonnet", &claude.seven_day_sonnet),
+        ("seven_day_oauth_apps", &claude.seven_day_oauth_apps),
+        ("seven_day_cowork", &claude.seven_day_cowork),
+    ];
+    meters.extend(
+        optional
+            .into_iter()
+            .filter_map(|(id, m)| m.as_ref().map(|m| (id, m.utilization))),
+    );
+    if let Some(copilot) = &data.copilot {
+        meters.push(("copilot", copilot.utilization));
+    }
+    meters
+}
+
+pub fn meter_label(meter: &str, locale: Locale) -> &'static str {
+    match (meter, locale) {
+        ("five_hour", Locale::En) => "5-hour",
+        ("five_hour", Locale::Ja) => "5 時間枠",
+        ("seven_day", Locale::En) => "Weekly",
+        ("seven_day", Locale::Ja) => "週間",
+        ("seven_day_opus", Locale::En) => "Weekly Opus",
+        ("seven_day_opus", Locale::Ja) => "週間 Opus",
+        ("seven_day_sonnet", Locale::En) => "Weekly Sonnet",
+        ("seven_day_sonnet", Locale::Ja) => "週間 Sonnet",
+        ("seven_day_oauth_apps", Locale::En) => "Weekly OAuth Apps",
+        ("seven_day_oauth_apps", Locale::Ja) => "週間 OAuth Apps",
+        ("seven_day_cowork", Locale::En) => "Weekly Cowork",
+        ("seven_day_cowork", Locale::Ja) => "週間 Cowork",
+        ("copilot", _) => "Copilot",
+        (_, Locale::En) => "Unknown",
+        (_, Locale::Ja) => "不明",
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MeterInfo {
+    pub id: String,
+    pub label: String,
+    /// 現在のプランでこのメーターが返されているか
+    pub present: bool,
+    pub utilization: Option<f64>,
+}
+
+/// 既知のすべてのメーターと、API が返した名前を知らないメーター。
+/// まだ取得していない場合はすべて `present: false` になる
+pub fn all_meters(data: Option<&CombinedUsageData>, locale: Locale) -> Vec<MeterInfo> {
+    let utilizations: HashMap<&str, f64> = data
+        .map(|d| meter_utilizations(d).into_iter().collect())
+        .unwrap_or_default();
+    let mut meters: Vec<MeterInfo> = crate::METER_IDS
+        .iter()
+        .map(|&id| MeterInfo {
+            id: id.to_string(),
+            label: meter_label(id, locale).to_string(),
+            present: utilizations.contains_key(id),
+            utilization: utilizations.get(id).copied(),
+        })
+        .collect();
+    if let Some(data) = data {
+        meters.extend(
+            data.claude
+                .extra_meters
+                .iter()
+                .map(|(id, meter)| MeterInfo {
+                    id: id.clone(),
+                    label: id.clone(),
+                    present: true,
+                    utilization: Some(meter.utilization),
+                }),
+        );
+    }
+    meters
+}
+
+fn parse_time(s: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(s, "%H:%M").ok()
+}
+
+/// `now` が通知を控える時間帯に含まれるかを返す。
+/// `start` > `end` の場合は日付をまたぐ時間帯 (例: 22:00〜08:00) として扱う。
+pub fn in_quiet_hours(config: &QuietHoursConfig, now: DateTime<Local>) -> bool {
+    if !config.enabled {
+        return false;
+    }
+    if config.weekends && matches!(now.weekday(), Weekday::Sat | Weekday::Sun) {
+        return true;
+    }
+
+    let (Some(start), Some(end)) = (parse_time(&config.start), parse_time(&config.end)) else {
+        return false;
+    };
+    let time = now.time();
+    if start <= end {
+        start <= time && time < end
+    } else {
+        time >= start || time < end
+    }
+}
+
+pub fn validate_time(s: &str) -> Result<(), String> {
+    parse_time(s)
+        .map(|_| ())
+        .ok_or_else(|| format!("Invalid time (expected HH:MM): {}", s))
+}
+
+/// 溜めておくアラートをまとめる単位。同じ種類・メーターのアラートは最新のものだけを残す
+fn queue_key(alert: &Alert) -> (&'static str, Option<&'static str>) {
+    let meter = match alert {
+        Alert::ThresholdCrossed { meter, .. } | Alert::QuotaReset { meter, .. } => Some(*meter),
+        _ => None,
+    };
+    (alert.event(), meter)
+}
+
+/// 前回の値と比較してアラートを検出するための状態。
+#[derive(Default)]
+pub struct AlertState {
+    tracker: std::sync::Mutex<AlertTracker>,
+}
+
+#[derive(Default)]
+struct AlertTracker {
+    last_utilization: HashMap<&'static str, f64>,
+    /// 前回取得時の 5 時間枠のリセット時刻
+    five_hour_resets_at: Option<DateTime<Utc>>,
+    token_expired: bool,
+    /// 通知を控える時間帯に検出したアラート
+    queued: Vec<Alert>,
+}
+
+impl AlertState {
+    /// 前回の取得結果と比較し、しきい値を上向きに越えたメーターと
+    /// 5 時間枠のリセットを検出する。起動直後の最初の取得ではアラートを出さない。
+    pub fn on_usage(&self, data: &CombinedUsageData, config: &AlertConfig) -> Vec<Alert> {
+        let mut tracker = self.tracker.lock().unwrap_or_else(|e| e.into_inner());
+        tracker.token_expired = false;
+
+        let five_hour = &data.claude.five_hour;
+        let previous_reset = std::mem::replace(
+            &mut tracker.five_hour_resets_at,
+            five_hour
+                .resets_at
+                .as_deref()
+                .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                .map(|t| t.with_timezone(&Utc)),
+        );
+        let previous_five_hour = tracker.last_utilization.get("five_hour").copied();
+
+        let mut alerts = Vec::new();
+        for (meter, utilization) in meter_utilizations(data) {
+            let previous = tracker.last_utilization.insert(meter, utilization);
+            let Some(previous) = previous else {
+                continue;
+            };
+            // 複数のしきい値を一度に越えた場合は最も高いものだけを通知する
+            if let Some(threshold) = highest_crossed(&config.thresholds, previous, utilization) {
+                alerts.push(Alert::ThresholdCrossed {
+                    meter,
+                    threshold,
+                    utilization,
+                });
+            }
+        }
+
+        if let Some(extra) = data.claude.extra_usage.as_ref().filter(|e| e.is_enabled) {
+            let previous = tracker
+                .last_utilization
+                .insert("extra_usage", extra.utilization);
+            let crossed = previous.and_then(|previous| {
+                highest_crossed(&config.extra_usage_thresholds, previous, extra.utilization)
+            });
+            if let Some(threshold) = crossed {
+                alerts.push(Alert::ExtraUsageCrossed {
+                    threshold,
+                    used_credits: extra.used_credits,
+                    monthly_limit: extra.monthly_limit,
+                    estimated_cost: extra.estimated_cost,
+                });
+            }
+        }
+
+        if let Some(budget) = &data.budget {
+            let previous = tracker
+                .last_utilization
+                .insert("budget", budget.utilization);
+            let crossed = previous.and_then(|previous| {
+                highest_crossed(&config.budget_thresholds, previous, budget.utilization)
+            });
+            if let Some(threshold) = crossed {
+                alerts.push(Alert::BudgetCrossed {
+                    threshold,
+                    spent: budget.spent,
+                    monthly_budget: budget.monthly_budget,
+                });
+            }
+        }
+
+        // リセット時刻を過ぎただけでは API 側の反映が遅れている場合があるため、
+        // 実際に使用率が下がったことを確認してから通知する
+        if config.notify_on_reset {
+            if let (Some(reset), Some(previous)) = (previous_reset, previous_five_hour) {
+                if Utc::now() >= reset && five_hour.utilization < previous {
+                    alerts.push(Alert::QuotaReset {
+                        meter: "five_hour",
+                        utilization: five_hour.utilization,
+                    });
+                }
+            }
+        }
+        alerts
+    }
+
+    /// トークンの期限切れを検出した時点で一度だけアラートを返す。
+    pub fn on_token_expired(&self) -> Option<Alert> {
+        let mut tracker = self.tracker.lock().unwrap_or_else(|e| e.into_inner());
+        if tracker.token_expired {
+            return None;
+        }
+        tracker.token_expired = true;
+        Some(Alert::TokenExpired)
+    }
+
+    fn queue(&self, alerts: Vec<Alert>) {
+        let mut tracker = self.tracker.lock().unwrap_or_else(|e| e.into_inner());
+        for alert in alerts {
+            let key = queue_key(&alert);
+            tracker.queued.retain(|a| queue_key(a) != key);
+            tracker.queued.push(alert);
+        }
+    }
+
+    fn take_queued(&self) -> Vec<Alert> {
+        let mut tracker = self.tracker.lock().unwrap_or_else(|e| e.into_inner());
+        std::mem::take(&mut tracker.queued)
+    }
+}
+
+/// スヌーズできる最長の時間 (分)
+pub const MAX_SNOOZE_MINUTES: u32 = 24 * 60;
+/// 通知のスヌーズボタンの ID と、押されたときに止める時間 (分)
+const SNOOZE_ACTION: &str = "snooze-60";
+const SNOOZE_ACTION_MINUTES: u32 = 60;
+
+pub fn is_snoozed(config: &AlertConfig, now: DateTime<Utc>) -> bool {
+    config.snoozed_until.is_some_and(|until| now < until)
+}
+
+/// スヌーズ中に止める、しきい値の超過の通知か
+fn is_threshold_alert(alert: &Alert) -> bool {
+    matches!(
+        alert,
+        Alert::ThresholdCrossed { .. }
+            | Alert::ExtraUsageCrossed { .. }
+            | Alert::BudgetCrossed { .. }
+    )
+}
+
+/// 通知にスヌーズのボタンを付けるか。スヌーズで止まるしきい値の通知だけに付ける
+fn offers_snooze(alert: &Alert) -> bool {
+    match alert {
+        Alert::QuietHoursSummary(alerts) => alerts.iter().any(is_threshold_alert),
+        alert => is_threshold_alert(alert),
+    }
+}
+
+/// スヌーズ中であれば、しきい値の通知を取り除く
+fn without_snoozed(config: &AlertConfig, alerts: Vec<Alert>, now: DateTime<Utc>) -> Vec<Alert> {
+    if !is_snoozed(config, now) {
+        return alerts;
+    }
+    alerts
+        .into_iter()
+        .filter(|a| !is_threshold_alert(a))
+        .collect()
+}
+
+/// `minutes` 分の間しきい値の通知を止める。0 の場合はスヌーズを解除する。
+/// 解除後の時刻を `alerts-snoozed` イベントで知らせる
+pub fn snooze(app: &tauri::AppHandle, minutes: u32) -> Result<Option<DateTime<Utc>>, String> {
+    if minutes > MAX_SNOOZE_MINUTES {
+        return Err(format!(
+            "Snooze duration must be at most {} minutes",
+            MAX_SNOOZE_MINUTES
+        ));
+    }
+    let mut config = crate::read_app_config()?;
+    config.alerts.snoozed_until =
+        (minutes > 0).then(|| Utc::now() + chrono::Duration::minutes(minutes.into()));
+    crate::write_app_config(&config)?;
+
+    tracing::info!(until = ?config.alerts.snoozed_until, "Alerts snoozed");
+    let _ = app.emit("alerts-snoozed", config.alerts.snoozed_until);
+    Ok(config.alerts.snoozed_until)
+}
+
+/// スヌーズのボタンを付けた通知の応答を待っているか
+static AWAITING_SNOOZE: AtomicBool = AtomicBool::new(false);
+
+/// デスクトップ通知を表示する。スヌーズのボタンを付けた場合は、押されるか
+/// 通知が閉じられるまでブロッキング用のスレッドで待つ。macOS では放置された通知の
+/// 応答を待ち続けるため、待つのは同時に 1 件までにしてスレッドが溜まらないようにする
+fn notify(app: &tauri::AppHandle, locale: Locale, alert: &Alert) {
+    let mut notification = notify_rust::Notification::new();
+    notification
+        .summary(i18n::text(locale, Text::AppTitle))
+        .body(&alert.message(locale))
+        .auto_icon();
+    // インストール済みのアプリとして実行している場合だけ ID を設定する
+    // (未登録の ID では Windows が通知を表示しない)
+    #[cfg(windows)]
+    {
+        let in_target_dir = std::env::current_exe().ok().is_some_and(|exe| {
+            exe.parent()
+                .and_then(|dir| dir.parent())
+                .and_then(|dir| dir.file_name())
+                .is_some_and(|name| name == "target")
+        });
+        if !in_target_dir {
+            notification.app_id(&app.config().identifier);
+        }
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let _ = notify_rust::set_application(if tauri::is_dev() {
+            "com.apple.Terminal"
+        } else {
+            &app.config().identifier
+        });
+    }
+    let snoozable = offers_snooze(alert)
+        && AWAITING_SNOOZE
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok();
+    if snoozable {
+        notification.action(SNOOZE_ACTION, i18n::text(locale, Text::NotificationSnooze));
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        match notification.show() {
+            Ok(handle) if snoozable => handle.wait_for_action(|action| {
+                if action == SNOOZE_ACTION {
+                    if let Err(e) = snooze(&app, SNOOZE_ACTION_MINUTES) {
+                        tracing::error!("Snooze error: {}", e);
+                    }
+                }
+            }),
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Notification error: {}", e),
+        }
+        if snoozable {
+            AWAITING_SNOOZE.store(false, Ordering::SeqCst);
+        }
+    });
+}
+
+/// アラートをバックグラウンドで送信する。ポーリングを送信の完了まで待たせない。
+/// 通知を控える時間帯の間は送信せずに溜めておく。
+pub fn raise(app: &tauri::AppHandle, alerts: Vec<Alert>) {
+    if alerts.is_empty() {
+        return;
+    }
+
+    let config = crate::read_app_config().unwrap_or_default().alerts;
+    let alerts = without_snoozed(&config, alerts, Utc::now());
+    if alerts.is_empty() {
+        return;
+    }
+    if in_quiet_hours(&config.quiet_hours, Local::now()) {
+        app.state::<AlertState>().queue(alerts);
+        return;
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        dispatch(&app, &config, alerts).await;
+    });
+}
+
+/// 通知を控える時間帯が終わっていれば、溜まっていたアラートをまとめて送る。
+/// 定期的に呼び出す。
+pub async fn flush_quiet_hours(app: &tauri::AppHandle) {
+    let config = crate::read_app_config().unwrap_or_default().alerts;
+    if in_quiet_hours(&config.quiet_hours, Local::now()) {
+        return;
+    }
+
+    let queued = without_snoozed(&config, app.state::<AlertState>().take_queued(), Utc::now());
+    if queued.is_empty() {
+        return;
+    }
+    dispatch(app, &config, vec![Alert::QuietHoursSummary(queued)]).await;
+}
+
+/// アラートを設定済みの送信先に送る。送信の失敗はログに出すだけにする。
+async fn dispatch(app: &tauri::AppHandle, config: &AlertConfig, alerts: Vec<Alert>) {
+    let locale = i18n::current();
+    // 同時に検出したアラートでも音は 1 回だけ鳴らす
+    if config.sound.enabled && alerts.iter().any(|a| sound::wants_sound(&config.sound, a)) {
+        sound::play(&config.sound);
+    }
+    if config.desktop_notifications {
+        for alert in &alerts {
+            notify(app, locale, alert);
+        }
+    }
+
+    // デモモードの合成データを実際の Webhook には送らない
+    if config.webhook_urls.is_empty() || crate::demo::is_enabled() {
+        return;
+    }
+
+    let client = {
+        let state = app.state::<Arc<Mutex<AppState>>>();
+        let s = state.lock().await;
+        s.http_client.clone()
+    };
+
+    for alert in &alerts {
+        for url in &config.webhook_urls {
+            if let Err(e) = send_webhook(&client, url, alert, locale).await {
+                tracing::warn!("Webhook error: {}", e);
+            }
+        }
+    }
+}
+
+/// Webhook の URL として受け付けるか。保存時とテスト送信時の両方で確かめ、
+/// 平文の http や URL でない文字列には送らない
+pub fn validate_webhook_url(url: &str) -> Result<(), String> {
+    match reqwest::Url::parse(url) {
+        Ok(parsed) if parsed.scheme() == "https" && parsed.host_str().is_some() => Ok(()),
+        Ok(_) => Err(format!("Webhook URL must use https: {}", url)),
+        Err(e) => Err(format!("Invalid webhook URL {}: {}", url, e)),
+    }
+}
+
+pub async fn send_webhook(
+    client: &reqwest::Client,
+    url: &str,
+    alert: &Alert,
+    locale: Locale,
+) -> Result<(), String> {
+    // Webhook URL はそれ自体が秘密情報なのでエラーに含めない
+    let resp = client
+        .post(url)
+        .json(&webhook_payload(url, alert, locale))
+        .send()
+        .await
+        .map_err(|e| format!("Webhook request failed: {}", e.without_url()))?;
+
+    let status = resp.status();
+    if !status.is_success() {
+        return Err(format!("Webhook returned status {}", status));
+    }
+    Ok(())
+}
+
+fn webhook_payload(url: &str, alert: &Alert, locale: Locale) -> serde_json::Value {
+    if url.starts_with("https://hooks.slack.com/") {
+        return json!({ "text": alert.message(locale) });
+    }
+    if url.starts_with("https://discord.com/api/webhooks/")
+        || url.starts_with("https://discordapp.com/api/webhooks/")
+    {
+        return json!({ "content": alert.message(locale) });
+    }
+    generic_payload(alert, locale)
+}
+
+fn generic_payload(alert: &Alert, locale: Locale) -> serde_json::Value {
+    let mut payload = json!({
+        "event": alert.event(),
+        "message": alert.message(locale),
+    });
+    match alert {
+        Alert::ThresholdCrossed {
+            meter,
+            threshold,
+            utilization,
+        } => {
+            payload["meter"] = json!(meter);
+            payload["threshold"] = json!(threshold);
+            payload["utilization"] = json!(utilization);
+        }
+        Alert::ExtraUsageCrossed {
+            threshold,
+            used_credits,
+            monthly_limit,
+            estimated_cost,
+        } => {
+            payload["threshold"] = json!(threshold);
+            payload["used_credits"] = json!(used_credits);
+            payload["monthly_limit"] = json!(monthly_limit);
+            payload["estimated_cost"] = json!(estimated_cost);
+        }
+        Alert::BudgetCrossed {
+            threshold,
+            spent,
+            monthly_budget,
+        } => {
+            payload["threshold"] = json!(threshold);
+            payload["spent"] = json!(spent);
+            payload["monthly_budget"] = json!(monthly_budget);
+        }
+        Alert::QuotaReset { meter, utilization } => {
+            payload["meter"] = json!(meter);
+            payload["utilization"] = json!(utilization);
+        }
+        Alert::TokenExpired => {}
+        Alert::QuietHoursSummary(alerts) => {
+            payload["alerts"] = alerts
+                .iter()
+                .map(|alert| generic_payload(alert, locale))
+                .collect();
+        }
+    }
+    payload
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_every_meter_with_its_presence() {
+        let mut data = crate::demo::sample_data(Utc::now());
+        let claude = &mut data.claude;
+        claude.seven_day_cowork = Some(claude.seven_day.clone());
+        claude.seven_day_oauth_apps = None;
+        claude.extra_meters.insert(
+            "seven_day_haiku".to_string(),
+            crate::UsageMeter {
+                utilization: 7.5,
+                resets_at: None,
+            },
+        );
+
+        let meters = all_meters(Some(&data), Locale::En);
+        let present = |id: &str| meters.iter().find(|m| m.id == id).map(|m| m.present);
+        assert_eq!(present("seven_day_cowork"), Some(true));
+        assert_eq!(present("seven_day_oauth_apps"), Some(false));
+        assert_eq!(present("copilot"), Some(false));
+        assert_eq!(present("seven_day_haiku"), Some(true));
+        assert_eq!(meters[0].label, "5-hour");
+
+        assert!(all_meters(None, Locale::Ja).iter().all(|m| !m.present));
+    }
+
+    /// 5 時間枠と週間枠だけを持つ取得結果
+    fn usage(five_hour: f64, seven_day: f64) -> CombinedUsageData {
+        let mut data = crate::demo::sample_data(Utc::now());
+        let claude = &mut data.claude;
+        claude.five_hour.utilization = five_hour;
+        claude.five_hour.resets_at = None;
+        claude.seven_day.utilization = seven_day;
+        claude.seven_day_opus = None;
+        claude.seven_day_sonnet = None;
+        claude.extra_usage = None;
+        data
+    }
+
+    fn crossed(alerts: &[Alert]) -> Vec<(&'static str, f64)> {
+        alerts
+            .iter()
+            .filter_map(|a| match a {
+                Alert::ThresholdCrossed {
+                    meter, threshold, ..
+                } => Some((*meter, *threshold)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reports_only_the_highest_threshold_crossed_upwards() {
+        let thresholds = [50.0, 80.0, 95.0];
+        assert_eq!(highest_crossed(&thresholds, 40.0, 96.0), Some(95.0));
+        assert_eq!(highest_crossed(&thresholds, 40.0, 80.0), Some(80.0));
+        assert_eq!(highest_crossed(&thresholds, 80.0, 90.0), None);
+        assert_eq!(highest_crossed(&thresholds, 96.0, 40.0), None);
+        assert_eq!(highest_crossed(&[], 0.0, 100.0), None);
+    }
+
+    #[test]
+    fn rearms_a_threshold_after_usage_drops() {
+        let state = AlertState::default();
+        let config = AlertConfig {
+            thresholds: vec![80.0],
+            ..AlertConfig::default()
+        };
+
+        // 最初の取得では前回値がないので通知しない
+        assert!(state.on_usage(&usage(85.0, 10.0), &config).is_empty());
+        assert!(state.on_usage(&usage(90.0, 10.0), &config).is_empty());
+        assert!(state.on_usage(&usage(5.0, 10.0), &config).is_empty());
+        assert_eq!(
+            crossed(&state.on_usage(&usage(82.0, 85.0), &config)),
+            vec![("five_hour", 80.0), ("seven_day", 80.0)]
+        );
+    }
+
+    #[test]
+    fn reports_a_reset_once_utilization_drops_after_the_reset_time() {
+        let state = AlertState::default();
+        let config = AlertConfig {
+            notify_on_reset: true,
+            ..AlertConfig::default()
+        };
+        let mut before = usage(60.0, 10.0);
+        before.claude.five_hour.resets_at =
+            Some((Utc::now() - chrono::Duration::minutes(1)).to_rfc3339());
+        state.on_usage(&before, &config);
+
+        // リセット時刻を過ぎても使用率が下がっていなければ通知しない
+        assert!(state.on_usage(&before, &config).is_empty());
+        let alerts = state.on_usage(&usage(3.0, 10.0), &config);
+        assert!(matches!(
+            alerts.as_slice(),
+            [Alert::QuotaReset {
+                meter: "five_hour",
+                ..
+            }]
+        ));
+    }
+
+    fn quiet_hours(start: &str, end: &str) -> QuietHoursConfig {
+        QuietHoursConfig {
+            enabled: true,
+            start: start.to_string(),
+            end: end.to_string(),
+            weekends: false,
+        }
+    }
+
+    fn local(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        use chrono::TimeZone;
+        // 2025-03-10 は月曜日
+        Local
+            .with_ymd_and_hms(2025, 3, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn checks_quiet_hours_within_a_day() {
+        let config = quiet_hours("12:00", "13:30");
+        assert!(!in_quiet_hours(&config, local(10, 11, 59)));
+        assert!(in_quiet_hours(&config, local(10, 12, 0)));
+        assert!(in_quiet_hours(&config, local(10, 13, 29)));
+        assert!(!in_quiet_hours(&config, local(10, 13, 30)));
+    }
+
+    #[test]
+    fn checks_quiet_hours_across_midnight() {
+        let config = quiet_hours("22:00", "08:00");
+        assert!(in_quiet_hours(&config, local(10, 23, 0)));
+        assert!(in_quiet_hours(&config, local(11, 0, 30)));
+        assert!(in_quiet_hours(&config, local(11, 7, 59)));
+        assert!(!in_quiet_hours(&config, local(11, 8, 0)));
+        assert!(!in_quiet_hours(&config, local(11, 21, 59)));
+    }
+
+    #[test]
+    fn checks_weekends_and_disabled_quiet_hours() {
+        let mut config = quiet_hours("22:00", "08:00");
+        config.weekends = true;
+        assert!(in_quiet_hours(&config, local(15, 12, 0)));
+        assert!(!in_quiet_hours(&config, local(14, 12, 0)));
+
+        config.enabled = false;
+        assert!(!in_quiet_hours(&config, local(15, 23, 0)));
+        assert!(!in_quiet_hours(
+            &quiet_hours("25:00", "08:00"),
+            local(10, 23, 0)
+        ));
+    }
+
+    #[test]
+    fn drops_threshold_alerts_only_while_snoozed() {
+        let now = Utc::now();
+        let mut config = AlertConfig {
+            snoozed_until: Some(now + chrono::Duration::minutes(30)),
+            ..AlertConfig::default()
+        };
+        let alerts = || {
+            vec![
+                Alert::ThresholdCrossed {
+                    meter: "five_hour",
+                    threshold: 80.0,
+                    utilization: 81.0,
+                },
+                Alert::TokenExpired,
+            ]
+        };
+        assert!(is_snoozed(&config, now));
+        assert!(matches!(
+            without_snoozed(&config, alerts(), now).as_slice(),
+            [Alert::TokenExpired]
+        ));
+
+        // 期限を過ぎたスヌーズは効かない
+        assert!(!is_snoozed(&config, now + chrono::Duration::minutes(30)));
+        assert_eq!(
+            without_snoozed(&config, alerts(), now + chrono::Duration::minutes(31)).len(),
+            2
+        );
+        config.snoozed_until = None;
+        assert!(!is_snoozed(&config, now));
+    }
+
+    #[test]
+    fn accepts_only_https_webhook_urls() {
+        assert!(validate_webhook_url("https://hooks.slack.com/services/T/B/X").is_ok());
+        assert!(validate_webhook_url("http://example.com/hook").is_err());
+        assert!(validate_webhook_url("file:///etc/passwd").is_err());
+        assert!(validate_webhook_url("hooks.slack.com/services").is_err());
+        assert!(validate_webhook_url("").is_err());
+    }
+
+    #[test]
+    fn offers_snooze_only_for_threshold_notifications() {
+        let crossed = || Alert::ThresholdCrossed {
+            meter: "five_hour",
+            threshold: 80.0,
+            utilization: 81.0,
+        };
+        assert!(offers_snooze(&crossed()));
+        assert!(!offers_snooze(&Alert::TokenExpired));
+        assert!(offers_snooze(&Alert::QuietHoursSummary(vec![
+            Alert::TokenExpired,
+            crossed(),
+        ])));
+        assert!(!offers_snooze(&Alert::QuietHoursSummary(vec![
+            Alert::TokenExpired
+        ])));
+    }
+
+    #[test]
+    fn keeps_only_the_latest_queued_alert_per_meter() {
+        let state = AlertState::default();
+        let threshold = |meter, threshold| Alert::ThresholdCrossed {
+            meter,
+            threshold,
+            utilization: threshold,
+        };
+        for _ in 0..50 {
+            state.queue(vec![threshold("five_hour", 80.0), Alert::TokenExpired]);
+        }
+        state.queue(vec![threshold("seven_day", 80.0)]);
+        state.queue(vec![threshold("five_hour", 95.0)]);
+
+        let queued = state.take_queued();
+        assert_eq!(queued.len(), 3);
+        assert!(matches!(queued[0], Alert::TokenExpired));
+        assert_eq!(
+            crossed(&queued),
+            vec![("seven_day", 80.0), ("five_hour", 95.0)]
+        );
+        assert!(state.take_queued().is_empty());
+    }
+}
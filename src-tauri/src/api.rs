@@ -0,0 +1,404 @@
+//! スクリプトや Stream Deck などの外部ツール向けのローカル REST API。
+//!
+//! `127.0.0.1` でのみ待ち受け、`Authorization: Bearer <token>` が設定と一致する
+//! リクエストだけを受け付ける。
+//!
+//! - `GET /usage`: ウィジェットに表示中の最新データ
+//! - `GET /history?range=24h&format=json`: 記録済みの履歴 (`format=csv` も可)
+//! - `POST /refresh`: 即時更新を要求する
+
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::future::Future;
+use std::sync::Arc;
+use tauri::Manager;
+use tokio::net::TcpListener;
+use tokio::sync::{oneshot, Mutex};
+
+use crate::{
+    calculate_budget_usage, history, i18n, reset_time, ApiConfig, AppState, CombinedUsageData,
+    PollingControl,
+};
+
+/// 起動中のサーバーと、それを止めるための通知先
+struct RunningServer {
+    shutdown: oneshot::Sender<()>,
+    task: tauri::async_runtime::JoinHandle<()>,
+}
+
+/// 起動中の API サーバー。設定変更時に停止して立ち上げ直す。
+#[derive(Default)]
+pub struct ApiServer {
+    running: std::sync::Mutex<Option<RunningServer>>,
+}
+
+impl ApiServer {
+    /// 既存のサーバーを停止し、設定で有効になっていれば新しく起動する。
+    ///
+    /// 古いサーバーが同じポートを解放し終えるまで待ってから待ち受けるので、
+    /// 再起動直後の bind が "address in use" で失敗しない。
+    pub fn restart(&self, app: &tauri::AppHandle, config: &ApiConfig) {
+        let mut running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+        let previous = running.take().map(|old| {
+            let _ = old.shutdown.send(());
+            old.task
+        });
+
+        if !config.enabled || config.token.is_empty() {
+            return;
+        }
+
+        let router = router(app.clone(), config.token.clone());
+        let port = config.port;
+        let (shutdown, stopped) = oneshot::channel();
+        let task = tauri::async_runtime::spawn(async move {
+            if let Some(previous) = previous {
+                let _ = previous.await;
+            }
+            let result = match bind(port).await {
+                Ok(listener) => serve(listener, router, stopped).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                tracing::error!("Local API error: {}", e);
+            }
+        });
+        *running = Some(RunningServer { shutdown, task });
+    }
+}
+
+/// API が参照するアプリの状態。テストでは固定のデータに差し替える
+trait Backend: Clone + Send + Sync + 'static {
+    fn usage(&self) -> impl Future<Output = Option<CombinedUsageData>> + Send;
+    fn history(&self, since: Option<DateTime<Utc>>) -> Result<Vec<history::HistorySample>, String>;
+    fn refresh(&self);
+}
+
+impl Backend for tauri::AppHandle {
+    async fn usage(&self) -> Option<CombinedUsageData> {
+        let state = self.state::<Arc<Mutex<AppState>>>();
+        let s = state.lock().await;
+        let claude = s.latest_usage.as_ref()?;
+        Some(CombinedUsageData {
+            claude: claude.clone(),
+            copilot: s.latest_copilot.clone(),
+            fetched_at: s.last_fetched_at,
+            reset_times: reset_time::for_usage(
+                claude,
+                s.latest_copilot.as_ref(),
+                chrono::Local::now(),
+                i18n::current(),
+            ),
+            budget: calculate_budget_usage(
+                claude,
+                s.latest_copilot.as_ref(),
+                &crate::read_app_config().unwrap_or_default(),
+            ),
+            rate_limit: s.rate_limit.clone(),
+            copilot_needs_reauth: s.copilot_auth_error.is_some(),
+            stale: s.last_emitted_usage.as_ref().and_then(|d| d.stale.clone()),
+        })
+    }
+
+    fn history(&self, since: Option<DateTime<Utc>>) -> Result<Vec<history::HistorySample>, String> {
+        history::read_samples(since)
+    }
+
+    fn refresh(&self) {
+        self.state::<Arc<PollingControl>>()
+            .refresh_notify
+            .notify_one();
+    }
+}
+
+#[derive(Clone)]
+struct ApiContext<B> {
+    backend: B,
+    token: Arc<str>,
+}
+
+fn router<B: Backend>(backend: B, token: String) -> Router {
+    Router::new()
+        .route("/usage", get(usage::<B>))
+        .route("/history", get(usage_history::<B>))
+        .route("/refresh", post(refresh::<B>))
+        .with_state(ApiContext {
+            backend,
+            token: token.into(),
+        })
+}
+
+async fn bind(port: u16) -> Result<TcpListener, String> {
+    TcpListener::bind((std::net::Ipv4Addr::LOCALHOST, port))
+        .await
+        .map_err(|e| format!("Failed to bind port {}: {}", port, e))
+}
+
+/// `shutdown` を受けたら新しい接続の受け付けをやめ、処理中のリクエストを終えてから返る
+async fn serve(
+    listener: TcpListener,
+    router: Router,
+    shutdown: oneshot::Receiver<()>,
+) -> Result<(), String> {
+    if let Ok(addr) = listener.local_addr() {
+        tracing::info!("Local API listening on http://{}", addr);
+    }
+
+    axum::serve(listener, router)
+        .with_graceful_shutdown(async {
+            let _ = shutdown.await;
+        })
+        .await
+        .map_err(|e| format!("Server stopped: {}", e))
+}
+
+fn error_response(status: StatusCode, message: impl Into<String>) -> Response {
+    (status, Json(serde_json::json!({ "error": message.into() }))).into_response()
+}
+
+/// 応答時間の差からトークンを推測されないよう、常に全バイトを比較する。
+fn is_authorized(token: &str, headers: &HeaderMap) -> bool {
+    let expected = format!("Bearer {}", token);
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .map(|v| v.as_bytes())
+        .unwrap_or_default();
+
+    provided.len() == expected.len()
+        && provided
+            .iter()
+            .zip(expected.as_bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+fn unauthorized() -> Response {
+    error_response(StatusCode::UNAUTHORIZED, "Invalid or missing token")
+}
+
+async fn usage<B: Backend>(State(ctx): State<ApiContext<B>>, headers: HeaderMap) -> Response {
+    if !is_authorized(&ctx.token, &headers) {
+        return unauthorized();
+    }
+
+    match ctx.backend.usage().await {
+        Some(data) => Json(data).into_response(),
+        None => error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "No usage data available yet",
+        ),
+    }
+}
+
+#[derive(Deserialize)]
+struct HistoryQuery {
+    range: Option<String>,
+    format: Option<String>,
+}
+
+async fn usage_history<B: Backend>(
+    State(ctx): State<ApiContext<B>>,
+    headers: HeaderMap,
+    Query(query): Query<HistoryQuery>,
+) -> Response {
+    if !is_authorized(&ctx.token, &headers) {
+        return unauthorized();
+    }
+
+    let parsed = history::ExportFormat::parse(query.format.as_deref().unwrap_or("json")).and_then(
+        |format| {
+            let range = history::parse_range(query.range.as_deref().unwrap_or("24h"))?;
+            Ok((format, range))
+        },
+    );
+    let (format, range) = match parsed {
+        Ok(v) => v,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e),
+    };
+
+    let since = range.map(|d| chrono::Utc::now() - d);
+    let body = match ctx
+        .backend
+        .history(since)
+        .and_then(|s| history::render(&s, format))
+    {
+        Ok(body) => body,
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    };
+
+    let content_type = match format {
+        history::ExportFormat::Csv => "text/csv; charset=utf-8",
+        history::ExportFormat::Json => "application/json",
+    };
+    ([(header::CONTENT_TYPE, content_type)], body).into_response()
+}
+
+async fn refresh<B: Backend>(State(ctx): State<ApiContext<B>>, headers: HeaderMap) -> Response {
+    if !is_authorized(&ctx.token, &headers) {
+        return unauthorized();
+    }
+
+    ctx.backend.refresh();
+    StatusCode::ACCEPTED.into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const TOKEN: &str = "secret-token";
+
+    #[derive(Clone, Default)]
+    struct FakeBackend {
+        usage: Option<CombinedUsageData>,
+        refreshes: Arc<AtomicUsize>,
+    }
+
+    impl Backend for FakeBackend {
+        async fn usage(&self) -> Option<CombinedUsageData> {
+            self.usage.clone()
+        }
+
+        fn history(
+            &self,
+            _since: Option<DateTime<Utc>>,
+        ) -> Result<Vec<history::HistorySample>, String> {
+            Ok(Vec::new())
+        }
+
+        fn refresh(&self) {
+            self.refreshes.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    struct TestServer {
+        base: String,
+        port: u16,
+        shutdown: oneshot::Sender<()>,
+        task: tokio::task::JoinHandle<Result<(), String>>,
+    }
+
+    async fn start(backend: FakeBackend) -> TestServer {
+        let listener = bind(0).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (shutdown, stopped) = oneshot::channel();
+        let task = tokio::spawn(serve(listener, router(backend, TOKEN.into()), stopped));
+        TestServer {
+            base: format!("http://127.0.0.1:{}", port),
+            port,
+            shutdown,
+            task,
+        }
+    }
+
+    fn client() -> reqwest::Client {
+        reqwest::Client::builder().no_proxy().build().unwrap()
+    }
+
+    fn bearer(token: &str) -> String {
+        format!("Bearer {}", token)
+    }
+
+    #[test]
+    fn accepts_only_the_exact_token() {
+        let with = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::AUTHORIZATION, value.parse().unwrap());
+            headers
+        };
+
+        assert!(is_authorized(TOKEN, &with(&bearer(TOKEN))));
+        assert!(!is_authorized(TOKEN, &HeaderMap::new()));
+        assert!(!is_authorized(TOKEN, &with(TOKEN)));
+        assert!(!is_authorized(TOKEN, &with(&bearer("secret-toke"))));
+        assert!(!is_authorized(TOKEN, &with(&bearer("secret-tokeN"))));
+        assert!(!is_authorized(TOKEN, &with(&bearer("secret-token2"))));
+    }
+
+    #[tokio::test]
+    async fn rejects_requests_without_the_token() {
+        let backend = FakeBackend::default();
+        let server = start(backend.clone()).await;
+        let client = client();
+
+        for (method, path) in [
+            (reqwest::Method::GET, "/usage"),
+            (reqwest::Method::GET, "/history"),
+            (reqwest::Method::POST, "/refresh"),
+        ] {
+            let url = format!("{}{}", server.base, path);
+            let resp = client.request(method.clone(), &url).send().await.unwrap();
+            assert_eq!(resp.status(), StatusCode::UNAUTHORIZED, "{}", path);
+
+            let resp = client
+                .request(method, &url)
+                .header(header::AUTHORIZATION, bearer("wrong"))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::UNAUTHORIZED, "{}", path);
+        }
+        assert_eq!(backend.refreshes.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn serves_usage_history_and_refresh() {
+        let client = client();
+        let get = |server: &TestServer, path: &str| {
+            client
+                .get(format!("{}{}", server.base, path))
+                .header(header::AUTHORIZATION, bearer(TOKEN))
+                .send()
+        };
+
+        let server = start(FakeBackend::default()).await;
+        let resp = get(&server, "/usage").await.unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let backend = FakeBackend {
+            usage: Some(crate::demo::sample_data(Utc::now())),
+            ..Default::default()
+        };
+        let server = start(backend.clone()).await;
+        let resp = get(&server, "/usage").await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = resp.json().await.unwrap();
+        assert!(body["claude"].is_object());
+
+        let resp = get(&server, "/history?range=7d&format=csv").await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()[header::CONTENT_TYPE],
+            "text/csv; charset=utf-8"
+        );
+
+        let resp = get(&server, "/history?range=forever").await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = client
+            .post(format!("{}/refresh", server.base))
+            .header(header::AUTHORIZATION, bearer(TOKEN))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        assert_eq!(backend.refreshes.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn releases_the_port_after_shutdown() {
+        let server = start(FakeBackend::default()).await;
+        server.shutdown.send(()).unwrap();
+        server.task.await.unwrap().unwrap();
+
+        // 停止を待ってからなら同じポートで待ち受け直せる
+        let listener = bind(server.port).await.unwrap();
+        assert_eq!(listener.local_addr().unwrap().port(), server.port);
+    }
+}
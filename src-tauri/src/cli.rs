@@ -0,0 +1,370 @@
+//! ウィンドウを開かずに使用量を取得して標準出力に書き出すヘッドレスモード。
+//!
+//! `usage-dashboard cli [--json] [--watch [SECS]] [--threshold PCT] [--demo]`
+//! (`--headless` でも可)。シェルのプロンプトや CI のチェックから使う。
+//!
+//! 終了コード: 0 = 正常、1 = 取得失敗・引数エラー、2 = しきい値超過
+
+use chrono::{Local, Utc};
+use std::time::Duration;
+
+use crate::{alerts, demo, github_auth, i18n, reset_time, AppConfig, CombinedUsageData};
+
+const EXIT_OK: i32 = 0;
+const EXIT_ERROR: i32 = 1;
+const EXIT_THRESHOLD_EXCEEDED: i32 = 2;
+
+const DEFAULT_WATCH_INTERVAL_SECS: u64 = 60;
+const MIN_WATCH_INTERVAL_SECS: u64 = 10;
+
+const USAGE: &str =
+    "Usage: usage-dashboard cli [--json] [--watch [SECS]] [--threshold PCT] [--demo]";
+
+struct Options {
+    json: bool,
+    /// `--watch` の更新間隔。`None` の場合は 1 回だけ取得して終了する
+    watch: Option<u64>,
+    /// 省略した場合は `AppConfig.alerts.thresholds` の最小値
+    threshold: Option<f64>,
+}
+
+/// コマンドライン引数が CLI モードを指定しているか
+pub fn is_requested(args: &[String]) -> bool {
+    args.first().is_some_and(|a| a == "cli") || args.iter().any(|a| a == "--headless")
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        json: false,
+        watch: None,
+        threshold: None,
+    };
+
+    let mut args = args
+        .iter()
+        .filter(|a| *a != "cli" && *a != "--headless" && *a != "--demo")
+        .peekable();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => options.json = true,
+            "--watch" => {
+                let secs = match args.next_if(|a| !a.starts_with("--")) {
+                    Some(v) => v
+                        .parse::<u64>()
+                        .map_err(|_| format!("Invalid watch interval: {}", v))?,
+                    None => DEFAULT_WATCH_INTERVAL_SECS,
+                };
+                options.watch = Some(secs.max(MIN_WATCH_INTERVAL_SECS));
+            }
+            "--threshold" => {
+                let value = args.next().ok_or("--threshold requires a value")?;
+                let threshold = value
+                    .parse::<f64>()
+                    .map_err(|_| format!("Invalid threshold: {}", value))?;
+                options.threshold = Some(threshold);
+            }
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            other => return Err(format!("Unknown option: {}\n{}", other, USAGE)),
+        }
+    }
+    Ok(options)
+}
+
+async fn fetch_once(
+    client: &reqwest::Client,
+    config: &AppConfig,
+) -> Result<CombinedUsageData, String> {
+    if demo::is_enabled() {
+        return demo_usage(config);
+    }
+
+    let token = crate::read_token_info()?;
+    if crate::is_token_expired(token.expires_at) {
+        return Err("Access token expired. Run Claude Code to refresh it.".to_string());
+    }
+
+    let (claude, rate_limit) = crate::fetch_usage(client, &token.access_token).await;
+    let limited_until = rate_limit.as_ref().and_then(|r| r.limited_until);
+    let mut claude = claude.map_err(|e| match limited_until {
+        Some(until) => format!(
+            "{} (rate limited until {})",
+            e,
+            until.with_timezone(&Local).format("%H:%M")
+        ),
+        None => e,
+    })?;
+    crate::apply_extra_usage_cost(&mut claude, config);
+
+    let candidates = github_auth::candidates(client, config.github.as_ref()).await;
+    let mut copilot_needs_reauth = false;
+    let copilot = match candidates.first() {
+        Some(first) => {
+            let monthly_limit =
+                crate::copilot_monthly_limit(client, &first.config, &mut None).await;
+            match crate::fetch_copilot_with_fallback(client, &candidates, monthly_limit).await {
+                Ok(data) => Some(data),
+                Err(e) => {
+                    copilot_needs_reauth = matches!(e, crate::CopilotError::Auth(_));
+                    eprintln!("Copilot: {}", e);
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    let reset_times =
+        reset_time::for_usage(&claude, copilot.as_ref(), Local::now(), i18n::current());
+    let budget = crate::calculate_budget_usage(&claude, copilot.as_ref(), config);
+    Ok(CombinedUsageData {
+        claude,
+        copilot,
+        fetched_at: Some(Utc::now()),
+        reset_times,
+        budget,
+        rate_limit,
+        copilot_needs_reauth,
+        stale: None,
+    })
+}
+
+fn demo_usage(config: &AppConfig) -> Result<CombinedUsageData, String> {
+    let now = Utc::now();
+    let mut claude = demo::usage(now)?;
+    crate::apply_extra_usage_cost(&mut claude, config);
+    let copilot = Some(demo::copilot(now));
+    Ok(CombinedUsageData {
+        reset_times: reset_time::for_usage(
+            &claude,
+            copilot.as_ref(),
+            Local::now(),
+            i18n::current(),
+        ),
+        budget: crate::calculate_budget_usage(&claude, copilot.as_ref(), config),
+        claude,
+        copilot,
+        fetched_at: Some(now),
+        rate_limit: None,
+        copilot_needs_reauth: false,
+        stale: None,
+    })
+}
+
+fn print_table(data: &CombinedUsageData) {
+    let locale = i18n::current();
+    println!("{:<20} {:>7}  RESETS", "METER", "USED");
+    for (meter, utilization) in alerts::meter_utilizations(data) {
+        let resets = data
+            .reset_times
+            .get(meter)
+            .map(|r| format!("{} ({})", r.relative, r.display))
+            .unwrap_or_default();
+        println!(
+            "{:<20} {:>6.1}%  {}",
+            alerts::meter_label(meter, locale),
+            utilization,
+            resets
+        );
+    }
+
+    if let Some(extra) = data.claude.extra_usage.as_ref().filter(|e| e.is_enabled) {
+        let cost = extra
+            .estimated_cost
+            .map(|c| format!("  (${:.2})", c))
+            .unwrap_or_default();
+        println!(
+            "{:<20} {:>6.1}%  {:.0} / {:.0} credits{}",
+            "Extra usage", extra.utilization, extra.used_credits, extra.monthly_limit, cost
+        );
+    }
+    if let Some(budget) = &data.budget {
+        println!(
+            "{:<20} {:>6.1}%  ${:.2} / ${:.2}",
+            "Budget", budget.utilization, budget.spent, budget.monthly_budget
+        );
+    }
+}
+
+/// いずれかのメーターまたは予算の使用率がしきい値以上か
+fn exceeds(data: &CombinedUsageData, threshold: f64) -> bool {
+    alerts::meter_utilizations(data)
+        .into_iter()
+        .map(|(_, utilization)| utilization)
+        .chain(data.budget.as_ref().map(|b| b.utilization))
+        .any(|utilization| utilization >= threshold)
+}
+
+/// 1 回分の取得と出力を行い、終了コードを返す
+async fn report(client: &reqwest::Client, options: &Options) -> i32 {
+    let config = crate::read_app_config().unwrap_or_default();
+    let data = match fetch_once(client, &config).await {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return EXIT_ERROR;
+        }
+    };
+
+    if options.json {
+        match serde_json::to_string(&data) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Error: Failed to serialize usage: {}", e);
+                return EXIT_ERROR;
+            }
+        }
+    } else {
+        print_table(&data);
+    }
+    exit_code(&data, options, &config)
+}
+
+/// 取得できた場合の終了コード。しきい値以上のメーターがあれば `EXIT_THRESHOLD_EXCEEDED`
+fn exit_code(data: &CombinedUsageData, options: &Options, config: &AppConfig) -> i32 {
+    let threshold = options
+        .threshold
+        .or_else(|| config.alerts.thresholds.iter().copied().reduce(f64::min));
+    if threshold.is_some_and(|t| exceeds(data, t)) {
+        EXIT_THRESHOLD_EXCEEDED
+    } else {
+        EXIT_OK
+    }
+}
+
+/// CLI モードを実行し、プロセスの終了コードを返す。
+/// `--watch` を指定した場合は中断されるまで取得を繰り返す。
+pub fn run(args: &[String]) -> i32 {
+    // リリースビルドの Windows ではコンソールを持たないため、呼び出し元のものに接続する
+    #[cfg(windows)]
+    unsafe {
+        use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+
+    if demo::is_requested(args) {
+        demo::enable();
+    }
+
+    let options = match parse_options(args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            return EXIT_ERROR;
+        }
+    };
+
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("Error: Failed to build HTTP client: {}", e);
+            return EXIT_ERROR;
+        }
+    };
+
+    tauri::async_runtime::block_on(async {
+        let Some(interval) = options.watch else {
+            return report(&client, &options).await;
+        };
+        loop {
+            report(&client, &options).await;
+            if !options.json {
+                println!();
+            }
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn detects_cli_mode() {
+        assert!(is_requested(&args(&["cli", "--json"])));
+        assert!(is_requested(&args(&["--demo", "--headless"])));
+        assert!(!is_requested(&args(&["--demo"])));
+        assert!(!is_requested(&args(&["--json", "cli"])));
+    }
+
+    #[test]
+    fn parses_options() {
+        let options =
+            parse_options(&args(&["cli", "--demo", "--json", "--threshold", "90"])).unwrap();
+        assert!(options.json);
+        assert_eq!(options.watch, None);
+        assert_eq!(options.threshold, Some(90.0));
+
+        let options = parse_options(&args(&["cli"])).unwrap();
+        assert!(!options.json);
+        assert_eq!(options.threshold, None);
+    }
+
+    #[test]
+    fn parses_watch_intervals() {
+        let watch = |a: &[&str]| parse_options(&args(a)).unwrap().watch;
+        assert_eq!(
+            watch(&["cli", "--watch"]),
+            Some(DEFAULT_WATCH_INTERVAL_SECS)
+        );
+        assert_eq!(
+            watch(&["cli", "--watch", "--json"]),
+            Some(DEFAULT_WATCH_INTERVAL_SECS)
+        );
+        assert_eq!(watch(&["cli", "--watch", "120"]), Some(120));
+        assert_eq!(
+            watch(&["cli", "--watch", "1"]),
+            Some(MIN_WATCH_INTERVAL_SECS)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_options() {
+        let error = |a: &[&str]| parse_options(&args(a)).err().unwrap();
+        assert!(error(&["cli", "--watch", "soon"]).starts_with("Invalid watch interval"));
+        assert_eq!(
+            error(&["cli", "--threshold"]),
+            "--threshold requires a value"
+        );
+        assert!(error(&["cli", "--threshold", "high"]).starts_with("Invalid threshold"));
+        assert!(error(&["cli", "--verbose"]).starts_with("Unknown option: --verbose"));
+        assert_eq!(error(&["cli", "--help"]), USAGE);
+    }
+
+    #[test]
+    fn exits_with_2_when_a_meter_reaches_the_threshold() {
+        let mut data = demo::sample_data(Utc::now());
+        data.claude.five_hour.utilization = 85.0;
+        data.claude.seven_day.utilization = 30.0;
+        data.claude.seven_day_opus = None;
+        data.claude.seven_day_sonnet = None;
+        let config = AppConfig::default();
+        let options = |threshold| Options {
+            json: false,
+            watch: None,
+            threshold,
+        };
+
+        assert_eq!(
+            exit_code(&data, &options(Some(85.0)), &config),
+            EXIT_THRESHOLD_EXCEEDED
+        );
+        assert_eq!(exit_code(&data, &options(Some(90.0)), &config), EXIT_OK);
+
+        // 省略した場合は設定のしきい値の最小値 (既定は 80%)
+        assert_eq!(
+            exit_code(&data, &options(None), &config),
+            EXIT_THRESHOLD_EXCEEDED
+        );
+        let mut config = AppConfig::default();
+        config.alerts.thresholds.clear();
+        assert_eq!(exit_code(&data, &options(None), &config), EXIT_OK);
+    }
+}
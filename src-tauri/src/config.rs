@@ -0,0 +1,662 @@
+//! `~/.usage-dashboard/config.json` の設定。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::{encryption, snap};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubConfig {
+    pub username: String,
+    pub token: String,
+    /// 手動で設定した月間プレミアムリクエスト数の上限。`None` の場合はプランから判定する
+    #[serde(default)]
+    pub monthly_limit: Option<f64>,
+    /// `monthly_limit` を手動で設定したか。プランを判定する前の版は上限を常に
+    /// (既定値の 300 でも) 保存していたため、この印がなければ読み込み時に移行する
+    #[serde(default)]
+    pub monthly_limit_override: bool,
+}
+
+/// プランを判定する前の版が既定値として保存していた上限
+const LEGACY_DEFAULT_MONTHLY_LIMIT: f64 = 300.0;
+
+impl GitHubConfig {
+    /// 以前の版の設定であれば、既定値のままだった上限を未設定 (プランから判定) に戻し、
+    /// それ以外の値は手動の設定として残す
+    fn migrate(&mut self) {
+        if self.monthly_limit_override {
+            return;
+        }
+        self.monthly_limit = self
+            .monthly_limit
+            .filter(|limit| *limit != LEGACY_DEFAULT_MONTHLY_LIMIT);
+        self.monthly_limit_override = self.monthly_limit.is_some();
+    }
+}
+
+/// Anthropic Admin API (組織全体の利用状況) の設定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminApiConfig {
+    /// `sk-ant-admin...` で始まる Admin API キー
+    pub api_key: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OverlayConfig {
+    #[serde(default)]
+    pub click_through: bool,
+    #[serde(default = "default_overlay_opacity")]
+    pub opacity: u8,
+}
+
+fn default_overlay_opacity() -> u8 {
+    60
+}
+
+impl Default for OverlayConfig {
+    fn default() -> Self {
+        Self {
+            click_through: false,
+            opacity: default_overlay_opacity(),
+        }
+    }
+}
+
+/// ウィジェットの画面端へのスナップ
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// 端からこの距離 (論理ピクセル) 以内に置くと吸着する
+    #[serde(default = "default_snap_margin")]
+    pub margin: u32,
+    /// モニター名ごとの吸着先
+    #[serde(default)]
+    pub docked: BTreeMap<String, snap::Dock>,
+}
+
+fn default_snap_margin() -> u32 {
+    16
+}
+
+impl Default for SnapConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            margin: default_snap_margin(),
+            docked: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatterySaverConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_battery_interval_multiplier")]
+    pub interval_multiplier: u64,
+    #[serde(default = "default_true")]
+    pub skip_copilot: bool,
+}
+
+fn default_battery_interval_multiplier() -> u64 {
+    3
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for BatterySaverConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_multiplier: default_battery_interval_multiplier(),
+            skip_copilot: true,
+        }
+    }
+}
+
+/// 操作がない間のポーリング間隔の延長 (`idle` モジュール)。既定では無効。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdleConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 最後の入力からこの秒数が経つとアイドル中とみなす
+    #[serde(default = "default_idle_after_secs")]
+    pub idle_after_secs: u64,
+    #[serde(default = "default_idle_interval_multiplier")]
+    pub interval_multiplier: u64,
+}
+
+fn default_idle_after_secs() -> u64 {
+    600
+}
+
+fn default_idle_interval_multiplier() -> u64 {
+    4
+}
+
+impl Default for IdleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            idle_after_secs: default_idle_after_secs(),
+            interval_multiplier: default_idle_interval_multiplier(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "default_history_retention_days")]
+    pub retention_days: u32,
+}
+
+fn default_history_retention_days() -> u32 {
+    90
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            retention_days: default_history_retention_days(),
+        }
+    }
+}
+
+/// `config.json` と履歴を暗号化して保存する設定。既定では無効
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EncryptionConfig {
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+/// Polybar / Waybar / Rainmeter などの外部ツール向けに、取得のたびに最新データを
+/// JSON ファイルへ書き出す設定。既定では無効。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DumpConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 書き出し先。未設定の場合は `~/.usage-dashboard/current.json`
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+/// ローカル REST API (`api` モジュール) の設定。既定では無効。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_api_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub token: String,
+}
+
+fn default_api_port() -> u16 {
+    47821
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: default_api_port(),
+            token: String::new(),
+        }
+    }
+}
+
+pub fn generate_api_token() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
+
+/// アラートの送信を控える時間帯。時刻はローカル時刻の "HH:MM"。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuietHoursConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_quiet_start")]
+    pub start: String,
+    #[serde(default = "default_quiet_end")]
+    pub end: String,
+    /// 土日は終日控える
+    #[serde(default)]
+    pub weekends: bool,
+}
+
+fn default_quiet_start() -> String {
+    "22:00".to_string()
+}
+
+fn default_quiet_end() -> String {
+    "08:00".to_string()
+}
+
+impl Default for QuietHoursConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            start: default_quiet_start(),
+            end: default_quiet_end(),
+            weekends: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertConfig {
+    /// 使用率 (%) のしきい値。いずれかを越えた時点でアラートを送る
+    #[serde(default = "default_alert_thresholds")]
+    pub thresholds: Vec<f64>,
+    /// Slack / Discord の Incoming Webhook、または任意の JSON 受信先
+    #[serde(default)]
+    pub webhook_urls: Vec<String>,
+    #[serde(default = "default_true")]
+    pub desktop_notifications: bool,
+    /// 5 時間枠がリセットされたときにも通知する
+    #[serde(default)]
+    pub notify_on_reset: bool,
+    #[serde(default)]
+    pub quiet_hours: QuietHoursConfig,
+    /// 超過利用 (extra usage) の月間上限に対する使用率 (%) のしきい値
+    #[serde(default = "default_extra_usage_thresholds")]
+    pub extra_usage_thresholds: Vec<f64>,
+    /// 月間予算に対する利用額 (%) のしきい値
+    #[serde(default = "default_budget_thresholds")]
+    pub budget_thresholds: Vec<f64>,
+    #[serde(default)]
+    pub sound: SoundConfig,
+    /// この時刻までしきい値の通知を止める (`snooze_alerts`)。再起動後も引き継ぐ
+    #[serde(default)]
+    pub snoozed_until: Option<DateTime<Utc>>,
+}
+
+fn default_alert_thresholds() -> Vec<f64> {
+    vec![80.0, 95.0]
+}
+
+fn default_extra_usage_thresholds() -> Vec<f64> {
+    vec![50.0, 80.0, 100.0]
+}
+
+fn default_budget_thresholds() -> Vec<f64> {
+    vec![50.0, 80.0, 100.0]
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            thresholds: default_alert_thresholds(),
+            webhook_urls: Vec::new(),
+            desktop_notifications: true,
+            notify_on_reset: false,
+            quiet_hours: QuietHoursConfig::default(),
+            extra_usage_thresholds: default_extra_usage_thresholds(),
+            budget_thresholds: default_budget_thresholds(),
+            sound: SoundConfig::default(),
+            snoozed_until: None,
+        }
+    }
+}
+
+/// アラートの効果音 (`sound` モジュール)。既定では無効。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SoundConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 使用率・超過利用・予算のしきい値を越えたときに鳴らす
+    #[serde(default = "default_true")]
+    pub on_threshold: bool,
+    /// 5 時間枠のリセットを通知するときに鳴らす
+    #[serde(default = "default_true")]
+    pub on_reset: bool,
+    /// 内蔵のチャイム ("chime" / "bell" / "ping")
+    #[serde(default = "default_chime")]
+    pub chime: String,
+    /// 指定した場合は内蔵のチャイムの代わりにこの音声ファイルを再生する
+    #[serde(default)]
+    pub file: Option<String>,
+    /// 0.0〜1.0
+    #[serde(default = "default_sound_volume")]
+    pub volume: f32,
+}
+
+fn default_chime() -> String {
+    "chime".to_string()
+}
+
+fn default_sound_volume() -> f32 {
+    0.8
+}
+
+impl Default for SoundConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            on_threshold: true,
+            on_reset: true,
+            chime: default_chime(),
+            file: None,
+            volume: default_sound_volume(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtraUsageConfig {
+    /// 1 クレジットあたりの料金 (USD)。API はセント単位で返す
+    #[serde(default = "default_credit_value")]
+    pub credit_value: f64,
+}
+
+fn default_credit_value() -> f64 {
+    0.01
+}
+
+impl Default for ExtraUsageConfig {
+    fn default() -> Self {
+        Self {
+            credit_value: default_credit_value(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetConfig {
+    /// 全プロバイダー合計の月間予算 (USD)。未設定の場合は予算メーターを表示しない
+    #[serde(default)]
+    pub monthly_budget: Option<f64>,
+    /// Copilot の月間上限を超えたプレミアムリクエスト 1 件あたりの料金 (USD)
+    #[serde(default = "default_copilot_overage_price")]
+    pub copilot_overage_price: f64,
+}
+
+fn default_copilot_overage_price() -> f64 {
+    0.04
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        Self {
+            monthly_budget: None,
+            copilot_overage_price: default_copilot_overage_price(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggingConfig {
+    /// "error" / "warn" / "info" / "debug" / "trace"
+    #[serde(default = "default_log_level")]
+    pub level: String,
+    /// ログをフロントエンドにも `log-event` イベントとして送る
+    #[serde(default)]
+    pub emit_events: bool,
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: default_log_level(),
+            emit_events: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppConfig {
+    #[serde(default)]
+    pub github: Option<GitHubConfig>,
+    #[serde(default)]
+    pub admin_api: Option<AdminApiConfig>,
+    #[serde(default)]
+    pub autostart_enabled: bool,
+    #[serde(default)]
+    pub overlay: OverlayConfig,
+    #[serde(default)]
+    pub snap: SnapConfig,
+    #[serde(default)]
+    pub battery_saver: BatterySaverConfig,
+    #[serde(default)]
+    pub idle: IdleConfig,
+    #[serde(default)]
+    pub history: HistoryConfig,
+    #[serde(default)]
+    pub encryption: EncryptionConfig,
+    #[serde(default)]
+    pub api: ApiConfig,
+    #[serde(default)]
+    pub alerts: AlertConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub extra_usage: ExtraUsageConfig,
+    #[serde(default)]
+    pub budget: BudgetConfig,
+    #[serde(default)]
+    pub dump: DumpConfig,
+    /// 通知などの表示言語 ("en" / "ja")。未設定の場合は OS のロケールに従う
+    #[serde(default)]
+    pub locale: Option<String>,
+}
+
+pub fn config_dir() -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or("Could not find home directory")?;
+    let config_dir = home.join(".usage-dashboard");
+    std::fs::create_dir_all(&config_dir)
+        .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    Ok(config_dir)
+}
+
+pub fn config_path() -> Result<PathBuf, String> {
+    Ok(config_dir()?.join("config.json"))
+}
+
+/// 直前に正しく読めた設定のコピー。`config.json` が壊れた場合に読み込む
+fn backup_path(path: &Path) -> PathBuf {
+    path.with_extension("json.bak")
+}
+
+pub fn read_app_config() -> Result<AppConfig, String> {
+    read_config_from(&config_path()?)
+}
+
+pub fn write_app_config(config: &AppConfig) -> Result<(), String> {
+    write_config_to(&config_path()?, config)
+}
+
+/// 設定を書き込み、バックアップも同じ内容で置き換える
+fn replace_config_and_backup_to(path: &Path, config: &AppConfig) -> Result<(), String> {
+    write_config_to(path, config)?;
+    write_atomic(&backup_path(path), &encode_config(config)?)
+}
+
+/// 暗号化を切り替えたときなど、以前の形式のバックアップを残したくない場合に使う
+pub fn replace_app_config_and_backup(config: &AppConfig) -> Result<(), String> {
+    replace_config_and_backup_to(&config_path()?, config)
+}
+
+fn parse_config_file(path: &Path) -> Result<AppConfig, String> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read config: {}", e))?;
+    let content = encryption::decode(&content)?;
+    let mut config: AppConfig =
+        serde_json::from_str(&content).map_err(|e| format!("Failed to parse config: {}", e))?;
+    if let Some(github) = config.github.as_mut() {
+        github.migrate();
+    }
+    Ok(config)
+}
+
+fn read_config_from(path: &Path) -> Result<AppConfig, String> {
+    if !path.exists() {
+        return Ok(AppConfig::default());
+    }
+    parse_config_file(path).or_else(|err| {
+        let backup = backup_path(path);
+        if !backup.exists() {
+            return Err(err);
+        }
+        match parse_config_file(&backup) {
+            Ok(config) => {
+                tracing::warn!(error = %err, "Config is unreadable, using backup");
+                Ok(config)
+            }
+            Err(_) => Err(err),
+        }
+    })
+}
+
+fn encode_config(config: &AppConfig) -> Result<String, String> {
+    let content = serde_json::to_string_pretty(config)
+        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+    encryption::encode(&content, config.encryption.enabled)
+}
+
+fn write_config_to(path: &Path, config: &AppConfig) -> Result<(), String> {
+    let content = encode_config(config)?;
+    // 壊れた設定でバックアップを上書きしないよう、読める場合だけ退避する
+    if parse_config_file(path).is_ok() {
+        if let Err(e) = std::fs::copy(path, backup_path(path)) {
+            tracing::warn!(error = %e, "Failed to back up config");
+        }
+    } else if std::fs::read_to_string(path).is_ok_and(|c| encryption::is_encrypted(&c)) {
+        // キーリングから鍵を読めないだけの場合に、暗号化された設定を既定値で上書きしない
+        return Err("Config is encrypted but the encryption key is unavailable".to_string());
+    }
+    write_atomic(path, &content)
+}
+
+/// 同じディレクトリの一時ファイルに書いてから置き換え、読み手が書きかけの内容を
+/// 読まないようにする。
+pub fn write_atomic(path: &Path, content: impl AsRef<[u8]>) -> Result<(), String> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| format!("Invalid path: {}", path.display()))?;
+    let mut tmp_name = file_name.to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    std::fs::write(&tmp_path, content)
+        .map_err(|e| format!("Failed to write {}: {}", tmp_path.display(), e))?;
+    std::fs::rename(&tmp_path, path).map_err(|e| {
+        let _ = std::fs::remove_file(&tmp_path);
+        format!("Failed to replace {}: {}", path.display(), e)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_config_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "usage-dashboard-config-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("config.json")
+    }
+
+    fn config_with_locale(locale: &str) -> AppConfig {
+        AppConfig {
+            locale: Some(locale.to_string()),
+            ..AppConfig::default()
+        }
+    }
+
+    #[test]
+    fn keeps_previous_config_as_backup() {
+        let path = temp_config_path("backup");
+        write_config_to(&path, &config_with_locale("en")).unwrap();
+        assert!(!backup_path(&path).exists());
+
+        write_config_to(&path, &config_with_locale("ja")).unwrap();
+        let backup = parse_config_file(&backup_path(&path)).unwrap();
+        assert_eq!(backup.locale, Some("en".to_string()));
+        let current = read_config_from(&path).unwrap();
+        assert_eq!(current.locale, Some("ja".to_string()));
+        assert!(!path.with_extension("json.tmp").exists());
+    }
+
+    #[test]
+    fn replaces_backup_with_the_new_config() {
+        let path = temp_config_path("replace");
+        write_config_to(&path, &config_with_locale("en")).unwrap();
+        write_config_to(&path, &config_with_locale("ja")).unwrap();
+
+        replace_config_and_backup_to(&path, &config_with_locale("fr")).unwrap();
+        let backup = parse_config_file(&backup_path(&path)).unwrap();
+        assert_eq!(backup.locale, Some("fr".to_string()));
+        let current = read_config_from(&path).unwrap();
+        assert_eq!(current.locale, Some("fr".to_string()));
+    }
+
+    #[test]
+    fn falls_back_to_backup_when_config_is_corrupted() {
+        let path = temp_config_path("recover");
+        write_config_to(&path, &config_with_locale("en")).unwrap();
+        write_config_to(&path, &config_with_locale("ja")).unwrap();
+        std::fs::write(&path, "{\"locale\": \"en").unwrap();
+
+        let config = read_config_from(&path).unwrap();
+        assert_eq!(config.locale, Some("en".to_string()));
+
+        // 壊れたファイルはバックアップに退避しない
+        write_config_to(&path, &config_with_locale("fr")).unwrap();
+        let backup = parse_config_file(&backup_path(&path)).unwrap();
+        assert_eq!(backup.locale, Some("en".to_string()));
+    }
+
+    #[test]
+    fn migrates_the_legacy_default_monthly_limit() {
+        let path = temp_config_path("github");
+        let github = |monthly_limit: &str| {
+            format!(
+                "{{\"github\": {{\"username\": \"octocat\", \"token\": \"t\", {}}}}}",
+                monthly_limit
+            )
+        };
+        let read = |content: String| {
+            std::fs::write(&path, content).unwrap();
+            let github = read_config_from(&path).unwrap().github.unwrap();
+            (github.monthly_limit, github.monthly_limit_override)
+        };
+
+        // 以前の版は既定値の 300 も保存していた
+        assert_eq!(read(github("\"monthly_limit\": 300.0")), (None, false));
+        assert_eq!(
+            read(github("\"monthly_limit\": 1000.0")),
+            (Some(1000.0), true)
+        );
+        assert_eq!(
+            read(github(
+                "\"monthly_limit\": 300.0, \"monthly_limit_override\": true"
+            )),
+            (Some(300.0), true)
+        );
+        assert_eq!(read(github("\"monthly_limit\": null")), (None, false));
+    }
+
+    #[test]
+    fn reports_parse_error_without_backup() {
+        let path = temp_config_path("nobackup");
+        std::fs::write(&path, "not json").unwrap();
+        let err = read_config_from(&path).err().unwrap();
+        assert!(err.starts_with("Failed to parse config"), "{}", err);
+    }
+}
@@ -0,0 +1,120 @@
+//! Claude Code の認証情報 (`~/.claude/.credentials.json`) の読み込み。
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Credentials {
+    claude_ai_oauth: OAuthCredentials,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OAuthCredentials {
+    access_token: String,
+    #[allow(dead_code)]
+    refresh_token: String,
+    expires_at: u64,
+}
+
+pub fn credentials_path() -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or_else(|| "Could not find home directory".to_string())?;
+    Ok(home.join(".claude").join(".credentials.json"))
+}
+
+pub struct TokenInfo {
+    pub access_token: String,
+    pub expires_at: u64,
+}
+
+/// `get_token_info` の戻り値。アクセストークン自体は含めない。
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenStatus {
+    /// 有効期限 (UNIX エポックからのミリ秒)
+    pub expires_at: u64,
+    pub remaining_secs: i64,
+    pub expired: bool,
+    /// 読み込んだ認証情報ファイルのパス
+    pub source: String,
+}
+
+impl TokenStatus {
+    pub fn new(token: &TokenInfo, source: String) -> Self {
+        Self {
+            expires_at: token.expires_at,
+            remaining_secs: token_remaining_secs(token.expires_at),
+            expired: is_token_expired(token.expires_at),
+            source,
+        }
+    }
+}
+
+#[tracing::instrument(name = "read_token")]
+pub fn read_token_info() -> Result<TokenInfo, String> {
+    let path = credentials_path()?;
+    let content =
+        std::fs::read_to_string(&path).map_err(|e| format!("Failed to read credentials: {}", e))?;
+    parse_token_info(&content)
+}
+
+fn parse_token_info(content: &str) -> Result<TokenInfo, String> {
+    let creds: Credentials =
+        serde_json::from_str(content).map_err(|e| format!("Failed to parse credentials: {}", e))?;
+    Ok(TokenInfo {
+        access_token: creds.claude_ai_oauth.access_token,
+        expires_at: creds.claude_ai_oauth.expires_at,
+    })
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+pub fn token_remaining_secs(expires_at: u64) -> i64 {
+    (expires_at as i64 - now_ms() as i64) / 1000
+}
+
+pub fn is_token_expired(expires_at: u64) -> bool {
+    now_ms() + 30_000 >= expires_at
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_claude_code_credentials() {
+        let content = r#"{
+            "claudeAiOauth": {
+                "accessToken": "sk-ant-oat01-test",
+                "refreshToken": "sk-ant-ort01-test",
+                "expiresAt": 1767225600000,
+                "scopes": ["user:inference"]
+            }
+        }"#;
+        let token = parse_token_info(content).unwrap();
+        assert_eq!(token.access_token, "sk-ant-oat01-test");
+        assert_eq!(token.expires_at, 1_767_225_600_000);
+    }
+
+    #[test]
+    fn rejects_credentials_without_oauth_section() {
+        let err = parse_token_info(r#"{"apiKey": "sk-ant-api03"}"#)
+            .err()
+            .unwrap();
+        assert!(err.starts_with("Failed to parse credentials"), "{}", err);
+    }
+
+    #[test]
+    fn token_expires_thirty_seconds_early() {
+        let now = now_ms();
+        assert!(is_token_expired(now + 10_000));
+        assert!(!is_token_expired(now + 120_000));
+        assert!((58..=60).contains(&token_remaining_secs(now + 60_000)));
+    }
+}
@@ -0,0 +1,226 @@
+//! `usage-dashboard://` スキームのディープリンク。
+//!
+//! スクリプトやランチャー、通知のアクションからアプリを操作できるようにする。
+//!
+//! - `usage-dashboard://refresh` — 即時更新
+//! - `usage-dashboard://show` / `hide` — メインウィンドウの表示・非表示
+//! - `usage-dashboard://overlay` — コンパクトオーバーレイの表示切り替え
+//! - `usage-dashboard://settings/<section>` — 設定メニューの該当セクションを開く
+//! - `usage-dashboard://snooze/<minutes>` — しきい値の通知をスヌーズする (`0` で解除)
+//!
+//! リンクは Web ページなどからも開けるため、ウィジェットを隠す・通知を止めるリンクは
+//! 確認のダイアログで許可された場合のみ実行する。
+
+use std::sync::Arc;
+use tauri::{Emitter, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::i18n::{self, Locale, Text};
+use crate::PollingControl;
+
+pub const SCHEME: &str = "usage-dashboard";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Refresh,
+    Show,
+    Hide,
+    Overlay,
+    /// 空文字列の場合はメニューの先頭を開く
+    Settings(String),
+    /// スヌーズする分数。0 で解除
+    Snooze(u32),
+}
+
+impl Action {
+    /// 実行する前に確認が必要か。気付かないうちに通知や表示が止まらないようにする
+    fn needs_confirmation(&self) -> bool {
+        matches!(self, Self::Hide) || matches!(self, Self::Snooze(minutes) if *minutes > 0)
+    }
+
+    fn confirmation(&self, locale: Locale) -> String {
+        match (self, locale) {
+            (Self::Snooze(minutes), Locale::En) => format!(
+                "A link requested to snooze usage alerts for {} minutes. Allow it?",
+                minutes
+            ),
+            (Self::Snooze(minutes), Locale::Ja) => format!(
+                "リンクから使用量の通知を {} 分間スヌーズするよう求められました。許可しますか?",
+                minutes
+            ),
+            (_, Locale::En) => "A link requested to hide the widget. Allow it?".to_string(),
+            (_, Locale::Ja) => {
+                "リンクからウィジェットを隠すよう求められました。許可しますか?".to_string()
+            }
+        }
+    }
+}
+
+pub fn parse(url: &str) -> Result<Action, String> {
+    let rest = url
+        .strip_prefix(SCHEME)
+        .and_then(|r| r.strip_prefix("://"))
+        .ok_or_else(|| format!("Not a {}:// link: {}", SCHEME, url))?;
+    let path = rest
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .trim_matches('/');
+
+    let mut segments = path.split('/');
+    let action = segments.next().unwrap_or_default().to_ascii_lowercase();
+    let section = segments.next();
+    match (action.as_str(), section) {
+        ("refresh", None) => Ok(Action::Refresh),
+        ("show", None) => Ok(Action::Show),
+        ("hide", None) => Ok(Action::Hide),
+        ("overlay", None) => Ok(Action::Overlay),
+        ("snooze", Some(minutes)) if segments.next().is_none() => minutes
+            .parse()
+            .map(Action::Snooze)
+            .map_err(|_| format!("Invalid snooze duration: {}", url)),
+        ("settings", section) if segments.next().is_none() => Ok(Action::Settings(
+            section.unwrap_or_default().to_ascii_lowercase(),
+        )),
+        _ => Err(format!("Unknown deep link: {}", url)),
+    }
+}
+
+fn show_main(app: &tauri::AppHandle) {
+    if let Some(w) = app.get_webview_window("main") {
+        let _ = w.unminimize();
+        let _ = w.show();
+        let _ = w.set_focus();
+        crate::set_window_visibility(app, "main", true);
+    }
+}
+
+/// 2 つ目のインスタンスが起動されたとき。ディープリンクは deep-link プラグイン経由で
+/// `handle` に届くため、リンクを伴わない通常の起動の場合のみウィンドウを前面に出す
+pub fn on_second_instance(app: &tauri::AppHandle, argv: &[String]) {
+    let prefix = format!("{}://", SCHEME);
+    if !argv.iter().any(|a| a.starts_with(&prefix)) {
+        show_main(app);
+    }
+}
+
+/// ディープリンクを解釈して実行する。不正なリンクはログに残して無視する
+pub fn handle(app: &tauri::AppHandle, url: &str) {
+    let action = match parse(url) {
+        Ok(action) => action,
+        Err(e) => {
+            tracing::warn!("{}", e);
+            return;
+        }
+    };
+    tracing::info!(?action, "Deep link");
+
+    if !action.needs_confirmation() {
+        run(app, action);
+        return;
+    }
+    let locale = i18n::current();
+    let (allow, deny) = match locale {
+        Locale::En => ("Allow", "Cancel"),
+        Locale::Ja => ("許可", "キャンセル"),
+    };
+    let handle = app.clone();
+    app.dialog()
+        .message(action.confirmation(locale))
+        .title(i18n::text(locale, Text::AppTitle))
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            allow.to_string(),
+            deny.to_string(),
+        ))
+        .show(move |allowed| {
+            if allowed {
+                run(&handle, action);
+            } else {
+                tracing::info!("Deep link declined");
+            }
+        });
+}
+
+fn run(app: &tauri::AppHandle, action: Action) {
+    match action {
+        Action::Refresh => {
+            app.state::<Arc<PollingControl>>()
+                .refresh_notify
+                .notify_one();
+        }
+        Action::Show => show_main(app),
+        Action::Hide => crate::hide_main_window(app),
+        Action::Overlay => {
+            if let Err(e) = crate::toggle_overlay(app) {
+                tracing::error!("Overlay error: {}", e);
+            }
+        }
+        Action::Settings(section) => {
+            show_main(app);
+            let _ = app.emit_to("main", "open-settings", section);
+        }
+        Action::Snooze(minutes) => {
+            if let Err(e) = crate::alerts::snooze(app, minutes) {
+                tracing::warn!("Snooze error: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_valid_links() {
+        assert_eq!(parse("usage-dashboard://refresh"), Ok(Action::Refresh));
+        assert_eq!(parse("usage-dashboard://show/"), Ok(Action::Show));
+        assert_eq!(parse("usage-dashboard://HIDE"), Ok(Action::Hide));
+        assert_eq!(
+            parse("usage-dashboard://overlay?from=launcher"),
+            Ok(Action::Overlay)
+        );
+        assert_eq!(
+            parse("usage-dashboard://settings"),
+            Ok(Action::Settings(String::new()))
+        );
+        assert_eq!(
+            parse("usage-dashboard://settings/Quiet-Hours#top"),
+            Ok(Action::Settings("quiet-hours".to_string()))
+        );
+        assert_eq!(parse("usage-dashboard://snooze/30"), Ok(Action::Snooze(30)));
+        assert_eq!(parse("usage-dashboard://snooze/0"), Ok(Action::Snooze(0)));
+    }
+
+    #[test]
+    fn rejects_invalid_links() {
+        for url in [
+            "https://example.com/refresh",
+            "usage-dashboard:refresh",
+            "usage-dashboard://",
+            "usage-dashboard://quit",
+            "usage-dashboard://refresh/now",
+            "usage-dashboard://settings/alerts/extra",
+            "usage-dashboard://snooze",
+            "usage-dashboard://snooze/-5",
+            "usage-dashboard://snooze/soon",
+            "usage-dashboard://snooze/30/extra",
+        ] {
+            assert!(parse(url).is_err(), "{}", url);
+        }
+    }
+
+    #[test]
+    fn asks_before_hiding_or_snoozing() {
+        assert!(Action::Hide.needs_confirmation());
+        assert!(Action::Snooze(30).needs_confirmation());
+        assert!(!Action::Snooze(0).needs_confirmation());
+        assert!(!Action::Refresh.needs_confirmation());
+        assert!(!Action::Show.needs_confirmation());
+        assert!(!Action::Settings("alerts".to_string()).needs_confirmation());
+        assert!(Action::Snooze(30)
+            .confirmation(Locale::En)
+            .contains("30 minutes"));
+    }
+}
@@ -0,0 +1,140 @@
+//! `--demo` で起動した場合の合成データ。
+//!
+//! Claude Code の認証情報や GitHub のトークンがなくても UI を確認できるよう、実際の
+//! プロバイダーの代わりに時刻から決まる使用量を返す。各メーターは数分〜数十分の周期で
+//! 0% から増えてはリセットされ、取得の数回に 1 回は失敗してエラー表示も確認できる。
+
+use chrono::{DateTime, Duration, Utc};
+use std::collections::BTreeMap;
+use std::f64::consts::TAU;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use crate::{CopilotUsageData, CopilotUsageItem, ExtraUsage, UsageData, UsageMeter};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static FETCH_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// 5 時間枠・週間枠を 1 周するのにかける時間 (秒)
+const SESSION_CYCLE_SECS: i64 = 10 * 60;
+const WEEKLY_CYCLE_SECS: i64 = 60 * 60;
+const COPILOT_CYCLE_SECS: i64 = 30 * 60;
+/// この回数に 1 回は取得を失敗させる
+const ERROR_EVERY: u64 = 7;
+
+const COPILOT_MONTHLY_LIMIT: f64 = 300.0;
+const EXTRA_USAGE_MONTHLY_LIMIT: f64 = 5000.0;
+
+/// `--demo` または環境変数 `USAGE_DASHBOARD_DEMO=1` が指定されているか
+pub fn is_requested(args: &[String]) -> bool {
+    args.iter().any(|a| a == "--demo")
+        || std::env::var("USAGE_DASHBOARD_DEMO").is_ok_and(|v| v == "1")
+}
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::SeqCst);
+    tracing::info!("Demo mode enabled, using synthetic usage data");
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+/// 周期内の経過割合 (0.0〜1.0)
+fn progress(now: DateTime<Utc>, cycle_secs: i64) -> f64 {
+    now.timestamp().rem_euclid(cycle_secs) as f64 / cycle_secs as f64
+}
+
+/// 周期の始めに 0% から増え、小さく揺らぎながら `peak` に近づく使用率
+fn ramp(progress: f64, peak: f64, phase: f64) -> f64 {
+    let wobble = (progress * TAU * 3.0 + phase).sin() * 3.0;
+    let utilization = (progress * peak + wobble).clamp(0.0, 100.0);
+    (utilization * 10.0).round() / 10.0
+}
+
+/// 周期を実際の枠の長さ (`window`) に引き伸ばしたリセット時刻を持つメーター。
+/// ウィジェットの経過時間のバーが使用率と同じ速さで進む
+fn meter(
+    now: DateTime<Utc>,
+    cycle_secs: i64,
+    window: Duration,
+    peak: f64,
+    phase: f64,
+) -> UsageMeter {
+    let progress = progress(now, cycle_secs);
+    let remaining_secs = ((1.0 - progress) * window.num_seconds() as f64) as i64;
+    UsageMeter {
+        utilization: ramp(progress, peak, phase),
+        resets_at: Some((now + Duration::seconds(remaining_secs)).to_rfc3339()),
+    }
+}
+
+/// Claude の使用量の代わり。`ERROR_EVERY` 回に 1 回はエラーを返す
+pub fn usage(now: DateTime<Utc>) -> Result<UsageData, String> {
+    let count = FETCH_COUNT.fetch_add(1, Ordering::SeqCst);
+    if count % ERROR_EVERY == ERROR_EVERY - 1 {
+        return Err("Simulated API error (demo mode)".to_string());
+    }
+    Ok(sample_usage(now))
+}
+
+/// `now` から決まる使用量。取得回数を数えないので、テストのデータにも使える
+pub fn sample_usage(now: DateTime<Utc>) -> UsageData {
+    let week = Duration::days(7);
+    let extra_utilization = ramp(progress(now, WEEKLY_CYCLE_SECS), 60.0, 0.5);
+    UsageData {
+        five_hour: meter(now, SESSION_CYCLE_SECS, Duration::hours(5), 110.0, 0.0),
+        seven_day: meter(now, WEEKLY_CYCLE_SECS, week, 90.0, 1.0),
+        seven_day_oauth_apps: None,
+        seven_day_opus: Some(meter(now, WEEKLY_CYCLE_SECS, week, 55.0, 2.0)),
+        seven_day_sonnet: Some(meter(now, WEEKLY_CYCLE_SECS, week, 35.0, 3.0)),
+        seven_day_cowork: None,
+        iguana_necktie: None,
+        extra_usage: Some(ExtraUsage {
+            is_enabled: true,
+            monthly_limit: EXTRA_USAGE_MONTHLY_LIMIT,
+            used_credits: (EXTRA_USAGE_MONTHLY_LIMIT * extra_utilization / 100.0).round(),
+            utilization: extra_utilization,
+            estimated_cost: None,
+        }),
+        extra_meters: BTreeMap::new(),
+        unknown_fields: BTreeMap::new(),
+    }
+}
+
+/// テスト用の送信データ。Copilot や取得時刻などの付加情報は含めない
+#[cfg(test)]
+pub fn sample_data(now: DateTime<Utc>) -> crate::CombinedUsageData {
+    crate::CombinedUsageData {
+        claude: sample_usage(now),
+        copilot: None,
+        fetched_at: None,
+        reset_times: BTreeMap::new(),
+        budget: None,
+        rate_limit: None,
+        copilot_needs_reauth: false,
+        stale: None,
+    }
+}
+
+/// Copilot の使用量の代わり。月間上限を一時的に超える周期で増減する
+pub fn copilot(now: DateTime<Utc>) -> CopilotUsageData {
+    let wave = (progress(now, COPILOT_CYCLE_SECS) * TAU).sin();
+    let total_requests = (COPILOT_MONTHLY_LIMIT * (0.7 + 0.5 * wave)).round();
+    let sonnet = (total_requests * 0.6).round();
+    CopilotUsageData {
+        total_requests,
+        monthly_limit: COPILOT_MONTHLY_LIMIT,
+        utilization: total_requests / COPILOT_MONTHLY_LIMIT * 100.0,
+        resets_at: crate::calculate_next_month_reset(),
+        items: vec![
+            CopilotUsageItem {
+                model: "Claude Sonnet 4".to_string(),
+                gross_quantity: sonnet,
+            },
+            CopilotUsageItem {
+                model: "GPT-4.1".to_string(),
+                gross_quantity: total_requests - sonnet,
+            },
+        ],
+    }
+}
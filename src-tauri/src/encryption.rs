@@ -0,0 +1,168 @@
+//! `config.json` と履歴の保存時の暗号化。
+//!
+//! 共有 PC 向けのオプション。AES-256-GCM の鍵は OS のキーリング (Keychain /
+//! Credential Manager / Secret Service) に保存し、起動時にそこから読むので
+//! 操作は不要。暗号化した内容は `udenc1:` で始まる 1 行のテキストにするため、
+//! 履歴は行ごとに暗号化したまま追記できる。読み込み時は接頭辞で判定するので、
+//! 平文と暗号文が混在していても読める。
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::sync::Mutex;
+
+const PREFIX: &str = "udenc1:";
+const KEY_LEN: usize = 32;
+const KEYRING_SERVICE: &str = "usage-dashboard";
+const KEYRING_USER: &str = "data-encryption-key";
+
+type Key = [u8; KEY_LEN];
+
+/// キーリングから読んだ鍵。毎回キーリングに問い合わせないよう保持する
+static KEY: Mutex<Option<Key>> = Mutex::new(None);
+
+pub fn is_encrypted(text: &str) -> bool {
+    text.trim_start().starts_with(PREFIX)
+}
+
+fn cipher(key: &Key) -> Result<LessSafeKey, String> {
+    UnboundKey::new(&AES_256_GCM, key)
+        .map(LessSafeKey::new)
+        .map_err(|_| "Invalid encryption key".to_string())
+}
+
+fn seal_with(key: &Key, plaintext: &str) -> Result<String, String> {
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| "Failed to generate nonce".to_string())?;
+    let mut in_out = plaintext.as_bytes().to_vec();
+    cipher(key)?
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::empty(),
+            &mut in_out,
+        )
+        .map_err(|_| "Failed to encrypt".to_string())?;
+
+    let mut out = nonce.to_vec();
+    out.extend_from_slice(&in_out);
+    Ok(format!("{}{}", PREFIX, BASE64.encode(out)))
+}
+
+fn open_with(key: &Key, text: &str) -> Result<String, String> {
+    let encoded = text
+        .trim()
+        .strip_prefix(PREFIX)
+        .ok_or_else(|| "Not encrypted".to_string())?;
+    let mut data = BASE64
+        .decode(encoded)
+        .map_err(|e| format!("Failed to decode encrypted data: {}", e))?;
+    if data.len() < NONCE_LEN {
+        return Err("Encrypted data is truncated".to_string());
+    }
+    let mut in_out = data.split_off(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(&data).map_err(|_| "Invalid nonce".to_string())?;
+    let plaintext = cipher(key)?
+        .open_in_place(nonce, Aad::empty(), &mut in_out)
+        .map_err(|_| "Failed to decrypt (wrong key or corrupted data)".to_string())?;
+    String::from_utf8(plaintext.to_vec()).map_err(|e| format!("Invalid decrypted data: {}", e))
+}
+
+fn keyring_entry() -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)
+        .map_err(|e| format!("Failed to access keyring: {}", e))
+}
+
+fn decode_key(encoded: &str) -> Result<Key, String> {
+    BASE64
+        .decode(encoded)
+        .ok()
+        .and_then(|bytes| Key::try_from(bytes.as_slice()).ok())
+        .ok_or_else(|| "Encryption key in keyring is invalid".to_string())
+}
+
+/// キーリングの鍵を返す。`create` の場合は無ければ新しく作って保存する
+fn load_key(create: bool) -> Result<Key, String> {
+    let mut cached = KEY.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(key) = *cached {
+        return Ok(key);
+    }
+
+    let entry = keyring_entry()?;
+    let key = match entry.get_password() {
+        Ok(encoded) => decode_key(&encoded)?,
+        Err(keyring::Error::NoEntry) if create => {
+            let mut key = [0u8; KEY_LEN];
+            SystemRandom::new()
+                .fill(&mut key)
+                .map_err(|_| "Failed to generate encryption key".to_string())?;
+            entry
+                .set_password(&BASE64.encode(key))
+                .map_err(|e| format!("Failed to save encryption key: {}", e))?;
+            key
+        }
+        Err(keyring::Error::NoEntry) => {
+            return Err("Encryption key not found in keyring".to_string())
+        }
+        Err(e) => return Err(format!("Failed to read encryption key: {}", e)),
+    };
+    *cached = Some(key);
+    Ok(key)
+}
+
+/// キーリングに鍵を用意する。暗号化を有効にする前に呼ぶ
+pub fn ensure_key() -> Result<(), String> {
+    load_key(true).map(|_| ())
+}
+
+/// `encrypt` の場合はキーリングの鍵で暗号化し、そうでなければそのまま返す
+pub fn encode(plaintext: &str, encrypt: bool) -> Result<String, String> {
+    if encrypt {
+        seal_with(&load_key(true)?, plaintext)
+    } else {
+        Ok(plaintext.to_string())
+    }
+}
+
+/// 暗号化されていればキーリングの鍵で復号し、平文ならそのまま返す
+pub fn decode(text: &str) -> Result<String, String> {
+    if is_encrypted(text) {
+        open_with(&load_key(false)?, text)
+    } else {
+        Ok(text.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_and_rejects_tampering() {
+        let key = [7u8; KEY_LEN];
+        let sealed = seal_with(&key, "{\"history\":true}").unwrap();
+        assert!(is_encrypted(&sealed));
+        assert!(!sealed.contains("history"));
+        assert_eq!(open_with(&key, &sealed).unwrap(), "{\"history\":true}");
+
+        // 同じ内容でも毎回異なる暗号文になる
+        assert_ne!(seal_with(&key, "{\"history\":true}").unwrap(), sealed);
+
+        assert!(open_with(&[8u8; KEY_LEN], &sealed).is_err());
+        let mut tampered = sealed.clone();
+        tampered.replace_range(PREFIX.len()..PREFIX.len() + 4, "AAAA");
+        assert!(open_with(&key, &tampered).is_err());
+    }
+
+    #[test]
+    fn passes_plaintext_through() {
+        assert!(!is_encrypted("{\"locale\":\"ja\"}"));
+        assert_eq!(
+            decode("{\"locale\":\"ja\"}").unwrap(),
+            "{\"locale\":\"ja\"}"
+        );
+        assert_eq!(encode("plain", false).unwrap(), "plain");
+    }
+}
@@ -0,0 +1,117 @@
+//! GitHub の認証情報の解決。
+//!
+//! `gh auth login` 済みであれば `gh auth token` のトークンを使い、PAT を設定しなくても
+//! Copilot の使用量を取得できるようにする。gh CLI が使えない場合や、そのトークンで
+//! 取得に失敗した場合は `AppConfig.github` の PAT を使う。
+
+use serde::Serialize;
+use std::sync::Mutex;
+
+use crate::GitHubConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GitHubAuthSource {
+    GhCli,
+    Config,
+}
+
+#[derive(Debug, Clone)]
+pub struct GitHubCredentials {
+    pub config: GitHubConfig,
+    pub source: GitHubAuthSource,
+}
+
+/// gh CLI のトークンとそれに対応するユーザー名。毎回 `/user` を問い合わせないよう保持する
+static GH_LOGIN: Mutex<Option<(String, String)>> = Mutex::new(None);
+
+async fn gh_cli_token() -> Option<String> {
+    let mut command = tokio::process::Command::new("gh");
+    command.args(["auth", "token", "--hostname", "github.com"]);
+    #[cfg(windows)]
+    {
+        // コンソールウィンドウを表示しない (CREATE_NO_WINDOW)
+        command.creation_flags(0x0800_0000);
+    }
+
+    let output = command.output().await.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let token = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (!token.is_empty()).then_some(token)
+}
+
+async fn fetch_login(client: &reqwest::Client, token: &str) -> Result<String, String> {
+    let resp = client
+        .get("https://api.github.com/user")
+        .header("Authorization", format!("token {}", token))
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", "tauri-usage-dashboard")
+        .send()
+        .await
+        .map_err(|e| format!("GitHub API request failed: {}", e.without_url()))?;
+
+    if !resp.status().is_success() {
+        return Err(format!("GitHub API status {}", resp.status()));
+    }
+
+    let body: serde_json::Value = resp
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse GitHub user: {}", e))?;
+    body["login"]
+        .as_str()
+        .map(String::from)
+        .ok_or_else(|| "Missing login in GitHub user".to_string())
+}
+
+async fn gh_cli_credentials(client: &reqwest::Client) -> Option<(String, String)> {
+    let token = gh_cli_token().await?;
+
+    let cached = GH_LOGIN.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    if let Some((cached_token, login)) = cached {
+        if cached_token == token {
+            return Some((login, token));
+        }
+    }
+
+    match fetch_login(client, &token).await {
+        Ok(login) => {
+            *GH_LOGIN.lock().unwrap_or_else(|e| e.into_inner()) =
+                Some((token.clone(), login.clone()));
+            Some((login, token))
+        }
+        Err(e) => {
+            tracing::warn!("gh CLI token rejected: {}", e);
+            None
+        }
+    }
+}
+
+/// 試す順に並べた GitHub の認証情報。gh CLI、設定の PAT の順。
+/// 上限 (`monthly_limit`) は設定の値を共通で使う。
+pub async fn candidates(
+    client: &reqwest::Client,
+    config: Option<&GitHubConfig>,
+) -> Vec<GitHubCredentials> {
+    let mut candidates = Vec::new();
+    if let Some((username, token)) = gh_cli_credentials(client).await {
+        candidates.push(GitHubCredentials {
+            config: GitHubConfig {
+                username,
+                token,
+                monthly_limit: config.and_then(|c| c.monthly_limit),
+                monthly_limit_override: config.is_some_and(|c| c.monthly_limit_override),
+            },
+            source: GitHubAuthSource::GhCli,
+        });
+    }
+    if let Some(config) = config.filter(|c| !c.token.is_empty()) {
+        candidates.push(GitHubCredentials {
+            config: config.clone(),
+            source: GitHubAuthSource::Config,
+        });
+    }
+    candidates
+}
@@ -0,0 +1,115 @@
+//! ポーリングタスク・認証情報ファイルの監視・各プロバイダーの状態の記録。
+//!
+//! データが更新されなくなった原因を設定画面から確認できるよう、`get_health` で返す。
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::rate_limit::RateLimitInfo;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provider {
+    Claude,
+    Copilot,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProviderHealth {
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_failure_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    /// 最後の応答に含まれていたレート制限ヘッダー
+    pub rate_limit: Option<RateLimitInfo>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PollerHealth {
+    pub running: bool,
+    pub last_error: Option<String>,
+    /// 次にポーリングする予定時刻。停止中 (オフライン・非表示時の一時停止) は `None`
+    pub next_fire_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum WatcherHealth {
+    #[default]
+    Starting,
+    Active {
+        path: String,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HealthReport {
+    pub polling: PollerHealth,
+    pub credentials_watcher: WatcherHealth,
+    pub claude: ProviderHealth,
+    pub copilot: ProviderHealth,
+}
+
+#[derive(Default)]
+pub struct HealthState {
+    report: std::sync::Mutex<HealthReport>,
+}
+
+impl HealthState {
+    fn update(&self, f: impl FnOnce(&mut HealthReport)) {
+        let mut report = self.report.lock().unwrap_or_else(|e| e.into_inner());
+        f(&mut report);
+    }
+
+    pub fn report(&self) -> HealthReport {
+        self.report
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn provider(report: &mut HealthReport, provider: Provider) -> &mut ProviderHealth {
+        match provider {
+            Provider::Claude => &mut report.claude,
+            Provider::Copilot => &mut report.copilot,
+        }
+    }
+
+    pub fn record_success(&self, provider: Provider) {
+        self.update(|r| Self::provider(r, provider).last_success_at = Some(Utc::now()));
+    }
+
+    pub fn record_failure(&self, provider: Provider, error: &str) {
+        self.update(|r| {
+            let health = Self::provider(r, provider);
+            health.last_failure_at = Some(Utc::now());
+            health.last_error = Some(error.to_string());
+        });
+    }
+
+    pub fn set_rate_limit(&self, provider: Provider, rate_limit: Option<RateLimitInfo>) {
+        self.update(|r| Self::provider(r, provider).rate_limit = rate_limit);
+    }
+
+    pub fn set_polling_running(&self) {
+        self.update(|r| r.polling.running = true);
+    }
+
+    /// ポーリングタスクが終了した (パニックを含む) ことを記録する。
+    pub fn set_polling_stopped(&self, error: String) {
+        self.update(|r| {
+            r.polling.running = false;
+            r.polling.last_error = Some(error);
+            r.polling.next_fire_at = None;
+        });
+    }
+
+    pub fn set_next_fire(&self, next_fire_at: Option<DateTime<Utc>>) {
+        self.update(|r| r.polling.next_fire_at = next_fire_at);
+    }
+
+    pub fn set_watcher(&self, watcher: WatcherHealth) {
+        self.update(|r| r.credentials_watcher = watcher);
+    }
+}
@@ -0,0 +1,386 @@
+//! 使用量の履歴 (`~/.usage-dashboard/history.jsonl`) の記録と書き出し。
+//!
+//! 取得に成功するたびに 1 行 1 サンプルの JSON Lines 形式で追記する。
+//! 暗号化が有効な場合は行ごとに暗号化する ([`crate::encryption`])。
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{encryption, CombinedUsageData, UsageMeter};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistorySample {
+    pub timestamp: DateTime<Utc>,
+    pub five_hour: Option<f64>,
+    pub seven_day: Option<f64>,
+    #[serde(default)]
+    pub seven_day_opus: Option<f64>,
+    #[serde(default)]
+    pub seven_day_sonnet: Option<f64>,
+    #[serde(default)]
+    pub seven_day_oauth_apps: Option<f64>,
+    #[serde(default)]
+    pub seven_day_cowork: Option<f64>,
+    #[serde(default)]
+    pub copilot_requests: Option<f64>,
+    #[serde(default)]
+    pub copilot_utilization: Option<f64>,
+    #[serde(default)]
+    pub extra_usage_credits: Option<f64>,
+    #[serde(default)]
+    pub extra_usage_utilization: Option<f64>,
+    /// 月間予算に対する利用額 (USD)
+    #[serde(default)]
+    pub budget_spent: Option<f64>,
+    #[serde(default)]
+    pub budget_utilization: Option<f64>,
+}
+
+impl HistorySample {
+    pub fn new(timestamp: DateTime<Utc>, data: &CombinedUsageData) -> Self {
+        let utilization = |m: &Option<UsageMeter>| m.as_ref().map(|m| m.utilization);
+        let claude = &data.claude;
+        let extra = claude.extra_usage.as_ref().filter(|e| e.is_enabled);
+        Self {
+            timestamp,
+            five_hour: Some(claude.five_hour.utilization),
+            seven_day: Some(claude.seven_day.utilization),
+            seven_day_opus: utilization(&claude.seven_day_opus),
+            seven_day_sonnet: utilization(&claude.seven_day_sonnet),
+            seven_day_oauth_apps: utilization(&claude.seven_day_oauth_apps),
+            seven_day_cowork: utilization(&claude.seven_day_cowork),
+            copilot_requests: data.copilot.as_ref().map(|c| c.total_requests),
+            copilot_utilization: data.copilot.as_ref().map(|c| c.utilization),
+            extra_usage_credits: extra.map(|e| e.used_credits),
+            extra_usage_utilization: extra.map(|e| e.utilization),
+            budget_spent: data.budget.as_ref().map(|b| b.spent),
+            budget_utilization: data.budget.as_ref().map(|b| b.utilization),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelPoint {
+    pub timestamp: DateTime<Utc>,
+    pub utilization: f64,
+}
+
+/// モデル別の週間メーター 1 つ分の推移
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelSeries {
+    pub meter: &'static str,
+    pub points: Vec<ModelPoint>,
+    pub latest: f64,
+    pub peak: f64,
+    /// 期間中に増えた使用率の合計。週間リセットで下がった分は差し引かない
+    pub consumed: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelBreakdown {
+    pub since: Option<DateTime<Utc>>,
+    /// 記録のないメーターは含めない
+    pub models: Vec<ModelSeries>,
+}
+
+/// 記録からメーターの値を取り出す関数
+type MeterValue = fn(&HistorySample) -> Option<f64>;
+
+/// Opus / Sonnet などモデル別の週間メーターの推移を集計する。
+pub fn model_breakdown(samples: &[HistorySample], since: Option<DateTime<Utc>>) -> ModelBreakdown {
+    let meters: [(&'static str, MeterValue); 4] = [
+        ("seven_day_opus", |s| s.seven_day_opus),
+        ("seven_day_sonnet", |s| s.seven_day_sonnet),
+        ("seven_day_oauth_apps", |s| s.seven_day_oauth_apps),
+        ("seven_day_cowork", |s| s.seven_day_cowork),
+    ];
+
+    let models = meters
+        .into_iter()
+        .filter_map(|(meter, value)| {
+            let points: Vec<ModelPoint> = samples
+                .iter()
+                .filter_map(|s| {
+                    value(s).map(|utilization| ModelPoint {
+                        timestamp: s.timestamp,
+                        utilization,
+                    })
+                })
+                .collect();
+            let latest = points.last()?.utilization;
+            let peak = points.iter().map(|p| p.utilization).fold(latest, f64::max);
+            let consumed = points
+                .windows(2)
+                .map(|w| (w[1].utilization - w[0].utilization).max(0.0))
+                .sum();
+            Some(ModelSeries {
+                meter,
+                points,
+                latest,
+                peak,
+                consumed,
+            })
+        })
+        .collect();
+
+    ModelBreakdown { since, models }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn parse(format: &str) -> Result<Self, String> {
+        match format {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("Unknown export format: {}", format)),
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// "24h" / "7d" / "30d" / "all" のような期間指定を解釈する。`all` は `None`。
+pub fn parse_range(range: &str) -> Result<Option<Duration>, String> {
+    if range == "all" {
+        return Ok(None);
+    }
+
+    let invalid = || format!("Invalid range: {}", range);
+    let (value, unit) = match range.char_indices().last() {
+        Some((i, _)) => range.split_at(i),
+        None => return Err(invalid()),
+    };
+    let value: i64 = value.parse().map_err(|_| invalid())?;
+    if value <= 0 {
+        return Err(invalid());
+    }
+
+    match unit {
+        "h" => Ok(Some(Duration::hours(value))),
+        "d" => Ok(Some(Duration::days(value))),
+        _ => Err(invalid()),
+    }
+}
+
+fn history_path() -> Result<PathBuf, String> {
+    Ok(crate::config_dir()?.join("history.jsonl"))
+}
+
+fn encode_line(sample: &HistorySample, encrypt: bool) -> Result<String, String> {
+    let line = serde_json::to_string(sample)
+        .map_err(|e| format!("Failed to serialize history sample: {}", e))?;
+    encryption::encode(&line, encrypt)
+}
+
+pub fn append_sample(sample: &HistorySample, encrypt: bool) -> Result<(), String> {
+    let path = history_path()?;
+    let line = encode_line(sample, encrypt)?;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to open history: {}", e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("Failed to write history: {}", e))
+}
+
+/// `since` 以降のサンプルを古い順に返す。JSON として壊れた行は読み飛ばす。
+/// 読み込めない・復号できない行 (鍵が違う・キーリングから読めない) がある場合はエラーにし、
+/// `prune` がそれらの行を捨てて書き直さないようにする。
+pub fn read_samples(since: Option<DateTime<Utc>>) -> Result<Vec<HistorySample>, String> {
+    read_samples_from(&history_path()?, since)
+}
+
+fn read_samples_from(
+    path: &Path,
+    since: Option<DateTime<Utc>>,
+) -> Result<Vec<HistorySample>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let file = std::fs::File::open(path).map_err(|e| format!("Failed to open history: {}", e))?;
+    let mut samples = Vec::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| format!("Failed to read history: {}", e))?;
+        let line = encryption::decode(&line)
+            .map_err(|e| format!("Failed to decrypt history (line {}): {}", i + 1, e))?;
+        let Ok(sample) = serde_json::from_str::<HistorySample>(&line) else {
+            continue;
+        };
+        if since.is_none_or(|since| sample.timestamp >= since) {
+            samples.push(sample);
+        }
+    }
+    Ok(samples)
+}
+
+/// 保持期間を過ぎたサンプルを削除する。残りは `encrypt` に合わせて書き直す。
+/// 復号できない行がある場合は書き直さずにエラーを返す。
+pub fn prune(retention_days: u32, encrypt: bool) -> Result<(), String> {
+    prune_at(&history_path()?, retention_days, encrypt)
+}
+
+fn prune_at(path: &Path, retention_days: u32, encrypt: bool) -> Result<(), String> {
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let cutoff = Utc::now() - Duration::days(i64::from(retention_days));
+    let samples = read_samples_from(path, Some(cutoff))?;
+    let mut content = String::new();
+    for sample in &samples {
+        content.push_str(&encode_line(sample, encrypt)?);
+        content.push('\n');
+    }
+    crate::write_atomic(path, &content)
+}
+
+pub fn render(samples: &[HistorySample], format: ExportFormat) -> Result<String, String> {
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(samples)
+            .map_err(|e| format!("Failed to serialize history: {}", e)),
+        ExportFormat::Csv => Ok(to_csv(samples)),
+    }
+}
+
+fn to_csv(samples: &[HistorySample]) -> String {
+    let cell = |v: Option<f64>| v.map(|v| v.to_string()).unwrap_or_default();
+
+    let mut out = String::from(
+        "timestamp,five_hour,seven_day,seven_day_opus,seven_day_sonnet,\
+         seven_day_oauth_apps,seven_day_cowork,copilot_requests,copilot_utilization,\
+         extra_usage_credits,extra_usage_utilization,budget_spent,budget_utilization\n",
+    );
+    for s in samples {
+        let row = [
+            s.timestamp.to_rfc3339(),
+            cell(s.five_hour),
+            cell(s.seven_day),
+            cell(s.seven_day_opus),
+            cell(s.seven_day_sonnet),
+            cell(s.seven_day_oauth_apps),
+            cell(s.seven_day_cowork),
+            cell(s.copilot_requests),
+            cell(s.copilot_utilization),
+            cell(s.extra_usage_credits),
+            cell(s.extra_usage_utilization),
+            cell(s.budget_spent),
+            cell(s.budget_utilization),
+        ];
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(minutes: i64, opus: Option<f64>) -> HistorySample {
+        let timestamp = DateTime::parse_from_rfc3339("2025-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+            + Duration::minutes(minutes);
+        serde_json::from_value(serde_json::json!({
+            "timestamp": timestamp,
+            "five_hour": 12.5,
+            "seven_day": 40.0,
+            "seven_day_opus": opus,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn parses_ranges() {
+        assert_eq!(parse_range("24h"), Ok(Some(Duration::hours(24))));
+        assert_eq!(parse_range("7d"), Ok(Some(Duration::days(7))));
+        assert_eq!(parse_range("all"), Ok(None));
+        for invalid in ["", "h", "0d", "-1d", "5m", "d7"] {
+            assert!(parse_range(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn writes_csv_with_empty_cells_for_missing_meters() {
+        let csv = to_csv(&[sample(0, Some(3.0)), sample(5, None)]);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("timestamp,five_hour,seven_day,seven_day_opus,"));
+        assert_eq!(lines[1], "2025-01-01T00:00:00+00:00,12.5,40,3,,,,,,,,,");
+        assert_eq!(lines[2], "2025-01-01T00:05:00+00:00,12.5,40,,,,,,,,,,");
+    }
+
+    #[test]
+    fn summarizes_model_meters_without_counting_resets() {
+        let samples = [
+            sample(0, Some(10.0)),
+            sample(5, Some(30.0)),
+            sample(10, None),
+            // 週間リセットで下がった分は差し引かない
+            sample(15, Some(5.0)),
+            sample(20, Some(15.0)),
+        ];
+        let breakdown = model_breakdown(&samples, None);
+
+        assert_eq!(breakdown.models.len(), 1);
+        let opus = &breakdown.models[0];
+        assert_eq!(opus.meter, "seven_day_opus");
+        assert_eq!(opus.points.len(), 4);
+        assert_eq!(opus.latest, 15.0);
+        assert_eq!(opus.peak, 30.0);
+        assert_eq!(opus.consumed, 30.0);
+    }
+
+    #[test]
+    fn round_trips_plaintext_lines() {
+        let original = sample(0, Some(3.0));
+        let line = encode_line(&original, false).unwrap();
+        assert!(!line.contains('\n'));
+
+        let decoded: HistorySample =
+            serde_json::from_str(&encryption::decode(&line).unwrap()).unwrap();
+        assert_eq!(decoded.timestamp, original.timestamp);
+        assert_eq!(decoded.seven_day_opus, Some(3.0));
+        assert_eq!(decoded.copilot_requests, None);
+    }
+
+    #[test]
+    fn keeps_history_that_cannot_be_decrypted() {
+        let dir =
+            std::env::temp_dir().join(format!("usage-dashboard-history-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("history.jsonl");
+        let content = format!(
+            "{}\nnot json\nudenc1:AAAA\n",
+            encode_line(&sample(0, None), false).unwrap()
+        );
+        std::fs::write(&path, &content).unwrap();
+
+        let err = read_samples_from(&path, None).err().unwrap();
+        assert!(
+            err.starts_with("Failed to decrypt history (line 3)"),
+            "{}",
+            err
+        );
+        assert!(prune_at(&path, 30, false).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), content);
+
+        // 壊れた JSON の行だけなら読み飛ばす
+        std::fs::write(&path, &content[..content.len() - "udenc1:AAAA\n".len()]).unwrap();
+        assert_eq!(read_samples_from(&path, None).unwrap().len(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
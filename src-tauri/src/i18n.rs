@@ -0,0 +1,81 @@
+//! バックエンドが生成するユーザー向けの文字列 (通知・トレイ・リセット時刻の表記) の言語切り替え。
+//!
+//! `AppConfig.locale` が未設定の場合は OS のロケールから選ぶ。対応していない言語は英語にする。
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    En,
+    Ja,
+}
+
+impl Locale {
+    /// "ja" / "ja-JP" / "ja_JP.UTF-8" のような表記を解釈する
+    pub fn parse(tag: &str) -> Option<Self> {
+        let language = tag.split(['-', '_', '.']).next()?.to_ascii_lowercase();
+        match language.as_str() {
+            "en" => Some(Self::En),
+            "ja" => Some(Self::Ja),
+            _ => None,
+        }
+    }
+
+    pub fn system() -> Self {
+        sys_locale::get_locale()
+            .and_then(|tag| Self::parse(&tag))
+            .unwrap_or(Self::En)
+    }
+}
+
+/// 設定またはシステムのロケール
+pub fn current() -> Locale {
+    crate::read_app_config()
+        .ok()
+        .and_then(|c| c.locale)
+        .and_then(|tag| Locale::parse(&tag))
+        .unwrap_or_else(Locale::system)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Text {
+    AppTitle,
+    TrayShowHide,
+    TrayShowHideOverlay,
+    TrayShowPopup,
+    TraySnooze,
+    TraySnooze30,
+    TraySnooze60,
+    TraySnooze240,
+    TraySnoozeOff,
+    TrayQuit,
+    NotificationSnooze,
+}
+
+pub fn text(locale: Locale, text: Text) -> &'static str {
+    match (locale, text) {
+        (Locale::En, Text::AppTitle) => "Claude Code Usage",
+        (Locale::Ja, Text::AppTitle) => "Claude Code 使用量",
+        (Locale::En, Text::TrayShowHide) => "Show/Hide",
+        (Locale::Ja, Text::TrayShowHide) => "表示/非表示",
+        (Locale::En, Text::TrayShowHideOverlay) => "Show/Hide Overlay",
+        (Locale::Ja, Text::TrayShowHideOverlay) => "オーバーレイの表示/非表示",
+        (Locale::En, Text::TrayShowPopup) => "Show Popup",
+        (Locale::Ja, Text::TrayShowPopup) => "ポップアップを表示",
+        (Locale::En, Text::TraySnooze) => "Snooze Alerts",
+        (Locale::Ja, Text::TraySnooze) => "通知をスヌーズ",
+        (Locale::En, Text::TraySnooze30) => "30 minutes",
+        (Locale::Ja, Text::TraySnooze30) => "30 分",
+        (Locale::En, Text::TraySnooze60) => "1 hour",
+        (Locale::Ja, Text::TraySnooze60) => "1 時間",
+        (Locale::En, Text::TraySnooze240) => "4 hours",
+        (Locale::Ja, Text::TraySnooze240) => "4 時間",
+        (Locale::En, Text::TraySnoozeOff) => "Resume",
+        (Locale::Ja, Text::TraySnoozeOff) => "再開",
+        (Locale::En, Text::TrayQuit) => "Quit",
+        (Locale::Ja, Text::TrayQuit) => "終了",
+        (Locale::En, Text::NotificationSnooze) => "Snooze 1 hour",
+        (Locale::Ja, Text::NotificationSnooze) => "1 時間スヌーズ",
+    }
+}
@@ -0,0 +1,110 @@
+//! ユーザーの操作がない時間 (アイドル時間) の取得。
+//!
+//! 常時起動している PC で席を外している間はポーリング間隔を延ばすために使う。
+//! トレイのアイコンは固定 (`tray::build` で一度だけ作る) なので、アイドル中に
+//! 描き直しを止める必要はない。
+//! 判定できない環境では `None` を返し、アイドル中とはみなさない。
+
+/// 最後のキーボード・マウス入力からの経過秒数
+#[cfg(target_os = "windows")]
+pub fn idle_secs() -> Option<u64> {
+    use windows_sys::Win32::System::SystemInformation::GetTickCount;
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+    let mut info = LASTINPUTINFO {
+        cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+        dwTime: 0,
+    };
+    // SAFETY: LASTINPUTINFO is a plain C struct with cbSize set as the API requires.
+    if unsafe { GetLastInputInfo(&mut info) } == 0 {
+        return None;
+    }
+    let now = unsafe { GetTickCount() };
+    Some(u64::from(now.wrapping_sub(info.dwTime)) / 1000)
+}
+
+#[cfg(target_os = "macos")]
+pub fn idle_secs() -> Option<u64> {
+    let output = std::process::Command::new("ioreg")
+        .args(["-c", "IOHIDSystem", "-d", "4"])
+        .output()
+        .ok()?;
+    parse_hid_idle_time(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// `ioreg` の出力の `"HIDIdleTime" = <ナノ秒>` を秒に変換する
+#[cfg(any(target_os = "macos", test))]
+fn parse_hid_idle_time(output: &str) -> Option<u64> {
+    let line = output.lines().find(|l| l.contains("\"HIDIdleTime\""))?;
+    let nanos: u64 = line.rsplit('=').next()?.trim().parse().ok()?;
+    Some(nanos / 1_000_000_000)
+}
+
+/// X11 では `xprintidle`、GNOME (Wayland を含む) では Mutter の IdleMonitor を使う
+#[cfg(target_os = "linux")]
+pub fn idle_secs() -> Option<u64> {
+    let run = |program: &str, args: &[&str]| {
+        std::process::Command::new(program)
+            .args(args)
+            .output()
+            .ok()
+            .filter(|o| o.status.success())
+            .map(|o| String::from_utf8_lossy(&o.stdout).into_owned())
+    };
+
+    if let Some(ms) = run("xprintidle", &[]).and_then(|s| s.trim().parse::<u64>().ok()) {
+        return Some(ms / 1000);
+    }
+    run(
+        "gdbus",
+        &[
+            "call",
+            "--session",
+            "--dest",
+            "org.gnome.Mutter.IdleMonitor",
+            "--object-path",
+            "/org/gnome/Mutter/IdleMonitor/Core",
+            "--method",
+            "org.gnome.Mutter.IdleMonitor.GetIdletime",
+        ],
+    )
+    .and_then(|s| parse_mutter_idle_time(&s))
+}
+
+/// `gdbus` の出力 `(uint64 12345,)` (ミリ秒) を秒に変換する
+#[cfg(any(target_os = "linux", test))]
+fn parse_mutter_idle_time(output: &str) -> Option<u64> {
+    let ms: u64 = output
+        .trim()
+        .trim_start_matches('(')
+        .trim_end_matches(')')
+        .trim_end_matches(',')
+        .strip_prefix("uint64 ")?
+        .parse()
+        .ok()?;
+    Some(ms / 1000)
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+pub fn idle_secs() -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ioreg_idle_time() {
+        let output = r#"    | |   "HIDIdleTime" = 125000000000
+    | |   "HIDParameters" = {}"#;
+        assert_eq!(parse_hid_idle_time(output), Some(125));
+        assert_eq!(parse_hid_idle_time("no idle time"), None);
+    }
+
+    #[test]
+    fn parses_mutter_idle_time() {
+        assert_eq!(parse_mutter_idle_time("(uint64 61500,)\n"), Some(61));
+        assert_eq!(parse_mutter_idle_time("Error: not found"), None);
+    }
+}
@@ -1,350 +1,1541 @@
+mod admin;
+mod alerts;
+mod api;
+mod cli;
+mod config;
+mod credentials;
+mod deep_link;
+mod demo;
+mod encryption;
+mod github_auth;
+mod health;
+mod history;
+mod i18n;
+mod idle;
+mod logging;
+mod milestones;
+mod polling;
+mod popup;
+mod power;
+mod providers;
+mod rate_limit;
+mod reset_time;
+mod share_image;
+mod snap;
+mod sound;
+mod sparkline;
+mod tray;
+
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::sync::mpsc as std_mpsc;
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
-use tauri::menu::{MenuBuilder, MenuItemBuilder};
-use tauri::tray::TrayIconBuilder;
 use tauri::{Emitter, Manager};
 use tauri_plugin_autostart::ManagerExt;
-use tokio::sync::{watch, Mutex, Notify};
+use tauri_plugin_dialog::DialogExt;
+use tauri_plugin_opener::OpenerExt;
+use tauri_plugin_updater::UpdaterExt;
+use tokio::sync::Mutex;
 use tokio::time::Duration;
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct Credentials {
-    claude_ai_oauth: OAuthCredentials,
+use config::{
+    config_dir, generate_api_token, read_app_config, replace_app_config_and_backup,
+    write_app_config, write_atomic,
+    AdminApiConfig, AlertConfig, ApiConfig, AppConfig, BatterySaverConfig, BudgetConfig,
+    DumpConfig, EncryptionConfig, GitHubConfig, HistoryConfig, IdleConfig, OverlayConfig,
+    QuietHoursConfig, SnapConfig, SoundConfig,
+};
+use credentials::{credentials_path, is_token_expired, read_token_info, TokenStatus};
+use polling::{is_data_stale, on_battery_power, PollingControl};
+use providers::claude::{fetch_usage, ExtraUsage, UsageData, UsageMeter};
+use providers::copilot::{
+    calculate_next_month_reset, copilot_monthly_limit, fetch_copilot_with_fallback,
+    CopilotAuthError, CopilotError, CopilotPlanInfo, CopilotUsageData, CopilotUsageItem,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CombinedUsageData {
+    claude: UsageData,
+    #[serde(default)]
+    copilot: Option<CopilotUsageData>,
+    /// Claude の使用量を取得した時刻
+    #[serde(default)]
+    fetched_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// メーター ID ごとのリセット時刻 (システムのタイムゾーンで整形済み)
+    #[serde(default)]
+    reset_times: BTreeMap<String, reset_time::ResetTime>,
+    /// 月間予算を設定している場合のみ
+    #[serde(default)]
+    budget: Option<BudgetUsage>,
+    /// 使用量 API の応答に含まれていたレート制限ヘッダー
+    #[serde(default)]
+    rate_limit: Option<rate_limit::RateLimitInfo>,
+    /// GitHub のトークンが拒否された (401 / 403)。PAT の再設定や `gh auth refresh` が必要
+    #[serde(default)]
+    copilot_needs_reauth: bool,
+    /// 取得に失敗したため、最後に取得できたデータをそのまま送っている場合のみ
+    #[serde(default)]
+    stale: Option<StaleUsage>,
+}
+
+/// 最後に取得できたデータを送り直している理由。起動直後に前回終了時のデータを
+/// 表示している間は `RESTORED_REASON` と起動した時刻
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct StaleUsage {
+    reason: String,
+    failed_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// 月間予算に対する各プロバイダーの利用額 (USD)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct BudgetUsage {
+    monthly_budget: f64,
+    spent: f64,
+    utilization: f64,
+    /// Claude の超過利用 (extra usage) の見積もり額
+    claude_extra_usage: f64,
+    /// Copilot の月間上限を超えたプレミアムリクエストの料金
+    copilot_overage: f64,
+    resets_at: String,
+}
+
+/// 超過利用の見積もり額を `extra_usage.estimated_cost` に設定する
+fn apply_extra_usage_cost(usage: &mut UsageData, app_config: &AppConfig) {
+    if let Some(extra) = usage.extra_usage.as_mut() {
+        extra.estimated_cost = Some(extra.used_credits * app_config.extra_usage.credit_value);
+    }
+}
+
+fn calculate_budget_usage(
+    claude: &UsageData,
+    copilot: Option<&CopilotUsageData>,
+    app_config: &AppConfig,
+) -> Option<BudgetUsage> {
+    let config = &app_config.budget;
+    let monthly_budget = config.monthly_budget.filter(|b| *b > 0.0)?;
+
+    let claude_extra_usage = claude
+        .extra_usage
+        .as_ref()
+        .filter(|e| e.is_enabled)
+        .map(|e| e.used_credits * app_config.extra_usage.credit_value)
+        .unwrap_or(0.0);
+    let copilot_overage = copilot
+        .map(|c| (c.total_requests - c.monthly_limit).max(0.0) * config.copilot_overage_price)
+        .unwrap_or(0.0);
+    let spent = claude_extra_usage + copilot_overage;
+
+    Some(BudgetUsage {
+        monthly_budget,
+        spent,
+        utilization: spent / monthly_budget * 100.0,
+        claude_extra_usage,
+        copilot_overage,
+        resets_at: calculate_next_month_reset(),
+    })
+}
+
+/// 前回終了時の表示をすぐに復元するため、最後に取得したデータを保存する形式
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredSnapshot {
+    fetched_at: chrono::DateTime<chrono::Utc>,
+    data: CombinedUsageData,
+}
+
+/// `get_usage` の戻り値。`stale` は `usage-update` と同じく、最後に取得できたデータ
+/// (起動直後は前回終了時のデータ) を表示している理由と時刻。
+#[derive(Debug, Clone, Serialize)]
+struct CachedUsage {
+    #[serde(flatten)]
+    usage: UsageData,
+    copilot: Option<CopilotUsageData>,
+    fetched_at: Option<chrono::DateTime<chrono::Utc>>,
+    reset_times: BTreeMap<String, reset_time::ResetTime>,
+    budget: Option<BudgetUsage>,
+    rate_limit: Option<rate_limit::RateLimitInfo>,
+    stale: Option<StaleUsage>,
+}
+
+/// `get_last_fetch_info` の戻り値
+#[derive(Debug, Clone, Serialize)]
+struct LastFetchInfo {
+    last_success_at: Option<chrono::DateTime<chrono::Utc>>,
+    last_attempt_at: Option<chrono::DateTime<chrono::Utc>>,
+    last_error: Option<String>,
+    /// 最後の成功からの経過秒数
+    age_secs: Option<i64>,
+    stale: bool,
+}
+
+/// `heartbeat` イベントのペイロード。取得に成功するたびに送り、
+/// データに変化がなく `usage-update` を省略した場合もポーリングが動いていることを伝える。
+#[derive(Debug, Clone, Serialize)]
+struct HeartbeatEvent {
+    fetched_at: chrono::DateTime<chrono::Utc>,
+    changed: bool,
+}
+
+/// `data-stale` イベントのペイロード
+#[derive(Debug, Clone, Serialize)]
+struct DataStaleEvent {
+    fetched_at: Option<chrono::DateTime<chrono::Utc>>,
+    age_secs: Option<i64>,
+    last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum BackgroundEffectResult {
+    Applied,
+    Unsupported { reason: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct UpdateInfo {
+    version: String,
+    current_version: String,
+    notes: Option<String>,
+}
+
+struct AppState {
+    latest_usage: Option<UsageData>,
+    latest_copilot: Option<CopilotUsageData>,
+    last_fetched_at: Option<chrono::DateTime<chrono::Utc>>,
+    last_attempt_at: Option<chrono::DateTime<chrono::Utc>>,
+    last_fetch_error: Option<String>,
+    usage_stale: bool,
+    /// 最後に `usage-update` で送ったデータ。変化がない場合は送信を省略する
+    last_emitted_usage: Option<CombinedUsageData>,
+    /// 判定済みの Copilot のプラン。GitHub の設定を保存し直すと破棄する
+    copilot_plan: Option<CopilotPlanInfo>,
+    /// 最後の Copilot の取得で GitHub に認証を拒否された場合のエラー。成功すると破棄する
+    copilot_auth_error: Option<CopilotAuthError>,
+    /// 最後に受け取った使用量 API のレート制限ヘッダー。制限中は取得を見送る
+    rate_limit: Option<rate_limit::RateLimitInfo>,
+    /// スパークライン用の直近の使用率
+    recent_samples: sparkline::RecentSamples,
+    http_client: reqwest::Client,
+    pending_update: Option<(tauri_plugin_updater::Update, Vec<u8>)>,
+    /// 更新の確認・ダウンロード中か。同時に 2 回ダウンロードしないようにする
+    update_in_flight: bool,
+}
+
+impl AppState {
+    /// 起動時の状態。前回終了時のデータがあれば、最初の取得が終わるまでそれを表示する。
+    /// 最初の取得に失敗した場合も、このデータを古いものとして送り直せる
+    fn restored(snapshot: Option<StoredSnapshot>, http_client: reqwest::Client) -> Self {
+        Self {
+            latest_usage: snapshot.as_ref().map(|s| s.data.claude.clone()),
+            latest_copilot: snapshot.as_ref().and_then(|s| s.data.copilot.clone()),
+            last_fetched_at: snapshot.as_ref().map(|s| s.fetched_at),
+            last_attempt_at: None,
+            last_fetch_error: None,
+            usage_stale: snapshot.is_some(),
+            last_emitted_usage: snapshot.map(|s| CombinedUsageData {
+                stale: Some(StaleUsage {
+                    reason: RESTORED_REASON.to_string(),
+                    failed_at: chrono::Utc::now(),
+                }),
+                ..s.data
+            }),
+            copilot_plan: None,
+            copilot_auth_error: None,
+            rate_limit: None,
+            recent_samples: sparkline::RecentSamples::default(),
+            http_client,
+            pending_update: None,
+            update_in_flight: false,
+        }
+    }
+}
+
+const UPDATE_CHECK_INTERVAL_SECS: u64 = 6 * 60 * 60;
+
+/// 通知を控える時間帯が終わったかを確認する間隔
+const QUIET_HOURS_CHECK_INTERVAL_SECS: u64 = 60;
+
+/// 有効期限のこの時間前に `token-expiring` イベントを送る
+const TOKEN_EXPIRING_WARNING_SECS: i64 = 10 * 60;
+const TOKEN_CHECK_INTERVAL_SECS: u64 = 60;
+
+/// 表示中のデータが古くなっていないかを確認する間隔
+const STALE_CHECK_INTERVAL_SECS: u64 = 30;
+
+const OVERLAY_LABEL: &str = "overlay";
+
+/// 前回終了時のデータを表示している間の `StaleUsage.reason`
+const RESTORED_REASON: &str = "Showing data saved at the last exit";
+
+const METER_IDS: &[&str] = &[
+    "five_hour",
+    "seven_day",
+    "seven_day_opus",
+    "seven_day_sonnet",
+    "seven_day_oauth_apps",
+    "seven_day_cowork",
+    "copilot",
+];
+
+fn snapshot_path() -> Result<PathBuf, String> {
+    Ok(config_dir()?.join("last_snapshot.json"))
+}
+
+fn read_snapshot() -> Result<Option<StoredSnapshot>, String> {
+    let path = snapshot_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read snapshot: {}", e))?;
+    let content = encryption::decode(&content)?;
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse snapshot: {}", e))
+}
+
+/// `encrypt` の場合は設定や履歴と同じ鍵で暗号化して保存する
+fn write_snapshot(snapshot: &StoredSnapshot, encrypt: bool) -> Result<(), String> {
+    let path = snapshot_path()?;
+    let content = serde_json::to_string(snapshot)
+        .map_err(|e| format!("Failed to serialize snapshot: {}", e))?;
+    write_atomic(&path, &encryption::encode(&content, encrypt)?)
+}
+
+fn dump_path(config: &DumpConfig) -> Result<PathBuf, String> {
+    match &config.path {
+        Some(path) if !path.trim().is_empty() => Ok(PathBuf::from(path.trim())),
+        _ => Ok(config_dir()?.join("current.json")),
+    }
+}
+
+fn write_dump(config: &DumpConfig, data: &CombinedUsageData) -> Result<(), String> {
+    let path = dump_path(config)?;
+    let content = serde_json::to_string_pretty(data)
+        .map_err(|e| format!("Failed to serialize usage: {}", e))?;
+    write_atomic(&path, &content)
+}
+
+/// 署名の公開鍵 (`plugins.updater.pubkey`) が設定されたビルドか。
+/// 設定されていない場合は更新を検証できないので、アップデーターを登録しない
+fn updater_configured(config: &tauri::Config) -> bool {
+    config
+        .plugins
+        .0
+        .get("updater")
+        .and_then(|c| c.get("pubkey"))
+        .and_then(|k| k.as_str())
+        .is_some_and(|k| !k.trim().is_empty())
+}
+
+/// 新しいバージョンを確認し、見つかった場合はダウンロードまで済ませておく。
+/// インストールは `install_update` でユーザーが再起動を選んだ時点で行う。
+/// 定期確認と `check_for_updates` が重なった場合は後から来た方をエラーにする。
+async fn check_and_download_update(app: &tauri::AppHandle) -> Result<Option<UpdateInfo>, String> {
+    if !updater_configured(app.config()) {
+        return Err("Updates are not configured in this build".to_string());
+    }
+    let state = app.state::<Arc<Mutex<AppState>>>();
+    {
+        let mut s = state.lock().await;
+        if s.update_in_flight {
+            return Err("An update check is already in progress".to_string());
+        }
+        s.update_in_flight = true;
+    }
+    let result = download_update(app).await;
+    state.lock().await.update_in_flight = false;
+    result
+}
+
+async fn download_update(app: &tauri::AppHandle) -> Result<Option<UpdateInfo>, String> {
+    let update = app
+        .updater()
+        .map_err(|e| format!("Failed to initialize updater: {}", e))?
+        .check()
+        .await
+        .map_err(|e| format!("Update check failed: {}", e))?;
+
+    let Some(update) = update else {
+        return Ok(None);
+    };
+
+    let info = UpdateInfo {
+        version: update.version.clone(),
+        current_version: update.current_version.clone(),
+        notes: update.body.clone(),
+    };
+
+    {
+        let state = app.state::<Arc<Mutex<AppState>>>();
+        let s = state.lock().await;
+        if let Some((pending, _)) = &s.pending_update {
+            if pending.version == update.version {
+                return Ok(Some(info));
+            }
+        }
+    }
+
+    let _ = app.emit("update-available", &info);
+
+    let bytes = update
+        .download(|_, _| {}, || {})
+        .await
+        .map_err(|e| format!("Failed to download update: {}", e))?;
+
+    {
+        let state = app.state::<Arc<Mutex<AppState>>>();
+        let mut s = state.lock().await;
+        s.pending_update = Some((update, bytes));
+    }
+
+    let _ = app.emit("update-downloaded", &info);
+    Ok(Some(info))
+}
+
+fn apply_overlay_config(
+    window: &tauri::WebviewWindow,
+    config: &OverlayConfig,
+) -> Result<(), String> {
+    window
+        .set_ignore_cursor_events(config.click_through)
+        .map_err(|e| format!("Failed to set click-through: {}", e))?;
+    window
+        .emit_to(OVERLAY_LABEL, "overlay-config", config)
+        .map_err(|e| format!("Failed to send overlay config: {}", e))
+}
+
+/// メインウィジェットと同じ見た目 (枠なし・透過・常に最前面) の補助ウィンドウを生成する。
+fn build_frameless_window(
+    app: &tauri::AppHandle,
+    label: &str,
+    page: &str,
+    title: &str,
+    (width, height): (f64, f64),
+    resizable: bool,
+) -> Result<tauri::WebviewWindow, String> {
+    tauri::WebviewWindowBuilder::new(app, label, tauri::WebviewUrl::App(page.into()))
+        .title(title)
+        .inner_size(width, height)
+        .resizable(resizable)
+        .decorations(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .shadow(false)
+        .transparent(true)
+        .build()
+        .map_err(|e| format!("Failed to create window '{}': {}", label, e))
+}
+
+/// オーバーレイウィンドウを表示する。初回はウィンドウを生成する。
+fn open_overlay(app: &tauri::AppHandle) -> Result<(), String> {
+    let window = match app.get_webview_window(OVERLAY_LABEL) {
+        Some(w) => w,
+        None => build_frameless_window(
+            app,
+            OVERLAY_LABEL,
+            "overlay.html",
+            "Claude Code Usage Overlay",
+            (200.0, 44.0),
+            false,
+        )?,
+    };
+
+    window
+        .show()
+        .map_err(|e| format!("Failed to show overlay: {}", e))?;
+
+    set_window_visibility(app, OVERLAY_LABEL, true);
+
+    let config = read_app_config().unwrap_or_default();
+    apply_overlay_config(&window, &config.overlay)
+}
+
+fn close_overlay(app: &tauri::AppHandle) -> Result<(), String> {
+    if let Some(w) = app.get_webview_window(OVERLAY_LABEL) {
+        w.hide()
+            .map_err(|e| format!("Failed to hide overlay: {}", e))?;
+    }
+    set_window_visibility(app, OVERLAY_LABEL, false);
+    Ok(())
+}
+
+/// メインウィンドウを隠す。トレイアイコンがない環境では戻せなくなるため、
+/// 代わりに最小化してタスクバーから戻せるようにする
+fn hide_main_window(app: &tauri::AppHandle) {
+    let Some(w) = app.get_webview_window("main") else {
+        return;
+    };
+    if tray::is_available() {
+        let _ = w.hide();
+    } else {
+        let _ = w.minimize();
+    }
+    set_window_visibility(app, "main", false);
+}
+
+/// ウィンドウの表示状態を記録し、いずれかのウィンドウ (メイン・オーバーレイ・
+/// 切り離したメーター) が表示されているかをポーリングループに通知する。
+fn set_window_visibility(app: &tauri::AppHandle, label: &str, visible: bool) {
+    let control = app.state::<Arc<PollingControl>>();
+    let any_visible = {
+        let mut windows = control
+            .visible_windows
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        if visible {
+            windows.insert(label.to_string());
+        } else {
+            windows.remove(label);
+        }
+        !windows.is_empty()
+    };
+    control.visible_tx.send_if_modified(|v| {
+        let changed = *v != any_visible;
+        *v = any_visible;
+        changed
+    });
+}
+
+fn toggle_overlay(app: &tauri::AppHandle) -> Result<(), String> {
+    let visible = app
+        .get_webview_window(OVERLAY_LABEL)
+        .and_then(|w| w.is_visible().ok())
+        .unwrap_or(false);
+    if visible {
+        close_overlay(app)
+    } else {
+        open_overlay(app)
+    }
+}
+
+fn meter_window_label(meter_id: &str) -> String {
+    format!("meter-{}", meter_id)
+}
+
+#[tauri::command]
+async fn get_usage(
+    state: tauri::State<'_, Arc<Mutex<AppState>>>,
+) -> Result<CachedUsage, String> {
+    let state = state.lock().await;
+    let usage = state
+        .latest_usage
+        .clone()
+        .ok_or_else(|| "No usage data available yet".to_string())?;
+    let reset_times = reset_time::for_usage(
+        &usage,
+        state.latest_copilot.as_ref(),
+        chrono::Local::now(),
+        i18n::current(),
+    );
+    let budget = calculate_budget_usage(
+        &usage,
+        state.latest_copilot.as_ref(),
+        &read_app_config().unwrap_or_default(),
+    );
+    Ok(CachedUsage {
+        usage,
+        copilot: state.latest_copilot.clone(),
+        fetched_at: state.last_fetched_at,
+        reset_times,
+        budget,
+        rate_limit: state.rate_limit.clone(),
+        stale: state
+            .last_emitted_usage
+            .as_ref()
+            .and_then(|d| d.stale.clone()),
+    })
+}
+
+/// UTC の RFC3339 文字列のリセット時刻をシステムのタイムゾーンで整形する。
+#[tauri::command]
+fn format_reset_time(resets_at: String) -> Result<reset_time::ResetTime, String> {
+    reset_time::format(&resets_at, chrono::Local::now(), i18n::current())
+}
+
+/// macOS の NSVisualEffectMaterial に対応するエフェクト名。
+/// Windows 向けの "mica" / "acrylic" も近い見た目のマテリアルに割り当てる。
+#[cfg(target_os = "macos")]
+fn macos_material(effect: &str) -> Option<window_vibrancy::NSVisualEffectMaterial> {
+    use window_vibrancy::NSVisualEffectMaterial;
+
+    match effect {
+        "mica" | "under_window" => Some(NSVisualEffectMaterial::UnderWindowBackground),
+        "acrylic" | "hud" => Some(NSVisualEffectMaterial::HudWindow),
+        "sidebar" => Some(NSVisualEffectMaterial::Sidebar),
+        "popover" => Some(NSVisualEffectMaterial::Popover),
+        "menu" => Some(NSVisualEffectMaterial::Menu),
+        "window" => Some(NSVisualEffectMaterial::WindowBackground),
+        _ => None,
+    }
+}
+
+/// Linux ではウィンドウを透過させた上で、KWin (X11) の場合のみ
+/// `_KDE_NET_WM_BLUR_BEHIND_REGION` プロパティでぼかしを要求する。
+/// Wayland のセッション (KWin を含む) ではこのプロパティが使えず、KWin の
+/// Wayland 用のぼかしプロトコルにも未対応のため `Unsupported` を返す。
+#[cfg(target_os = "linux")]
+fn apply_linux_effect(
+    window: &tauri::WebviewWindow,
+    effect: &str,
+) -> Result<BackgroundEffectResult, String> {
+    use gtk::gdk;
+    use gtk::prelude::WidgetExt;
+
+    if !matches!(effect, "transparent" | "mica" | "acrylic") {
+        return Err(format!("Unknown effect: {}", effect));
+    }
+
+    window
+        .set_background_color(Some(tauri::window::Color(0, 0, 0, 0)))
+        .map_err(|e| format!("Failed to enable transparency: {}", e))?;
+
+    let gdk_window = window
+        .gtk_window()
+        .map_err(|e| format!("Failed to get GTK window: {}", e))?
+        .window()
+        .ok_or("GTK window is not realized")?;
+
+    let blur_region = gdk::Atom::intern("_KDE_NET_WM_BLUR_BEHIND_REGION");
+    if effect == "transparent" {
+        gdk::property_delete(&gdk_window, &blur_region);
+        return Ok(BackgroundEffectResult::Applied);
+    }
+
+    if std::env::var_os("WAYLAND_DISPLAY").is_some()
+        && std::env::var("GDK_BACKEND").as_deref() != Ok("x11")
+    {
+        return Ok(BackgroundEffectResult::Unsupported {
+            reason: "Blur is only supported on KDE X11 sessions, not on Wayland".to_string(),
+        });
+    }
+
+    let is_kde = std::env::var("XDG_CURRENT_DESKTOP")
+        .map(|d| d.to_uppercase().contains("KDE"))
+        .unwrap_or(false);
+    if !is_kde {
+        return Ok(BackgroundEffectResult::Unsupported {
+            reason: "Blur is only supported on KDE X11 sessions (KWin)".to_string(),
+        });
+    }
+
+    // 空の領域を指定するとウィンドウ全体がぼかし対象になる
+    gdk::property_change(
+        &gdk_window,
+        &blur_region,
+        &gdk::Atom::intern("CARDINAL"),
+        32,
+        gdk::PropMode::Replace,
+        gdk::ChangeData::ULongs(&[]),
+    );
+    Ok(BackgroundEffectResult::Applied)
+}
+
+#[cfg(target_os = "windows")]
+const MACOS_ONLY_EFFECTS: &[&str] = &[
+    "under_window",
+    "hud",
+    "sidebar",
+    "popover",
+    "menu",
+    "window",
+];
+
+#[tauri::command]
+fn set_background_effect(
+    window: tauri::WebviewWindow,
+    effect: String,
+) -> Result<BackgroundEffectResult, String> {
+    #[cfg(target_os = "windows")]
+    {
+        use window_vibrancy::{apply_acrylic, apply_mica, clear_acrylic, clear_mica};
+
+        let _ = clear_mica(&window);
+        let _ = clear_acrylic(&window);
+
+        let result = match effect.as_str() {
+            "transparent" => Ok(()),
+            "mica" => apply_mica(&window, Some(true))
+                .map_err(|e| format!("Failed to apply mica: {}", e)),
+            "acrylic" => apply_acrylic(&window, Some((18, 18, 18, 200)))
+                .map_err(|e| format!("Failed to apply acrylic: {}", e)),
+            e if MACOS_ONLY_EFFECTS.contains(&e) => {
+                Err(format!("Effect '{}' is only supported on macOS", effect))
+            }
+            _ => Err(format!("Unknown effect: {}", effect)),
+        };
+        result.map(|()| BackgroundEffectResult::Applied)
+    }
+    #[cfg(target_os = "macos")]
+    {
+        use window_vibrancy::{apply_vibrancy, clear_vibrancy, NSVisualEffectState};
+
+        let _ = clear_vibrancy(&window);
+
+        if effect == "transparent" {
+            return Ok(BackgroundEffectResult::Applied);
+        }
+        let material =
+            macos_material(&effect).ok_or_else(|| format!("Unknown effect: {}", effect))?;
+        apply_vibrancy(
+            &window,
+            material,
+            Some(NSVisualEffectState::Active),
+            Some(8.0),
+        )
+        .map(|()| BackgroundEffectResult::Applied)
+        .map_err(|e| format!("Failed to apply vibrancy: {}", e))
+    }
+    #[cfg(target_os = "linux")]
+    {
+        apply_linux_effect(&window, &effect)
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    {
+        let _ = (window, effect);
+        Ok(BackgroundEffectResult::Unsupported {
+            reason: "Background effects are not supported on this platform".to_string(),
+        })
+    }
+}
+
+#[tauri::command]
+fn set_always_on_top(window: tauri::WebviewWindow, enabled: bool) -> Result<(), String> {
+    window
+        .set_always_on_top(enabled)
+        .map_err(|e| format!("Failed to set always on top: {}", e))
+}
+
+#[tauri::command]
+async fn get_last_fetch_info(
+    state: tauri::State<'_, Arc<Mutex<AppState>>>,
+    control: tauri::State<'_, Arc<PollingControl>>,
+) -> Result<LastFetchInfo, String> {
+    let interval = *control.interval_tx.borrow();
+    let now = chrono::Utc::now();
+    let s = state.lock().await;
+    Ok(LastFetchInfo {
+        last_success_at: s.last_fetched_at,
+        last_attempt_at: s.last_attempt_at,
+        last_error: s.last_fetch_error.clone(),
+        age_secs: s.last_fetched_at.map(|t| (now - t).num_seconds()),
+        stale: is_data_stale(&s, interval, now),
+    })
+}
+
+/// ポーリング・認証情報の監視・各プロバイダーの状態を返す。
+/// データが更新されなくなった原因を設定画面で表示するために使う。
+#[tauri::command]
+async fn get_health(
+    health: tauri::State<'_, health::HealthState>,
+    state: tauri::State<'_, Arc<Mutex<AppState>>>,
+) -> Result<health::HealthReport, String> {
+    let mut report = health.report();
+    if report.polling.running {
+        report.polling.last_error = state.lock().await.last_fetch_error.clone();
+    }
+    Ok(report)
+}
+
+#[tauri::command]
+fn get_token_info() -> Result<TokenStatus, String> {
+    let token = read_token_info()?;
+    let source = credentials_path()?.display().to_string();
+    Ok(TokenStatus::new(&token, source))
+}
+
+/// `--demo` で起動し、合成データを表示しているか
+#[tauri::command]
+fn is_demo_mode() -> bool {
+    demo::is_enabled()
+}
+
+#[tauri::command]
+fn force_refresh(control: tauri::State<'_, Arc<PollingControl>>) -> Result<(), String> {
+    control.refresh_notify.notify_one();
+    Ok(())
+}
+
+/// 指定したプロバイダー (`claude` / `copilot`) だけをすぐに取得する。
+/// もう一方のプロバイダーは前回の値を使い、バックオフなどの状態も変えない
+#[tauri::command]
+fn refresh_provider(
+    control: tauri::State<'_, Arc<PollingControl>>,
+    id: String,
+) -> Result<(), String> {
+    let provider = match id.as_str() {
+        "claude" => health::Provider::Claude,
+        "copilot" => health::Provider::Copilot,
+        _ => return Err(format!("Unknown provider: {}", id)),
+    };
+    control.request_refresh(polling::Targets::only(provider));
+    Ok(())
+}
+
+#[tauri::command]
+fn set_polling_interval(
+    control: tauri::State<'_, Arc<PollingControl>>,
+    seconds: u64,
+) -> Result<(), String> {
+    if seconds < 10 || seconds > 600 {
+        return Err("Polling interval must be between 10 and 600 seconds".to_string());
+    }
+    control
+        .interval_tx
+        .send(seconds)
+        .map_err(|e| format!("Failed to set interval: {}", e))
+}
+
+#[tauri::command]
+fn set_background_polling_interval(
+    control: tauri::State<'_, Arc<PollingControl>>,
+    seconds: u64,
+) -> Result<(), String> {
+    if seconds != 0 && !(60..=3600).contains(&seconds) {
+        return Err(
+            "Background polling interval must be 0 (paused) or between 60 and 3600 seconds"
+                .to_string(),
+        );
+    }
+    control
+        .background_interval_tx
+        .send(seconds)
+        .map_err(|e| format!("Failed to set background interval: {}", e))
+}
+
+#[tauri::command]
+fn quit_app(app: tauri::AppHandle) {
+    app.exit(0);
+}
+
+#[tauri::command]
+async fn check_for_updates(app: tauri::AppHandle) -> Result<Option<UpdateInfo>, String> {
+    check_and_download_update(&app).await
+}
+
+#[tauri::command]
+async fn install_update(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Arc<Mutex<AppState>>>,
+) -> Result<(), String> {
+    let pending = state.lock().await.pending_update.take();
+    let (update, bytes) = pending.ok_or_else(|| "No downloaded update available".to_string())?;
+    update
+        .install(&bytes)
+        .map_err(|e| format!("Failed to install update: {}", e))?;
+    app.restart()
+}
+
+#[tauri::command]
+async fn show_overlay(app: tauri::AppHandle) -> Result<(), String> {
+    open_overlay(&app)
+}
+
+#[tauri::command]
+async fn hide_overlay(app: tauri::AppHandle) -> Result<(), String> {
+    close_overlay(&app)
+}
+
+/// トレイのポップアップからメインウィジェットを開く
+#[tauri::command]
+async fn show_dashboard(app: tauri::AppHandle) -> Result<(), String> {
+    popup::hide(&app)?;
+    let window = app
+        .get_webview_window("main")
+        .ok_or_else(|| "Main window not found".to_string())?;
+    window
+        .show()
+        .map_err(|e| format!("Failed to show dashboard: {}", e))?;
+    let _ = window.set_focus();
+    set_window_visibility(&app, "main", true);
+    Ok(())
+}
+
+#[tauri::command]
+async fn spawn_meter_window(app: tauri::AppHandle, meter_id: String) -> Result<(), String> {
+    if !METER_IDS.contains(&meter_id.as_str()) {
+        return Err(format!("Unknown meter: {}", meter_id));
+    }
+
+    let label = meter_window_label(&meter_id);
+    if let Some(w) = app.get_webview_window(&label) {
+        w.show()
+            .map_err(|e| format!("Failed to show meter window: {}", e))?;
+        return w
+            .set_focus()
+            .map_err(|e| format!("Failed to focus meter window: {}", e));
+    }
+
+    build_frameless_window(
+        &app,
+        &label,
+        "meter.html",
+        "Claude Code Usage Meter",
+        (240.0, 64.0),
+        true,
+    )?;
+    set_window_visibility(&app, &label, true);
+    Ok(())
+}
+
+#[tauri::command]
+fn close_meter_window(app: tauri::AppHandle, meter_id: String) -> Result<(), String> {
+    if let Some(w) = app.get_webview_window(&meter_window_label(&meter_id)) {
+        w.close()
+            .map_err(|e| format!("Failed to close meter window: {}", e))?;
+    }
+    Ok(())
+}
+
+/// スパークライン用に、メーターの直近 `count` 件の使用率を古い順に返す。
+#[tauri::command]
+async fn get_recent_samples(
+    state: tauri::State<'_, Arc<Mutex<AppState>>>,
+    meter_id: String,
+    count: usize,
+) -> Result<Vec<sparkline::RecentSample>, String> {
+    if !METER_IDS.contains(&meter_id.as_str()) {
+        return Err(format!("Unknown meter: {}", meter_id));
+    }
+    let s = state.lock().await;
+    Ok(s.recent_samples.latest(&meter_id, count))
+}
+
+/// 既知のすべてのメーターの表示名と、現在のプランで返されているかを返す。
+/// 名前を知らない新しいメーターも含めるので、UI はアカウントにあるメーターだけを描画できる
+#[tauri::command]
+async fn get_all_meters(
+    state: tauri::State<'_, Arc<Mutex<AppState>>>,
+) -> Result<Vec<alerts::MeterInfo>, String> {
+    let s = state.lock().await;
+    Ok(alerts::all_meters(
+        s.last_emitted_usage.as_ref(),
+        i18n::current(),
+    ))
+}
+
+/// `range` ("24h" / "7d" / "all" など) の期間について、モデル別の週間メーターの推移を返す。
+#[tauri::command]
+fn get_model_breakdown(range: String) -> Result<history::ModelBreakdown, String> {
+    let since = history::parse_range(&range)?.map(|d| chrono::Utc::now() - d);
+    let samples = history::read_samples(since)?;
+    Ok(history::model_breakdown(&samples, since))
+}
+
+/// 保存ダイアログでファイルを選ばせる。キャンセルされた場合は `None`。
+/// ダイアログを開いている間も非同期ランタイムのスレッドを止めないよう、コールバックの結果を待つ
+async fn ask_save_path(
+    app: &tauri::AppHandle,
+    (filter, extension): (&str, &str),
+    file_name: String,
+) -> Result<Option<PathBuf>, String> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    app.dialog()
+        .file()
+        .add_filter(filter, &[extension])
+        .set_file_name(file_name)
+        .save_file(move |file| {
+            let _ = tx.send(file);
+        });
+    match rx.await {
+        Ok(Some(file)) => file
+            .into_path()
+            .map(Some)
+            .map_err(|e| format!("Invalid export path: {}", e)),
+        _ => Ok(None),
+    }
+}
+
+/// `content` を `path` (省略した場合は保存ダイアログで選んだファイル) に書き出し、
+/// 書き出したパスを返す。ダイアログがキャンセルされた場合は `None`。
+/// 書き込みの途中で失敗しても中途半端なファイルを残さないよう、一時ファイルから置き換える
+async fn save_export(
+    app: &tauri::AppHandle,
+    path: Option<String>,
+    filter: (&str, &str),
+    file_name: String,
+    content: impl AsRef<[u8]> + Send + 'static,
+) -> Result<Option<String>, String> {
+    let path = match path {
+        Some(p) => PathBuf::from(p),
+        None => match ask_save_path(app, filter, file_name).await? {
+            Some(p) => p,
+            None => return Ok(None),
+        },
+    };
+    let display = path.display().to_string();
+    tauri::async_runtime::spawn_blocking(move || write_atomic(&path, content))
+        .await
+        .map_err(|e| format!("Export task failed: {}", e))??;
+    Ok(Some(display))
+}
+
+/// 履歴を CSV / JSON で書き出す。`path` を省略した場合は保存ダイアログで選択させ、
+/// キャンセルされた場合は `None` を返す。
+#[tauri::command]
+async fn export_history(
+    app: tauri::AppHandle,
+    format: String,
+    range: String,
+    path: Option<String>,
+) -> Result<Option<String>, String> {
+    let format = history::ExportFormat::parse(&format)?;
+    let since = history::parse_range(&range)?.map(|d| chrono::Utc::now() - d);
+
+    let content = tauri::async_runtime::spawn_blocking(move || {
+        history::read_samples(since).and_then(|samples| history::render(&samples, format))
+    })
+    .await
+    .map_err(|e| format!("Export task failed: {}", e))??;
+
+    let extension = format.extension();
+    save_export(
+        &app,
+        path,
+        (&extension.to_uppercase(), extension),
+        format!("usage-history.{}", extension),
+        content,
+    )
+    .await
+}
+
+/// 現在の使用量を PNG 画像として書き出す。`path` を省略した場合は保存ダイアログで選択させ、
+/// キャンセルされた場合は `None` を返す。
+#[tauri::command]
+async fn export_snapshot(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Arc<Mutex<AppState>>>,
+    path: Option<String>,
+) -> Result<Option<String>, String> {
+    let (data, copilot_plan) = {
+        let s = state.lock().await;
+        (s.last_emitted_usage.clone(), s.copilot_plan.clone())
+    };
+    let data = data.ok_or_else(|| "No usage data yet".to_string())?;
+    let fetched_at = data
+        .fetched_at
+        .unwrap_or_else(chrono::Utc::now)
+        .with_timezone(&chrono::Local);
+    let png = tauri::async_runtime::spawn_blocking(move || {
+        share_image::render(&data, copilot_plan.as_ref(), fetched_at)
+    })
+    .await
+    .map_err(|e| format!("Export task failed: {}", e))??;
+
+    save_export(
+        &app,
+        path,
+        ("PNG", "png"),
+        format!("usage-{}.png", fetched_at.format("%Y%m%d-%H%M")),
+        png,
+    )
+    .await
+}
+
+#[tauri::command]
+fn get_history_config() -> Result<HistoryConfig, String> {
+    Ok(read_app_config()?.history)
+}
+
+#[tauri::command]
+fn save_history_config(enabled: bool, retention_days: u32) -> Result<(), String> {
+    if !(1..=3650).contains(&retention_days) {
+        return Err("Retention must be between 1 and 3650 days".to_string());
+    }
+    let mut config = read_app_config()?;
+    config.history = HistoryConfig {
+        enabled,
+        retention_days,
+    };
+    write_app_config(&config)?;
+    history::prune(retention_days, config.encryption.enabled)
+}
+
+#[tauri::command]
+fn get_encryption_config() -> Result<EncryptionConfig, String> {
+    Ok(read_app_config()?.encryption)
+}
+
+/// 保存時の暗号化を切り替え、既存の履歴・スナップショット・設定も新しい形式で書き直す。
+/// 復号できない履歴がある場合は何も書き直さずにエラーを返す
+#[tauri::command]
+fn set_encryption_enabled(enabled: bool) -> Result<(), String> {
+    // 読めない設定を既定値で上書きしないよう、読み込みの失敗はそのまま返す
+    let mut config = read_app_config()?;
+    if enabled {
+        encryption::ensure_key()?;
+    }
+    history::prune(config.history.retention_days, enabled)?;
+    // スナップショットは表示を復元するためだけのものなので、読めなければそのままにする
+    match read_snapshot() {
+        Ok(Some(snapshot)) if !demo::is_enabled() => write_snapshot(&snapshot, enabled)?,
+        Ok(_) => {}
+        Err(e) => tracing::warn!("Snapshot error: {}", e),
+    }
+    config.encryption.enabled = enabled;
+    // 以前の形式 (平文など) のバックアップを残さないよう、バックアップも置き換える
+    replace_app_config_and_backup(&config)
+}
+
+/// `get_dump_config` の戻り値。`resolved_path` は実際の書き出し先
+#[derive(Debug, Clone, Serialize)]
+struct DumpConfigInfo {
+    #[serde(flatten)]
+    config: DumpConfig,
+    resolved_path: String,
+}
+
+#[tauri::command]
+fn get_dump_config() -> Result<DumpConfigInfo, String> {
+    let config = read_app_config()?.dump;
+    let resolved_path = dump_path(&config)?.display().to_string();
+    Ok(DumpConfigInfo {
+        config,
+        resolved_path,
+    })
+}
+
+#[tauri::command]
+fn save_dump_config(enabled: bool, path: Option<String>) -> Result<(), String> {
+    let path = path.filter(|p| !p.trim().is_empty());
+    if let Some(p) = &path {
+        let parent = PathBuf::from(p.trim())
+            .parent()
+            .map(|d| d.to_path_buf())
+            .unwrap_or_default();
+        if !parent.as_os_str().is_empty() && !parent.is_dir() {
+            return Err(format!("Directory does not exist: {}", parent.display()));
+        }
+    }
+    let mut config = read_app_config()?;
+    config.dump.enabled = enabled;
+    config.dump.path = path;
+    write_app_config(&config)
+}
+
+#[tauri::command]
+fn get_api_config() -> Result<ApiConfig, String> {
+    Ok(read_app_config()?.api)
+}
+
+/// ローカル API の有効/無効とポートを保存し、サーバーを再起動する。
+/// トークンが未生成の場合はここで生成する。
+#[tauri::command]
+fn save_api_config(
+    app: tauri::AppHandle,
+    server: tauri::State<'_, api::ApiServer>,
+    enabled: bool,
+    port: u16,
+) -> Result<ApiConfig, String> {
+    if port < 1024 {
+        return Err("Port must be between 1024 and 65535".to_string());
+    }
+    let mut config = read_app_config()?;
+    config.api.enabled = enabled;
+    config.api.port = port;
+    if config.api.token.is_empty() {
+        config.api.token = generate_api_token();
+    }
+    write_app_config(&config)?;
+    server.restart(&app, &config.api);
+    Ok(config.api)
+}
+
+/// 新しいトークンを発行する。以前のトークンは使えなくなる。
+#[tauri::command]
+fn regenerate_api_token(
+    app: tauri::AppHandle,
+    server: tauri::State<'_, api::ApiServer>,
+) -> Result<ApiConfig, String> {
+    let mut config = read_app_config()?;
+    config.api.token = generate_api_token();
+    write_app_config(&config)?;
+    server.restart(&app, &config.api);
+    Ok(config.api)
+}
+
+#[tauri::command]
+fn get_alert_config() -> Result<AlertConfig, String> {
+    Ok(read_app_config()?.alerts)
+}
+
+#[tauri::command]
+fn save_alert_config(
+    thresholds: Vec<f64>,
+    webhook_urls: Vec<String>,
+    desktop_notifications: bool,
+    notify_on_reset: bool,
+    extra_usage_thresholds: Vec<f64>,
+) -> Result<(), String> {
+    if thresholds
+        .iter()
+        .chain(&extra_usage_thresholds)
+        .any(|t| !(1.0..=100.0).contains(t))
+    {
+        return Err("Thresholds must be between 1 and 100".to_string());
+    }
+    for url in &webhook_urls {
+        alerts::validate_webhook_url(url)?;
+    }
+    let mut config = read_app_config()?;
+    config.alerts.thresholds = thresholds;
+    config.alerts.webhook_urls = webhook_urls;
+    config.alerts.desktop_notifications = desktop_notifications;
+    config.alerts.notify_on_reset = notify_on_reset;
+    config.alerts.extra_usage_thresholds = extra_usage_thresholds;
+    write_app_config(&config)
+}
+
+#[tauri::command]
+fn get_quiet_hours_config() -> Result<QuietHoursConfig, String> {
+    Ok(read_app_config()?.alerts.quiet_hours)
+}
+
+#[tauri::command]
+fn get_budget_config() -> Result<BudgetConfig, String> {
+    Ok(read_app_config()?.budget)
+}
+
+#[tauri::command]
+fn save_budget_config(
+    monthly_budget: Option<f64>,
+    copilot_overage_price: f64,
+    budget_thresholds: Vec<f64>,
+) -> Result<(), String> {
+    if monthly_budget.is_some_and(|b| b <= 0.0) {
+        return Err("Monthly budget must be greater than 0".to_string());
+    }
+    if copilot_overage_price < 0.0 {
+        return Err("Copilot overage price must not be negative".to_string());
+    }
+    if budget_thresholds.iter().any(|t| !(1.0..=100.0).contains(t)) {
+        return Err("Thresholds must be between 1 and 100".to_string());
+    }
+    let mut config = read_app_config()?;
+    config.budget.monthly_budget = monthly_budget;
+    config.budget.copilot_overage_price = copilot_overage_price;
+    config.alerts.budget_thresholds = budget_thresholds;
+    write_app_config(&config)
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct OAuthCredentials {
-    access_token: String,
-    #[allow(dead_code)]
-    refresh_token: String,
-    expires_at: u64,
+#[tauri::command]
+fn save_quiet_hours_config(
+    enabled: bool,
+    start: String,
+    end: String,
+    weekends: bool,
+) -> Result<(), String> {
+    alerts::validate_time(&start)?;
+    alerts::validate_time(&end)?;
+    let mut config = read_app_config()?;
+    config.alerts.quiet_hours = QuietHoursConfig {
+        enabled,
+        start,
+        end,
+        weekends,
+    };
+    write_app_config(&config)
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct UsageMeter {
-    utilization: f64,
-    resets_at: Option<String>,
+/// スヌーズの終了時刻。スヌーズしていなければ `None`
+#[tauri::command]
+fn get_alert_snooze() -> Result<Option<chrono::DateTime<chrono::Utc>>, String> {
+    let config = read_app_config().unwrap_or_default().alerts;
+    Ok(config
+        .snoozed_until
+        .filter(|_| alerts::is_snoozed(&config, chrono::Utc::now())))
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct ExtraUsage {
-    is_enabled: bool,
-    monthly_limit: f64,
-    used_credits: f64,
-    utilization: f64,
+/// `duration_minutes` 分の間しきい値の通知を止める。0 で解除する
+#[tauri::command]
+fn snooze_alerts(
+    app: tauri::AppHandle,
+    duration_minutes: u32,
+) -> Result<Option<chrono::DateTime<chrono::Utc>>, String> {
+    alerts::snooze(&app, duration_minutes)
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct UsageData {
-    five_hour: UsageMeter,
-    seven_day: UsageMeter,
-    #[serde(default)]
-    seven_day_oauth_apps: Option<UsageMeter>,
-    #[serde(default)]
-    seven_day_opus: Option<UsageMeter>,
-    #[serde(default)]
-    seven_day_sonnet: Option<UsageMeter>,
-    #[serde(default)]
-    seven_day_cowork: Option<UsageMeter>,
-    #[serde(default)]
-    iguana_necktie: Option<serde_json::Value>,
-    #[serde(default)]
-    extra_usage: Option<ExtraUsage>,
+/// 指定した Webhook URL にテストメッセージを送る。
+#[tauri::command]
+async fn test_webhook(
+    state: tauri::State<'_, Arc<Mutex<AppState>>>,
+    url: String,
+) -> Result<(), String> {
+    alerts::validate_webhook_url(&url)?;
+    let client = state.lock().await.http_client.clone();
+    let alert = alerts::Alert::ThresholdCrossed {
+        meter: "five_hour",
+        threshold: 80.0,
+        utilization: 80.0,
+    };
+    alerts::send_webhook(&client, &url, &alert, i18n::current()).await
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct GitHubConfig {
-    username: String,
-    token: String,
-    #[serde(default = "default_monthly_limit")]
-    monthly_limit: f64,
+#[tauri::command]
+fn get_sound_config() -> Result<SoundConfig, String> {
+    Ok(read_app_config()?.alerts.sound)
 }
 
-fn default_monthly_limit() -> f64 {
-    300.0
+#[tauri::command]
+fn save_sound_config(
+    enabled: bool,
+    on_threshold: bool,
+    on_reset: bool,
+    chime: String,
+    file: Option<String>,
+    volume: f32,
+) -> Result<(), String> {
+    let sound = SoundConfig {
+        enabled,
+        on_threshold,
+        on_reset,
+        chime,
+        file: file.filter(|f| !f.trim().is_empty()),
+        volume,
+    };
+    sound::validate(&sound)?;
+    let mut config = read_app_config()?;
+    config.alerts.sound = sound;
+    write_app_config(&config)
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct AppConfig {
-    #[serde(default)]
-    github: Option<GitHubConfig>,
-    #[serde(default)]
-    autostart_enabled: bool,
+/// 効果音に使う音声ファイルを選択させる。キャンセルされた場合は `None` を返す。
+#[tauri::command]
+async fn choose_sound_file(app: tauri::AppHandle) -> Result<Option<String>, String> {
+    let Some(file) = app
+        .dialog()
+        .file()
+        .add_filter("Audio", &["wav", "mp3", "flac", "ogg"])
+        .blocking_pick_file()
+    else {
+        return Ok(None);
+    };
+    let path = file
+        .into_path()
+        .map_err(|e| format!("Invalid sound file path: {}", e))?;
+    Ok(Some(path.display().to_string()))
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct CopilotUsageItem {
-    model: String,
-    gross_quantity: f64,
+/// 保存前の設定で効果音を試聴する。
+#[tauri::command]
+fn test_sound(chime: String, file: Option<String>, volume: f32) -> Result<(), String> {
+    let sound = SoundConfig {
+        chime,
+        file: file.filter(|f| !f.trim().is_empty()),
+        volume,
+        ..SoundConfig::default()
+    };
+    sound::validate(&sound)?;
+    sound::play(&sound);
+    Ok(())
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct CopilotUsageData {
-    total_requests: f64,
-    monthly_limit: f64,
-    utilization: f64,
-    resets_at: String,
-    items: Vec<CopilotUsageItem>,
+/// `get_locale` の戻り値
+#[derive(Debug, Clone, Serialize)]
+struct LocaleInfo {
+    /// 設定値。`None` は OS のロケールに従う
+    setting: Option<String>,
+    /// 実際に使う言語
+    resolved: i18n::Locale,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct CombinedUsageData {
-    claude: UsageData,
-    #[serde(default)]
-    copilot: Option<CopilotUsageData>,
+#[tauri::command]
+fn get_locale() -> Result<LocaleInfo, String> {
+    Ok(LocaleInfo {
+        setting: read_app_config()?.locale,
+        resolved: i18n::current(),
+    })
 }
 
-struct AppState {
-    latest_usage: Option<UsageData>,
-    http_client: reqwest::Client,
+/// 表示言語を設定する。トレイメニューには次回起動時に反映される。
+#[tauri::command]
+fn set_locale(locale: Option<String>) -> Result<(), String> {
+    if let Some(tag) = &locale {
+        i18n::Locale::parse(tag).ok_or_else(|| format!("Unsupported locale: {}", tag))?;
+    }
+    let mut config = read_app_config()?;
+    config.locale = locale;
+    write_app_config(&config)
 }
 
-struct PollingControl {
-    interval_tx: watch::Sender<u64>,
-    refresh_notify: Notify,
+/// 設定ディレクトリ (`~/.usage-dashboard/`) をファイルマネージャーで開き、そのパスを返す。
+#[tauri::command]
+fn open_config_dir(app: tauri::AppHandle) -> Result<String, String> {
+    let dir = config_dir()?.display().to_string();
+    app.opener()
+        .open_path(dir.as_str(), None::<&str>)
+        .map_err(|e| format!("Failed to open config directory: {}", e))?;
+    Ok(dir)
 }
 
-fn credentials_path() -> Result<PathBuf, String> {
-    let home = dirs::home_dir().ok_or_else(|| "Could not find home directory".to_string())?;
-    Ok(home.join(".claude").join(".credentials.json"))
-}
+/// `open_external` で開ける外部ページ。フロントエンドに URL を持たせないためにここで管理する
+const EXTERNAL_PAGES: &[(&str, &str)] = &[
+    ("claude-usage", "https://claude.ai/settings/usage"),
+    ("claude-plan", "https://claude.ai/settings/billing"),
+    ("copilot-billing", "https://github.com/settings/billing"),
+    ("copilot-settings", "https://github.com/settings/copilot"),
+];
 
-fn config_path() -> Result<PathBuf, String> {
-    let home = dirs::home_dir().ok_or("Could not find home directory")?;
-    let config_dir = home.join(".usage-dashboard");
-    std::fs::create_dir_all(&config_dir)
-        .map_err(|e| format!("Failed to create config directory: {}", e))?;
-    Ok(config_dir.join("config.json"))
+/// 許可された外部ページ (`EXTERNAL_PAGES`) または設定フォルダ (`config-folder`) を開く。
+#[tauri::command]
+fn open_external(app: tauri::AppHandle, target: String) -> Result<(), String> {
+    if target == "config-folder" {
+        return open_config_dir(app).map(|_| ());
+    }
+    let (_, url) = EXTERNAL_PAGES
+        .iter()
+        .find(|(name, _)| *name == target)
+        .ok_or_else(|| format!("Unknown external target: {}", target))?;
+    app.opener()
+        .open_url(*url, None::<&str>)
+        .map_err(|e| format!("Failed to open {}: {}", url, e))
 }
 
-fn read_app_config() -> Result<AppConfig, String> {
-    let path = config_path()?;
+/// 読み込んでいる認証情報ファイルをファイルマネージャーで選択した状態で表示し、そのパスを返す。
+#[tauri::command]
+fn open_credentials_file(app: tauri::AppHandle) -> Result<String, String> {
+    let path = credentials_path()?;
     if !path.exists() {
-        return Ok(AppConfig { github: None, autostart_enabled: false });
+        return Err(format!("Credentials file not found: {}", path.display()));
     }
-    let content = std::fs::read_to_string(&path)
-        .map_err(|e| format!("Failed to read config: {}", e))?;
-    serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse config: {}", e))
+    app.opener()
+        .reveal_item_in_dir(&path)
+        .map_err(|e| format!("Failed to reveal credentials file: {}", e))?;
+    Ok(path.display().to_string())
 }
 
-fn write_app_config(config: &AppConfig) -> Result<(), String> {
-    let path = config_path()?;
-    let content = serde_json::to_string_pretty(config)
-        .map_err(|e| format!("Failed to serialize config: {}", e))?;
-    std::fs::write(&path, content)
-        .map_err(|e| format!("Failed to write config: {}", e))
+/// 直近のログを返す (トラブルシューティング用)
+#[tauri::command]
+fn get_recent_logs(lines: usize) -> Result<Vec<String>, String> {
+    logging::recent_lines(lines.clamp(1, 5000))
 }
 
-fn calculate_next_month_reset() -> String {
-    use chrono::{Datelike, TimeZone, Utc};
-
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-
-    let datetime = chrono::DateTime::<Utc>::from_timestamp(now as i64, 0).unwrap();
-
-    let next_month = if datetime.month() == 12 {
-        Utc.with_ymd_and_hms(datetime.year() + 1, 1, 1, 0, 0, 0).unwrap()
-    } else {
-        Utc.with_ymd_and_hms(datetime.year(), datetime.month() + 1, 1, 0, 0, 0).unwrap()
-    };
-
-    next_month.to_rfc3339()
+#[tauri::command]
+fn get_log_level() -> Result<String, String> {
+    Ok(read_app_config()?.logging.level)
 }
 
-struct TokenInfo {
-    access_token: String,
-    expires_at: u64,
+#[tauri::command]
+fn set_log_level(level: String) -> Result<(), String> {
+    let filter = logging::parse_level(&level)?;
+    let mut config = read_app_config()?;
+    config.logging.level = level;
+    write_app_config(&config)?;
+    log::set_max_level(filter);
+    Ok(())
 }
 
-fn read_token_info() -> Result<TokenInfo, String> {
-    let path = credentials_path()?;
-    let content = std::fs::read_to_string(&path)
-        .map_err(|e| format!("Failed to read credentials: {}", e))?;
-    let creds: Credentials = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse credentials: {}", e))?;
-    Ok(TokenInfo {
-        access_token: creds.claude_ai_oauth.access_token,
-        expires_at: creds.claude_ai_oauth.expires_at,
-    })
+#[tauri::command]
+fn set_log_events_enabled(enabled: bool) -> Result<(), String> {
+    let mut config = read_app_config()?;
+    config.logging.emit_events = enabled;
+    write_app_config(&config)?;
+    logging::set_events_enabled(enabled);
+    Ok(())
 }
 
-fn is_token_expired(expires_at: u64) -> bool {
-    let now_ms = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_millis() as u64;
-    now_ms + 30_000 >= expires_at
+#[tauri::command]
+fn get_battery_saver_config() -> Result<BatterySaverConfig, String> {
+    Ok(read_app_config()?.battery_saver)
 }
 
-async fn fetch_usage(client: &reqwest::Client, token: &str) -> Result<UsageData, String> {
-    let resp = client
-        .get("https://api.anthropic.com/api/oauth/usage")
-        .header("Authorization", format!("Bearer {}", token))
-        .header("Accept", "application/json")
-        .header("Content-Type", "application/json")
-        .header("anthropic-beta", "oauth-2025-04-20")
-        .send()
-        .await
-        .map_err(|e| {
-            // Avoid leaking token through reqwest error details
-            format!("HTTP request failed: {}", e.without_url())
-        })?;
-
-    let status = resp.status();
-    if !status.is_success() {
-        let body = resp.text().await.unwrap_or_else(|_| "<unreadable>".into());
-        return Err(format!("API returned status {}: {}", status, body));
+#[tauri::command]
+fn save_battery_saver_config(
+    enabled: bool,
+    interval_multiplier: u64,
+    skip_copilot: bool,
+) -> Result<(), String> {
+    if !(1..=10).contains(&interval_multiplier) {
+        return Err("Interval multiplier must be between 1 and 10".to_string());
     }
-
-    let body = resp
-        .text()
-        .await
-        .map_err(|e| format!("Failed to read response body: {}", e))?;
-
-    let truncated: String = body.chars().take(500).collect();
-    serde_json::from_str::<UsageData>(&body).map_err(|e| {
-        format!("Failed to parse response: {}. Body: {}", e, truncated)
-    })
+    let mut config = read_app_config()?;
+    config.battery_saver = BatterySaverConfig {
+        enabled,
+        interval_multiplier,
+        skip_copilot,
+    };
+    write_app_config(&config)
 }
 
-async fn fetch_copilot_usage(
-    client: &reqwest::Client,
-    username: &str,
-    token: &str,
-    monthly_limit: f64,
-) -> Result<CopilotUsageData, String> {
-    let url = format!(
-        "https://api.github.com/users/{}/settings/billing/premium_request/usage",
-        username
-    );
-
-    let resp = client
-        .get(&url)
-        .header("Authorization", format!("token {}", token))
-        .header("Accept", "application/vnd.github+json")
-        .header("X-GitHub-Api-Version", "2022-11-28")
-        .header("User-Agent", "tauri-usage-dashboard")
-        .send()
-        .await
-        .map_err(|e| format!("GitHub API request failed: {}", e.without_url()))?;
+#[tauri::command]
+fn get_idle_config() -> Result<IdleConfig, String> {
+    Ok(read_app_config()?.idle)
+}
 
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let body = resp.text().await.unwrap_or_else(|_| "<unreadable>".into());
-        return Err(format!("GitHub API status {}: {}", status, body));
+#[tauri::command]
+fn save_idle_config(
+    enabled: bool,
+    idle_after_secs: u64,
+    interval_multiplier: u64,
+) -> Result<(), String> {
+    if idle_after_secs < 60 {
+        return Err("Idle time must be at least 60 seconds".to_string());
     }
-
-    let body = resp.text().await
-        .map_err(|e| format!("Failed to read GitHub response: {}", e))?;
-
-    let api_response: serde_json::Value = serde_json::from_str(&body)
-        .map_err(|e| format!("Failed to parse GitHub response: {}", e))?;
-
-    let items = api_response["usageItems"]
-        .as_array()
-        .ok_or("Missing usageItems array")?;
-
-    let mut total_requests = 0.0;
-    let mut usage_items = Vec::new();
-
-    for item in items {
-        if let Some(quantity) = item["grossQuantity"].as_f64() {
-            total_requests += quantity;
-            if let Some(model) = item["model"].as_str() {
-                usage_items.push(CopilotUsageItem {
-                    model: model.to_string(),
-                    gross_quantity: quantity,
-                });
-            }
-        }
+    if !(1..=10).contains(&interval_multiplier) {
+        return Err("Interval multiplier must be between 1 and 10".to_string());
     }
-
-    let utilization = (total_requests / monthly_limit) * 100.0;
-    let resets_at = calculate_next_month_reset();
-
-    Ok(CopilotUsageData {
-        total_requests,
-        monthly_limit,
-        utilization,
-        resets_at,
-        items: usage_items,
-    })
+    let mut config = read_app_config()?;
+    config.idle = IdleConfig {
+        enabled,
+        idle_after_secs,
+        interval_multiplier,
+    };
+    write_app_config(&config)
 }
 
 #[tauri::command]
-async fn get_usage(state: tauri::State<'_, Arc<Mutex<AppState>>>) -> Result<UsageData, String> {
-    let state = state.lock().await;
-    state
-        .latest_usage
-        .clone()
-        .ok_or_else(|| "No usage data available yet".to_string())
+fn get_snap_config() -> Result<SnapConfig, String> {
+    Ok(read_app_config()?.snap)
 }
 
+/// スナップの有効/無効と吸着する距離を保存する。保存済みの吸着先はそのまま残す
 #[tauri::command]
-fn set_background_effect(window: tauri::WebviewWindow, effect: String) -> Result<(), String> {
-    #[cfg(target_os = "windows")]
-    {
-        use window_vibrancy::{apply_acrylic, apply_mica, clear_acrylic, clear_mica};
-
-        let _ = clear_mica(&window);
-        let _ = clear_acrylic(&window);
-
-        match effect.as_str() {
-            "transparent" => Ok(()),
-            "mica" => apply_mica(&window, Some(true))
-                .map_err(|e| format!("Failed to apply mica: {}", e)),
-            "acrylic" => apply_acrylic(&window, Some((18, 18, 18, 200)))
-                .map_err(|e| format!("Failed to apply acrylic: {}", e)),
-            _ => Err(format!("Unknown effect: {}", effect)),
-        }
-    }
-    #[cfg(not(target_os = "windows"))]
-    {
-        let _ = effect;
-        Ok(())
+fn save_snap_config(enabled: bool, margin: u32) -> Result<(), String> {
+    if margin > 200 {
+        return Err("Snap margin must be 200 px or less".to_string());
     }
+    let mut config = read_app_config()?;
+    config.snap.enabled = enabled;
+    config.snap.margin = margin;
+    write_app_config(&config)
 }
 
 #[tauri::command]
-fn set_always_on_top(window: tauri::WebviewWindow, enabled: bool) -> Result<(), String> {
-    window
-        .set_always_on_top(enabled)
-        .map_err(|e| format!("Failed to set always on top: {}", e))
+fn is_on_battery_power() -> bool {
+    on_battery_power()
 }
 
 #[tauri::command]
-fn force_refresh(control: tauri::State<'_, Arc<PollingControl>>) -> Result<(), String> {
-    control.refresh_notify.notify_one();
-    Ok(())
+fn get_overlay_config() -> Result<OverlayConfig, String> {
+    Ok(read_app_config()?.overlay)
 }
 
 #[tauri::command]
-fn set_polling_interval(
-    control: tauri::State<'_, Arc<PollingControl>>,
-    seconds: u64,
-) -> Result<(), String> {
-    if seconds < 10 || seconds > 600 {
-        return Err("Polling interval must be between 10 and 600 seconds".to_string());
+fn set_overlay_click_through(app: tauri::AppHandle, enabled: bool) -> Result<(), String> {
+    let mut config = read_app_config()?;
+    config.overlay.click_through = enabled;
+    write_app_config(&config)?;
+
+    if let Some(w) = app.get_webview_window(OVERLAY_LABEL) {
+        apply_overlay_config(&w, &config.overlay)?;
     }
-    control
-        .interval_tx
-        .send(seconds)
-        .map_err(|e| format!("Failed to set interval: {}", e))
+    Ok(())
 }
 
 #[tauri::command]
-fn quit_app(app: tauri::AppHandle) {
-    app.exit(0);
+fn set_overlay_opacity(app: tauri::AppHandle, opacity: u8) -> Result<(), String> {
+    if !(10..=100).contains(&opacity) {
+        return Err("Overlay opacity must be between 10 and 100".to_string());
+    }
+    let mut config = read_app_config()?;
+    config.overlay.opacity = opacity;
+    write_app_config(&config)?;
+
+    if let Some(w) = app.get_webview_window(OVERLAY_LABEL) {
+        apply_overlay_config(&w, &config.overlay)?;
+    }
+    Ok(())
 }
 
 #[tauri::command]
@@ -352,22 +1543,81 @@ fn get_github_config() -> Result<Option<GitHubConfig>, String> {
     Ok(read_app_config()?.github)
 }
 
+/// GitHub の設定を保存する。`monthly_limit` を省略した場合はプランから上限を判定する。
 #[tauri::command]
-fn save_github_config(
+async fn save_github_config(
+    state: tauri::State<'_, Arc<Mutex<AppState>>>,
     username: String,
     token: String,
-    monthly_limit: f64,
+    monthly_limit: Option<f64>,
 ) -> Result<(), String> {
-    let mut config = read_app_config().unwrap_or(AppConfig { github: None, autostart_enabled: false });
+    if monthly_limit.is_some_and(|l| l <= 0.0) {
+        return Err("Monthly limit must be greater than 0".to_string());
+    }
+    state.lock().await.copilot_plan = None;
+    let mut config = read_app_config()?;
     config.github = Some(GitHubConfig {
         username,
         token,
         monthly_limit,
+        monthly_limit_override: monthly_limit.is_some(),
     });
     write_app_config(&config)?;
     Ok(())
 }
 
+/// 判定済みの Copilot のプランを返す。未判定または上限を手動で設定している場合は `None`。
+#[tauri::command]
+async fn get_copilot_plan(
+    state: tauri::State<'_, Arc<Mutex<AppState>>>,
+) -> Result<Option<CopilotPlanInfo>, String> {
+    Ok(state.lock().await.copilot_plan.clone())
+}
+
+/// Admin API キーが設定済みかを返す。キー自体はフロントエンドに渡さない。
+#[tauri::command]
+fn get_admin_api_config() -> Result<bool, String> {
+    Ok(read_app_config()?.admin_api.is_some())
+}
+
+/// Admin API キーを保存する。空文字の場合は設定を削除する。
+#[tauri::command]
+fn save_admin_api_config(api_key: String) -> Result<(), String> {
+    let api_key = api_key.trim();
+    if !api_key.is_empty() && !api_key.starts_with("sk-ant-admin") {
+        return Err("Admin API keys start with sk-ant-admin".to_string());
+    }
+    let mut config = read_app_config()?;
+    config.admin_api = (!api_key.is_empty()).then(|| AdminApiConfig {
+        api_key: api_key.to_string(),
+    });
+    write_app_config(&config)
+}
+
+/// 当月のワークスペース別の料金と API キー別のトークン数を返す。
+#[tauri::command]
+async fn get_org_breakdown(
+    state: tauri::State<'_, Arc<Mutex<AppState>>>,
+) -> Result<admin::OrgBreakdown, String> {
+    let admin_api = read_app_config()?
+        .admin_api
+        .ok_or("Admin API key is not configured")?;
+    let client = state.lock().await.http_client.clone();
+    admin::fetch_breakdown(&client, &admin_api.api_key).await
+}
+
+/// Team / Enterprise のメンバーごとの直近 7 日間の Claude Code の利用量を返す。
+#[tauri::command]
+async fn get_team_usage(
+    state: tauri::State<'_, Arc<Mutex<AppState>>>,
+) -> Result<admin::TeamUsage, String> {
+    let admin_api = read_app_config()?
+        .admin_api
+        .ok_or("Admin API key is not configured")?;
+    let client = state.lock().await.http_client.clone();
+    admin::fetch_team_usage(&client, &admin_api.api_key).await
+}
+
 #[tauri::command]
 #[cfg(target_os = "windows")]
 async fn is_autostart_enabled(app: tauri::AppHandle) -> Result<bool, String> {
@@ -379,15 +1629,13 @@ async fn is_autostart_enabled(app: tauri::AppHandle) -> Result<bool, String> {
 #[tauri::command]
 #[cfg(target_os = "windows")]
 async fn enable_autostart(app: tauri::AppHandle) -> Result<(), String> {
+    // 設定を読めない場合は自動起動も切り替えない
+    let mut config = read_app_config()?;
     app.autolaunch()
         .enable()
         .map_err(|e| format!("Failed to enable autostart: {}", e))?;
 
     // 設定ファイルに保存
-    let mut config = read_app_config().unwrap_or(AppConfig {
-        github: None,
-        autostart_enabled: false,
-    });
     config.autostart_enabled = true;
     write_app_config(&config)?;
 
@@ -397,15 +1645,13 @@ async fn enable_autostart(app: tauri::AppHandle) -> Result<(), String> {
 #[tauri::command]
 #[cfg(target_os = "windows")]
 async fn disable_autostart(app: tauri::AppHandle) -> Result<(), String> {
+    // 設定を読めない場合は自動起動も切り替えない
+    let mut config = read_app_config()?;
     app.autolaunch()
         .disable()
         .map_err(|e| format!("Failed to disable autostart: {}", e))?;
 
     // 設定ファイルに保存
-    let mut config = read_app_config().unwrap_or(AppConfig {
-        github: None,
-        autostart_enabled: false,
-    });
     config.autostart_enabled = false;
     write_app_config(&config)?;
 
@@ -431,16 +1677,34 @@ async fn disable_autostart(_app: tauri::AppHandle) -> Result<(), String> {
     Err("Autostart is only supported on Windows".to_string())
 }
 
+/// 引数がヘッドレスモード (`cli` / `--headless`) を指定している場合はそれを実行し、
+/// 終了コードを返す。それ以外は `None`。
+pub fn run_cli(args: &[String]) -> Option<i32> {
+    cli::is_requested(args).then(|| cli::run(args))
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    let (interval_tx, interval_rx) = watch::channel(60u64);
-    let polling_control = Arc::new(PollingControl {
-        interval_tx,
-        refresh_notify: Notify::new(),
-    });
+    let log_config = read_app_config().unwrap_or_default().logging;
+    if let Err(e) = logging::init(&log_config.level, log_config.emit_events) {
+        eprintln!("Logging error: {}", e);
+    }
 
+    let (polling_control, polling_receivers) = PollingControl::new();
+
+    // 単一インスタンスのプラグインは最初に登録する必要がある
     let mut builder = tauri::Builder::default()
-        .plugin(tauri_plugin_opener::init());
+        .plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
+            deep_link::on_second_instance(app, &argv);
+        }))
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_dialog::init());
+
+    let context = tauri::generate_context!();
+    if updater_configured(context.config()) {
+        builder = builder.plugin(tauri_plugin_updater::Builder::new().build());
+    }
 
     #[cfg(target_os = "windows")]
     {
@@ -450,20 +1714,71 @@ pub fn run() {
         ));
     }
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if demo::is_requested(&args) {
+        demo::enable();
+    }
+
+    // 前回終了時のデータを復元し、最初の取得が終わるまでの間も表示できるようにする
+    // (デモモードでは実際のデータを表示しない)
+    let snapshot = if demo::is_enabled() {
+        None
+    } else {
+        read_snapshot().unwrap_or_else(|e| {
+            tracing::warn!("Snapshot error: {}", e);
+            None
+        })
+    };
+
     builder
-        .manage(Arc::new(Mutex::new(AppState {
-            latest_usage: None,
-            http_client: reqwest::Client::builder()
+        .manage(Arc::new(Mutex::new(AppState::restored(
+            snapshot,
+            reqwest::Client::builder()
                 .timeout(Duration::from_secs(30))
                 .build()
                 .expect("Failed to build HTTP client"),
-        })))
+        ))))
         .manage(Arc::clone(&polling_control))
+        .manage(api::ApiServer::default())
+        .manage(alerts::AlertState::default())
+        .manage(health::HealthState::default())
+        .manage(snap::SnapState::default())
+        .manage(popup::PopupState::default())
+        .manage(milestones::MilestoneState::default())
         .setup(move |app| {
+            logging::set_event_app(app.handle().clone());
+
+            // usage-dashboard:// のリンク。Windows / Linux ではインストールせずに
+            // 実行した場合にも開けるよう、起動時にスキームを登録する
+            {
+                use tauri_plugin_deep_link::DeepLinkExt;
+
+                #[cfg(any(windows, target_os = "linux"))]
+                if let Err(e) = app.deep_link().register_all() {
+                    tracing::warn!("Failed to register deep link scheme: {}", e);
+                }
+
+                let deep_link_handle = app.handle().clone();
+                app.deep_link().on_open_url(move |event| {
+                    for url in event.urls() {
+                        deep_link::handle(&deep_link_handle, url.as_str());
+                    }
+                });
+                if let Ok(Some(urls)) = app.deep_link().get_current() {
+                    for url in urls {
+                        deep_link::handle(app.handle(), url.as_str());
+                    }
+                }
+            }
+
             let window = app
                 .get_webview_window("main")
                 .ok_or("Main window not found")?;
 
+            // 保存した画面端・角の位置に戻し、以降は解像度の変更を監視する
+            snap::restore(app.handle());
+            snap::spawn_display_watcher(app.handle().clone());
+
             #[cfg(target_os = "windows")]
             {
                 use window_vibrancy::{apply_acrylic, apply_mica};
@@ -471,185 +1786,298 @@ pub fn run() {
                     let _ = apply_acrylic(&window, Some((18, 18, 18, 200)));
                 }
             }
+            #[cfg(target_os = "macos")]
+            {
+                use window_vibrancy::{
+                    apply_vibrancy, NSVisualEffectMaterial, NSVisualEffectState,
+                };
+                let _ = apply_vibrancy(
+                    &window,
+                    NSVisualEffectMaterial::UnderWindowBackground,
+                    Some(NSVisualEffectState::Active),
+                    Some(8.0),
+                );
+            }
 
-            // System tray
-            let toggle = MenuItemBuilder::with_id("toggle", "Show/Hide").build(app)?;
-            let quit = MenuItemBuilder::with_id("quit", "Quit").build(app)?;
-            let menu = MenuBuilder::new(app).items(&[&toggle, &quit]).build()?;
-
-            TrayIconBuilder::new()
-                .icon(
-                    app.default_window_icon()
-                        .ok_or("Default window icon not found")?
-                        .clone(),
-                )
-                .menu(&menu)
-                .on_menu_event(move |app, event| match event.id().as_ref() {
-                    "toggle" => {
-                        if let Some(w) = app.get_webview_window("main") {
-                            if w.is_visible().unwrap_or(false) {
-                                let _ = w.hide();
-                            } else {
-                                let _ = w.show();
-                                let _ = w.set_focus();
-                            }
-                        }
-                    }
-                    "quit" => {
-                        app.exit(0);
-                    }
-                    _ => {}
-                })
-                .build(app)?;
+            // Drop history samples older than the retention period
+            // (設定を読めない場合は暗号化の設定が分からないので書き直さない)
+            let pruned = read_app_config()
+                .and_then(|c| history::prune(c.history.retention_days, c.encryption.enabled));
+            if let Err(e) = pruned {
+                tracing::warn!("History error: {}", e);
+            }
+
+            // Local REST API (opt-in)
+            let api_config = read_app_config().unwrap_or_default().api;
+            app.state::<api::ApiServer>().restart(app.handle(), &api_config);
+
+            tray::build(app)?;
+            if !tray::is_available() {
+                // トレイから戻せないので、タスクバーに出して表示したままにする
+                let _ = window.set_skip_taskbar(false);
+                let _ = window.show();
+                set_window_visibility(app.handle(), "main", true);
+            }
 
             // Start dynamic polling loop
-            let app_handle = app.handle().clone();
-            let pc = polling_control;
-            let watcher_pc = Arc::clone(&pc);
-            let mut interval_rx = interval_rx;
+            let watcher_pc = Arc::clone(&polling_control);
+            let stale_pc = polling_control;
+            let polling_task = polling::spawn(app.handle().clone(), polling_receivers);
 
+            // Record in the health report if the polling loop ever stops (e.g. panics)
+            let polling_health_handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
-                async fn do_fetch(app_handle: &tauri::AppHandle) {
-                    let token_info = match read_token_info() {
-                        Ok(t) => t,
-                        Err(e) => {
-                            eprintln!("Token error: {}", e);
-                            let _ = app_handle.emit("token-status", "error");
-                            return;
-                        }
-                    };
+                let error = match polling_task.await {
+                    Ok(()) => "Polling loop exited".to_string(),
+                    Err(e) => format!("Polling task failed: {}", e),
+                };
+                tracing::error!("{}", error);
+                polling_health_handle
+                    .state::<health::HealthState>()
+                    .set_polling_stopped(error);
+            });
 
-                    if is_token_expired(token_info.expires_at) {
-                        eprintln!("Access token expired. Run Claude Code to refresh.");
-                        let _ = app_handle.emit("token-status", "expired");
-                        return;
-                    }
+            // Connectivity monitor: while offline, probe periodically and
+            // resume polling as soon as the API host is reachable again.
+            polling::spawn_connectivity_monitor(app.handle().clone());
+            polling::spawn_idle_monitor(app.handle().clone());
+
+            // Refetch right away when the system resumes from sleep
+            power::spawn_resume_listener(app.handle().clone());
 
-                    let client = {
-                        let state = app_handle.state::<Arc<Mutex<AppState>>>();
+            // Periodically tell the UI when the displayed data can no longer be trusted
+            let stale_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    tokio::time::sleep(Duration::from_secs(STALE_CHECK_INTERVAL_SECS)).await;
+                    let interval = *stale_pc.interval_tx.borrow();
+                    let event = {
+                        let state = stale_handle.state::<Arc<Mutex<AppState>>>();
                         let s = state.lock().await;
-                        s.http_client.clone()
+                        let now = chrono::Utc::now();
+                        if s.latest_usage.is_none() || !is_data_stale(&s, interval, now) {
+                            continue;
+                        }
+                        DataStaleEvent {
+                            fetched_at: s.last_fetched_at,
+                            age_secs: s.last_fetched_at.map(|t| (now - t).num_seconds()),
+                            last_error: s.last_fetch_error.clone(),
+                        }
                     };
+                    let _ = stale_handle.emit("data-stale", &event);
+                }
+            });
 
-                    let claude_result = fetch_usage(&client, &token_info.access_token).await;
-
-                    // GitHub 設定を読み込み
-                    let github_config = read_app_config().ok().and_then(|c| c.github);
-
-                    // GitHub 使用量取得（設定がある場合のみ）
-                    let copilot_result = if let Some(gh) = github_config {
-                        fetch_copilot_usage(&client, &gh.username, &gh.token, gh.monthly_limit)
-                            .await
-                            .ok()
-                    } else {
-                        None
+            // Warn shortly before the access token expires so the user can run
+            // Claude Code before the dashboard stops updating
+            let token_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let mut warned_for: Option<u64> = None;
+                loop {
+                    tokio::time::sleep(Duration::from_secs(TOKEN_CHECK_INTERVAL_SECS)).await;
+                    if demo::is_enabled() {
+                        continue;
+                    }
+                    let (Ok(token), Ok(path)) = (read_token_info(), credentials_path()) else {
+                        continue;
                     };
-
-                    // 結果を結合して送信
-                    match claude_result {
-                        Ok(claude_data) => {
-                            let combined = CombinedUsageData {
-                                claude: claude_data.clone(),
-                                copilot: copilot_result,
-                            };
-
-                            let _ = app_handle.emit("usage-update", &combined);
-                            let _ = app_handle.emit("token-status", "ok");
-
-                            let state = app_handle.state::<Arc<Mutex<AppState>>>();
-                            let mut s = state.lock().await;
-                            s.latest_usage = Some(claude_data);
-                        }
-                        Err(e) => {
-                            eprintln!("Claude API error: {}", e);
-                            let _ = app_handle.emit("token-status", "fetch_error");
-
-                            // Claude 失敗時でも Copilot データは送信
-                            if let Some(copilot_data) = copilot_result {
-                                let _ = app_handle.emit("copilot-only-update", &copilot_data);
-                            }
-                        }
+                    let status = TokenStatus::new(&token, path.display().to_string());
+                    if status.expired
+                        || status.remaining_secs > TOKEN_EXPIRING_WARNING_SECS
+                        || warned_for == Some(status.expires_at)
+                    {
+                        continue;
                     }
+                    tracing::warn!(
+                        remaining_secs = status.remaining_secs,
+                        "Access token is about to expire"
+                    );
+                    let _ = token_handle.emit("token-expiring", &status);
+                    warned_for = Some(status.expires_at);
                 }
+            });
 
-                // Immediate first fetch
-                do_fetch(&app_handle).await;
-
-                // Dynamic polling loop
+            // Send alerts held back during quiet hours once they end
+            let quiet_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
                 loop {
-                    let secs = *interval_rx.borrow();
+                    tokio::time::sleep(Duration::from_secs(QUIET_HOURS_CHECK_INTERVAL_SECS)).await;
+                    alerts::flush_quiet_hours(&quiet_handle).await;
+                }
+            });
 
-                    tokio::select! {
-                        _ = tokio::time::sleep(Duration::from_secs(secs)) => {
-                            do_fetch(&app_handle).await;
-                        }
-                        _ = pc.refresh_notify.notified() => {
-                            do_fetch(&app_handle).await;
-                        }
-                        Ok(_) = interval_rx.changed() => {
-                            continue;
-                        }
+            // Periodic update check (only in builds with an updater public key)
+            let update_handle = app.handle().clone();
+            let updater_enabled = updater_configured(app.config());
+            tauri::async_runtime::spawn(async move {
+                if !updater_enabled {
+                    tracing::info!("Updater public key not configured, skipping update checks");
+                    return;
+                }
+                loop {
+                    if let Err(e) = check_and_download_update(&update_handle).await {
+                        tracing::warn!("Update check error: {}", e);
                     }
+                    tokio::time::sleep(Duration::from_secs(UPDATE_CHECK_INTERVAL_SECS)).await;
                 }
             });
 
             // Start credentials file watcher
+            let watcher_handle = app.handle().clone();
             tauri::async_runtime::spawn_blocking(move || {
-                if let Ok(cred_path) = credentials_path() {
-                    if let Some(parent) = cred_path.parent() {
-                        let (tx, rx) = std_mpsc::channel();
-                        let mut watcher: RecommendedWatcher =
-                            match notify::recommended_watcher(move |res: Result<notify::Event, notify::Error>| {
-                                if let Ok(event) = res {
-                                    if event.kind.is_modify() || event.kind.is_create() {
-                                        let _ = tx.send(());
-                                    }
-                                }
-                            }) {
-                                Ok(w) => w,
-                                Err(e) => {
-                                    eprintln!("Failed to create file watcher: {}", e);
-                                    return;
+                let watcher_health = watcher_handle.state::<health::HealthState>();
+                let cred_path = match credentials_path() {
+                    Ok(p) => p,
+                    Err(e) => {
+                        watcher_health.set_watcher(health::WatcherHealth::Failed { error: e });
+                        return;
+                    }
+                };
+                if let Some(parent) = cred_path.parent() {
+                    let (tx, rx) = std_mpsc::channel();
+                    let mut watcher: RecommendedWatcher =
+                        match notify::recommended_watcher(move |res: Result<notify::Event, notify::Error>| {
+                            if let Ok(event) = res {
+                                if event.kind.is_modify() || event.kind.is_create() {
+                                    let _ = tx.send(());
                                 }
-                            };
+                            }
+                        }) {
+                            Ok(w) => w,
+                            Err(e) => {
+                                tracing::error!("Failed to create file watcher: {}", e);
+                                watcher_health.set_watcher(health::WatcherHealth::Failed {
+                                    error: format!("Failed to create file watcher: {}", e),
+                                });
+                                return;
+                            }
+                        };
 
-                        if let Err(e) = watcher.watch(parent, RecursiveMode::NonRecursive) {
-                            eprintln!("Failed to watch credentials dir: {}", e);
-                            return;
-                        }
+                    if let Err(e) = watcher.watch(parent, RecursiveMode::NonRecursive) {
+                        tracing::error!("Failed to watch credentials dir: {}", e);
+                        watcher_health.set_watcher(health::WatcherHealth::Failed {
+                            error: format!("Failed to watch credentials dir: {}", e),
+                        });
+                        return;
+                    }
 
-                        eprintln!("Watching credentials file: {}", cred_path.display());
-
-                        loop {
-                            // Wait for file change, debounce with 1s timeout
-                            if rx.recv().is_ok() {
-                                // Drain any additional events within 1 second
-                                while rx.recv_timeout(std::time::Duration::from_secs(1)).is_ok() {}
-                                eprintln!("Credentials file changed, triggering refresh...");
-                                watcher_pc.refresh_notify.notify_one();
-                            } else {
-                                break;
-                            }
+                    tracing::info!("Watching credentials file: {}", cred_path.display());
+                    watcher_health.set_watcher(health::WatcherHealth::Active {
+                        path: cred_path.display().to_string(),
+                    });
+
+                    loop {
+                        // Wait for file change, debounce with 1s timeout
+                        if rx.recv().is_ok() {
+                            // Drain any additional events within 1 second
+                            while rx.recv_timeout(std::time::Duration::from_secs(1)).is_ok() {}
+                            tracing::info!("Credentials file changed, triggering refresh...");
+                            watcher_pc.request_refresh(polling::Targets::only(
+                                health::Provider::Claude,
+                            ));
+                        } else {
+                            break;
                         }
                     }
+
+                    watcher_health.set_watcher(health::WatcherHealth::Failed {
+                        error: "Credentials watcher stopped".to_string(),
+                    });
                 }
             });
 
             Ok(())
         })
+        .on_window_event(|window, event| match event {
+            tauri::WindowEvent::Destroyed => {
+                set_window_visibility(window.app_handle(), window.label(), false);
+            }
+            tauri::WindowEvent::Moved(_) => snap::on_moved(window),
+            tauri::WindowEvent::Focused(false) => popup::on_focus_lost(window),
+            // トレイがない環境でタスクバーから最小化を戻したとき
+            tauri::WindowEvent::Focused(true) if window.label() == "main" => {
+                set_window_visibility(window.app_handle(), "main", true);
+            }
+            _ => {}
+        })
         .invoke_handler(tauri::generate_handler![
             get_usage,
             set_background_effect,
             set_always_on_top,
             force_refresh,
+            refresh_provider,
+            is_demo_mode,
             set_polling_interval,
+            set_background_polling_interval,
             quit_app,
             get_github_config,
             save_github_config,
             is_autostart_enabled,
             enable_autostart,
             disable_autostart,
+            check_for_updates,
+            install_update,
+            show_overlay,
+            hide_overlay,
+            show_dashboard,
+            get_overlay_config,
+            set_overlay_click_through,
+            set_overlay_opacity,
+            spawn_meter_window,
+            close_meter_window,
+            get_battery_saver_config,
+            get_snap_config,
+            save_snap_config,
+            save_battery_saver_config,
+            is_on_battery_power,
+            get_idle_config,
+            save_idle_config,
+            export_history,
+            export_snapshot,
+            get_history_config,
+            save_history_config,
+            get_encryption_config,
+            set_encryption_enabled,
+            get_api_config,
+            save_api_config,
+            regenerate_api_token,
+            get_alert_config,
+            save_alert_config,
+            test_webhook,
+            get_sound_config,
+            save_sound_config,
+            choose_sound_file,
+            test_sound,
+            get_quiet_hours_config,
+            save_quiet_hours_config,
+            get_alert_snooze,
+            snooze_alerts,
+            get_last_fetch_info,
+            get_health,
+            get_recent_logs,
+            get_log_level,
+            set_log_level,
+            set_log_events_enabled,
+            get_token_info,
+            get_model_breakdown,
+            get_recent_samples,
+            get_all_meters,
+            format_reset_time,
+            get_locale,
+            set_locale,
+            open_config_dir,
+            open_credentials_file,
+            open_external,
+            get_budget_config,
+            save_budget_config,
+            get_dump_config,
+            save_dump_config,
+            get_admin_api_config,
+            save_admin_api_config,
+            get_org_breakdown,
+            get_team_usage,
+            get_copilot_plan,
         ])
-        .run(tauri::generate_context!())
+        .run(context)
         .expect("error while running tauri application");
 }
@@ -0,0 +1,361 @@
+//! `~/.usage-dashboard/logs/` へのログ出力。
+//!
+//! このクレートのログは `tracing` で記録し、依存クレートが `log` で出力するものと
+//! 合わせて同じファイルと標準エラー出力に書き出す。
+//! ログファイルが `MAX_LOG_FILE_BYTES` を超えたら `usage-dashboard.1.log` 以降にずらし、
+//! 古いものから `MAX_ROTATED_FILES` 個を超えた分を削除する。
+//!
+//! `log-event` の送信を有効にすると、各ログをフロントエンドにもイベントとして送る。
+
+use chrono::{SecondsFormat, Utc};
+use log::{LevelFilter, Log, Metadata, Record};
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+use tauri::Emitter;
+use tracing::field::{Field, Visit};
+use tracing::subscriber::Interest;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+const LOG_FILE_NAME: &str = "usage-dashboard.log";
+const MAX_LOG_FILE_BYTES: u64 = 1024 * 1024;
+const MAX_ROTATED_FILES: usize = 3;
+
+/// このクレート以外 (reqwest / hyper など) のログは警告以上のみ記録する
+const CRATE_TARGET: &str = "usage_dashboard_lib";
+
+static LOG_FILE: Mutex<Option<LogFile>> = Mutex::new(None);
+static EVENT_APP: OnceLock<tauri::AppHandle> = OnceLock::new();
+static EVENTS_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// `log-event` イベントのペイロード
+#[derive(Debug, Clone, Serialize)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    /// 外側から順に並べた、記録時点で有効なスパン名
+    pub spans: Vec<String>,
+    pub message: String,
+}
+
+impl LogEntry {
+    fn new(level: log::Level, target: &str, spans: Vec<String>, message: String) -> Self {
+        Self {
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            level: level.to_string(),
+            target: target.to_string(),
+            spans,
+            message,
+        }
+    }
+
+    fn to_line(&self) -> String {
+        let spans = if self.spans.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", self.spans.join(":"))
+        };
+        format!(
+            "{} {:<5} {}{}: {}\n",
+            self.timestamp, self.level, self.target, spans, self.message
+        )
+    }
+}
+
+fn write_entry(entry: LogEntry) {
+    let line = entry.to_line();
+    eprint!("{}", line);
+
+    {
+        let mut file = LOG_FILE.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(f) = file.as_mut() {
+            if let Err(e) = f.write_line(&line) {
+                // 書き込めなくなった場合は標準エラー出力のみに切り替える
+                eprintln!("Failed to write log file: {}", e);
+                *file = None;
+            }
+        }
+    }
+
+    if EVENTS_ENABLED.load(Ordering::Relaxed) {
+        if let Some(app) = EVENT_APP.get() {
+            let _ = app.emit("log-event", &entry);
+        }
+    }
+}
+
+/// `log-event` の送信先を設定する。`setup` で一度だけ呼ぶ。
+pub fn set_event_app(app: tauri::AppHandle) {
+    let _ = EVENT_APP.set(app);
+}
+
+pub fn set_events_enabled(enabled: bool) {
+    EVENTS_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn log_dir() -> Result<PathBuf, String> {
+    let dir = crate::config_dir()?.join("logs");
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create log directory: {}", e))?;
+    Ok(dir)
+}
+
+fn rotated_path(dir: &Path, index: usize) -> PathBuf {
+    dir.join(format!("usage-dashboard.{}.log", index))
+}
+
+struct LogFile {
+    dir: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl LogFile {
+    fn open(dir: PathBuf) -> std::io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(LOG_FILE_NAME))?;
+        let size = file.metadata()?.len();
+        Ok(Self { dir, file, size })
+    }
+
+    fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        if self.size > MAX_LOG_FILE_BYTES {
+            self.rotate()?;
+        }
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let _ = std::fs::remove_file(rotated_path(&self.dir, MAX_ROTATED_FILES));
+        for index in (1..MAX_ROTATED_FILES).rev() {
+            let from = rotated_path(&self.dir, index);
+            if from.exists() {
+                std::fs::rename(&from, rotated_path(&self.dir, index + 1))?;
+            }
+        }
+        std::fs::rename(self.dir.join(LOG_FILE_NAME), rotated_path(&self.dir, 1))?;
+        *self = Self::open(self.dir.clone())?;
+        Ok(())
+    }
+}
+
+/// 依存クレートが `log` で出力したものを受け取る
+struct FileLogger;
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+            && (metadata.target().starts_with(CRATE_TARGET) || metadata.level() <= log::Level::Warn)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        write_entry(LogEntry::new(
+            record.level(),
+            record.target(),
+            Vec::new(),
+            record.args().to_string(),
+        ));
+    }
+
+    fn flush(&self) {
+        let mut file = LOG_FILE.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(f) = file.as_mut() {
+            let _ = f.file.flush();
+        }
+    }
+}
+
+fn to_log_level(level: &tracing::Level) -> log::Level {
+    match *level {
+        tracing::Level::ERROR => log::Level::Error,
+        tracing::Level::WARN => log::Level::Warn,
+        tracing::Level::INFO => log::Level::Info,
+        tracing::Level::DEBUG => log::Level::Debug,
+        tracing::Level::TRACE => log::Level::Trace,
+    }
+}
+
+/// スパンの開始時刻。終了時に所要時間を記録するために使う
+struct SpanStart(Instant);
+
+/// イベントのメッセージとフィールドを 1 行にまとめる
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: Vec<String>,
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.fields.push(format!("{}={}", field.name(), value));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            self.fields.push(format!("{}={:?}", field.name(), value));
+        }
+    }
+}
+
+impl MessageVisitor {
+    fn finish(mut self) -> String {
+        if !self.fields.is_empty() {
+            if !self.message.is_empty() {
+                self.message.push(' ');
+            }
+            self.message.push_str(&self.fields.join(" "));
+        }
+        self.message
+    }
+}
+
+/// このクレートの `tracing` のイベントとスパンを `write_entry` に渡すレイヤー。
+/// ログレベルは `log::max_level()` に合わせ、実行中の変更もすぐに反映する。
+struct TracingLayer;
+
+impl<S> Layer<S> for TracingLayer
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn register_callsite(&self, metadata: &'static tracing::Metadata<'static>) -> Interest {
+        if metadata.target().starts_with(CRATE_TARGET) {
+            Interest::sometimes()
+        } else {
+            Interest::never()
+        }
+    }
+
+    fn enabled(&self, metadata: &tracing::Metadata<'_>, _ctx: Context<'_, S>) -> bool {
+        to_log_level(metadata.level()) <= log::max_level()
+    }
+
+    fn on_new_span(
+        &self,
+        _attrs: &tracing::span::Attributes<'_>,
+        id: &tracing::span::Id,
+        ctx: Context<'_, S>,
+    ) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanStart(Instant::now()));
+        }
+    }
+
+    fn on_event(&self, event: &tracing::Event<'_>, ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+
+        let spans = ctx
+            .event_scope(event)
+            .map(|scope| scope.from_root().map(|s| s.name().to_string()).collect())
+            .unwrap_or_default();
+        let metadata = event.metadata();
+        write_entry(LogEntry::new(
+            to_log_level(metadata.level()),
+            metadata.target(),
+            spans,
+            visitor.finish(),
+        ));
+    }
+
+    /// デバッグレベルではスパンごとの所要時間を記録する
+    fn on_close(&self, id: tracing::span::Id, ctx: Context<'_, S>) {
+        if log::Level::Debug > log::max_level() {
+            return;
+        }
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(elapsed) = span.extensions().get::<SpanStart>().map(|s| s.0.elapsed()) else {
+            return;
+        };
+
+        let spans = span
+            .scope()
+            .from_root()
+            .map(|s| s.name().to_string())
+            .collect();
+        write_entry(LogEntry::new(
+            log::Level::Debug,
+            span.metadata().target(),
+            spans,
+            format!("finished in {:.1}ms", elapsed.as_secs_f64() * 1000.0),
+        ));
+    }
+}
+
+pub fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    level
+        .parse()
+        .map_err(|_| format!("Unknown log level: {}", level))
+}
+
+/// ロガーと `tracing` のサブスクライバーを登録する。
+/// ログファイルを開けない場合も標準エラー出力への出力は行う。
+pub fn init(level: &str, emit_events: bool) -> Result<(), String> {
+    let file = log_dir()
+        .and_then(|dir| LogFile::open(dir).map_err(|e| format!("Failed to open log file: {}", e)));
+    let file_error = match file {
+        Ok(f) => {
+            *LOG_FILE.lock().unwrap_or_else(|e| e.into_inner()) = Some(f);
+            None
+        }
+        Err(e) => Some(e),
+    };
+
+    log::set_logger(&FileLogger).map_err(|e| format!("Failed to set logger: {}", e))?;
+    log::set_max_level(parse_level(level).unwrap_or(LevelFilter::Info));
+    set_events_enabled(emit_events);
+
+    tracing::subscriber::set_global_default(tracing_subscriber::registry().with(TracingLayer))
+        .map_err(|e| format!("Failed to set tracing subscriber: {}", e))?;
+
+    match file_error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// 直近 `lines` 行のログを古い順に返す。現在のファイルで足りない場合は
+/// ローテーション済みのファイルからも読む。
+pub fn recent_lines(lines: usize) -> Result<Vec<String>, String> {
+    let dir = log_dir()?;
+    let files = std::iter::once(dir.join(LOG_FILE_NAME))
+        .chain((1..=MAX_ROTATED_FILES).map(|i| rotated_path(&dir, i)));
+
+    let mut collected: Vec<String> = Vec::new();
+    for path in files {
+        if collected.len() >= lines {
+            break;
+        }
+        let Ok(file) = File::open(&path) else {
+            continue;
+        };
+        let mut file_lines: Vec<String> =
+            BufReader::new(file).lines().map_while(Result::ok).collect();
+        file_lines.append(&mut collected);
+        collected = file_lines;
+    }
+
+    let skip = collected.len().saturating_sub(lines);
+    Ok(collected.split_off(skip))
+}
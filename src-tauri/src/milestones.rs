@@ -0,0 +1,114 @@
+//! 使用率が 25 / 50 / 75 / 90 / 100% の境界をまたいだときの `milestone-crossed` イベント。
+//!
+//! 連続する取得結果をバックエンドで比較し、フロントエンドやトレイはイベントを受けて
+//! アニメーションや色の変更だけを行う (差分の判定を JS 側で重複させない)。
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::{alerts, CombinedUsageData};
+
+/// バケットの下限 (%)。0 未満の区間は 0 とする
+const BOUNDARIES: [u32; 5] = [25, 50, 75, 90, 100];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Up,
+    Down,
+}
+
+/// `milestone-crossed` イベントのペイロード。バケットは区間の下限 (0 / 25 / 50 / 75 / 90 / 100)
+#[derive(Debug, Clone, Serialize)]
+pub struct MilestoneCrossed {
+    pub meter: &'static str,
+    pub old_bucket: u32,
+    pub new_bucket: u32,
+    pub direction: Direction,
+    pub utilization: f64,
+}
+
+fn bucket(utilization: f64) -> u32 {
+    BOUNDARIES
+        .iter()
+        .rev()
+        .copied()
+        .find(|b| utilization >= *b as f64)
+        .unwrap_or(0)
+}
+
+/// メーターごとの前回のバケット
+#[derive(Default)]
+pub struct MilestoneState {
+    buckets: std::sync::Mutex<HashMap<&'static str, u32>>,
+}
+
+impl MilestoneState {
+    /// 前回の取得結果からバケットが変わったメーターを返す。初回の取得では何も返さない
+    pub fn on_usage(&self, data: &CombinedUsageData) -> Vec<MilestoneCrossed> {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let mut crossed = Vec::new();
+        let mut next = HashMap::new();
+
+        for (meter, utilization) in alerts::meter_utilizations(data) {
+            let new_bucket = bucket(utilization);
+            if let Some(&old_bucket) = buckets.get(meter) {
+                if old_bucket != new_bucket {
+                    crossed.push(MilestoneCrossed {
+                        meter,
+                        old_bucket,
+                        new_bucket,
+                        direction: if new_bucket > old_bucket {
+                            Direction::Up
+                        } else {
+                            Direction::Down
+                        },
+                        utilization,
+                    });
+                }
+            }
+            next.insert(meter, new_bucket);
+        }
+
+        *buckets = next;
+        crossed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_by_lower_boundary() {
+        assert_eq!(bucket(0.0), 0);
+        assert_eq!(bucket(24.9), 0);
+        assert_eq!(bucket(25.0), 25);
+        assert_eq!(bucket(89.9), 75);
+        assert_eq!(bucket(90.0), 90);
+        assert_eq!(bucket(130.0), 100);
+    }
+
+    #[test]
+    fn reports_crossings_after_the_first_fetch() {
+        let state = MilestoneState::default();
+        let mut data = crate::demo::sample_data(chrono::Utc::now());
+        data.claude.five_hour.utilization = 20.0;
+        assert!(state.on_usage(&data).is_empty());
+
+        data.claude.five_hour.utilization = 24.0;
+        assert!(state.on_usage(&data).is_empty());
+
+        data.claude.five_hour.utilization = 55.0;
+        let crossed = state.on_usage(&data);
+        let five_hour = crossed.iter().find(|c| c.meter == "five_hour").unwrap();
+        assert_eq!((five_hour.old_bucket, five_hour.new_bucket), (0, 50));
+        assert_eq!(five_hour.direction, Direction::Up);
+
+        data.claude.five_hour.utilization = 3.0;
+        let crossed = state.on_usage(&data);
+        let five_hour = crossed.iter().find(|c| c.meter == "five_hour").unwrap();
+        assert_eq!(five_hour.direction, Direction::Down);
+        assert!(crossed.iter().all(|c| c.meter == "five_hour"));
+    }
+}
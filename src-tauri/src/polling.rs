@@ -0,0 +1,993 @@
+//! 使用量のポーリング。
+//!
+//! 表示中・非表示中の間隔、リセット時刻に合わせた調整、バッテリー駆動中・
+//! アイドル中の延長、レート制限中の待機、オフライン中の停止をまとめて扱う。
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use tauri::{Emitter, Manager};
+use tokio::sync::{watch, Mutex, Notify};
+use tokio::time::Duration;
+
+use crate::providers::{self, claude::UsageData};
+use crate::{
+    alerts, apply_extra_usage_cost, calculate_budget_usage, copilot_monthly_limit, demo,
+    fetch_copilot_with_fallback, fetch_usage, github_auth, health, history, i18n, idle,
+    is_token_expired, milestones, read_app_config, read_token_info, reset_time, write_dump,
+    write_snapshot, AppConfig, AppState, BatterySaverConfig, CombinedUsageData, CopilotAuthError,
+    CopilotError, HeartbeatEvent, IdleConfig, StaleUsage, StoredSnapshot,
+};
+
+/// リセット時刻の前後この範囲内は短い間隔でポーリングする
+const RESET_NEAR_WINDOW_SECS: i64 = 5 * 60;
+const RESET_NEAR_INTERVAL_SECS: u64 = 15;
+/// 次のリセットがこれより先ならポーリング間隔を延ばす
+const RESET_FAR_THRESHOLD_SECS: i64 = 60 * 60;
+const MAX_ADAPTIVE_INTERVAL_SECS: u64 = 600;
+
+/// オフライン中の接続確認 (HEAD リクエスト) の送信先と間隔
+const CONNECTIVITY_PROBE_URL: &str = providers::claude::API_BASE;
+const CONNECTIVITY_RETRY_SECS: u64 = 10;
+
+/// 最後の取得成功からポーリング間隔のこの倍数以上経つと古いデータとみなす
+const STALE_INTERVAL_MULTIPLIER: i64 = 3;
+
+/// バッテリー駆動中・アイドル中に延長したポーリング間隔の上限
+const MAX_STRETCHED_INTERVAL_SECS: u64 = 3600;
+
+/// アイドル状態を確認する間隔。操作が再開されてからこの時間内に通常の間隔へ戻る
+const IDLE_CHECK_INTERVAL_SECS: u64 = 30;
+
+/// 取得に失敗してから、通常の間隔を待たずに再取得するまでの時間
+const REVALIDATE_DELAY_SECS: u64 = 15;
+
+pub struct PollingControl {
+    pub interval_tx: watch::Sender<u64>,
+    /// ウィンドウが全て非表示の間に使う間隔 (秒)。0 の場合はポーリングを停止する
+    pub background_interval_tx: watch::Sender<u64>,
+    pub visible_tx: watch::Sender<bool>,
+    pub visible_windows: std::sync::Mutex<HashSet<String>>,
+    pub online_tx: watch::Sender<bool>,
+    /// ユーザーの操作がなくアイドル中か
+    pub idle_tx: watch::Sender<bool>,
+    /// `refresh_notify` だけで起こした場合は全プロバイダーを取得する
+    pub refresh_notify: Notify,
+    /// `request_refresh` で指定した、次の即時取得で取得するプロバイダー
+    refresh_targets: std::sync::Mutex<Option<Targets>>,
+}
+
+/// 1 回の取得で取得するプロバイダー。取得しないプロバイダーは前回の値を使い、
+/// バックオフやキャッシュなどの状態にも触れない
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Targets {
+    pub claude: bool,
+    pub copilot: bool,
+}
+
+impl Targets {
+    pub const ALL: Self = Self {
+        claude: true,
+        copilot: true,
+    };
+
+    pub fn only(provider: health::Provider) -> Self {
+        Self {
+            claude: provider == health::Provider::Claude,
+            copilot: provider == health::Provider::Copilot,
+        }
+    }
+
+    fn union(self, other: Self) -> Self {
+        Self {
+            claude: self.claude || other.claude,
+            copilot: self.copilot || other.copilot,
+        }
+    }
+}
+
+/// ポーリングループ側で受け取る `PollingControl` の各チャネル
+pub struct PollingReceivers {
+    interval_rx: watch::Receiver<u64>,
+    background_interval_rx: watch::Receiver<u64>,
+    visible_rx: watch::Receiver<bool>,
+    online_rx: watch::Receiver<bool>,
+    idle_rx: watch::Receiver<bool>,
+}
+
+impl PollingControl {
+    pub fn new() -> (Arc<Self>, PollingReceivers) {
+        let (interval_tx, interval_rx) = watch::channel(60u64);
+        let (background_interval_tx, background_interval_rx) = watch::channel(300u64);
+        let (visible_tx, visible_rx) = watch::channel(true);
+        let (online_tx, online_rx) = watch::channel(true);
+        let (idle_tx, idle_rx) = watch::channel(false);
+        let control = Arc::new(PollingControl {
+            interval_tx,
+            background_interval_tx,
+            visible_tx,
+            visible_windows: std::sync::Mutex::new(HashSet::from(["main".to_string()])),
+            online_tx,
+            idle_tx,
+            refresh_notify: Notify::new(),
+            refresh_targets: std::sync::Mutex::new(None),
+        });
+        let receivers = PollingReceivers {
+            interval_rx,
+            background_interval_rx,
+            visible_rx,
+            online_rx,
+            idle_rx,
+        };
+        (control, receivers)
+    }
+
+    /// 指定したプロバイダーだけをすぐに取得する。取得前に重ねて要求された場合はまとめる
+    pub fn request_refresh(&self, targets: Targets) {
+        let mut pending = self
+            .refresh_targets
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        *pending = Some(pending.map_or(targets, |p| p.union(targets)));
+        drop(pending);
+        self.refresh_notify.notify_one();
+    }
+
+    fn take_refresh_targets(&self) -> Targets {
+        self.refresh_targets
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+            .unwrap_or(Targets::ALL)
+    }
+}
+
+/// メーターのリセット時刻に応じてポーリング間隔を調整する。
+///
+/// リセット直前・直後は表示がすぐ 0% に戻るよう短い間隔にし、
+/// 次のリセットまで十分に時間がある間は間隔を延ばして API 呼び出しを減らす。
+pub fn adaptive_interval(
+    base_secs: u64,
+    usage: Option<&UsageData>,
+    now: chrono::DateTime<chrono::Utc>,
+) -> u64 {
+    let lazy_secs = base_secs.max(base_secs.saturating_mul(2).min(MAX_ADAPTIVE_INTERVAL_SECS));
+
+    let Some(usage) = usage else {
+        return base_secs;
+    };
+
+    let nearest = [&usage.five_hour, &usage.seven_day]
+        .iter()
+        .filter_map(|m| m.resets_at.as_deref())
+        .filter_map(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+        .map(|t| (t.with_timezone(&chrono::Utc) - now).num_seconds())
+        .min_by_key(|delta| delta.abs());
+
+    // アクティブなセッションがない場合はリセットを待つ必要がない
+    let Some(delta) = nearest else {
+        return lazy_secs;
+    };
+
+    if delta.abs() <= RESET_NEAR_WINDOW_SECS {
+        return base_secs.min(RESET_NEAR_INTERVAL_SECS);
+    }
+
+    if delta > 0 {
+        let interval = if delta > RESET_FAR_THRESHOLD_SECS {
+            lazy_secs
+        } else {
+            base_secs
+        };
+        // リセット直前の短間隔ウィンドウに入るタイミングで起きる
+        let until_window = (delta - RESET_NEAR_WINDOW_SECS) as u64;
+        return interval.min(until_window.max(1));
+    }
+
+    base_secs
+}
+
+/// AC 電源に接続されておらず、バッテリーで動作しているかを返す。
+/// 判定できない場合 (デスクトップ PC など) は `false`。
+#[cfg(target_os = "windows")]
+pub fn on_battery_power() -> bool {
+    use windows_sys::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    // SAFETY: SYSTEM_POWER_STATUS is a plain C struct that GetSystemPowerStatus fills in.
+    let mut status: SYSTEM_POWER_STATUS = unsafe { std::mem::zeroed() };
+    let ok = unsafe { GetSystemPowerStatus(&mut status) } != 0;
+    ok && status.ACLineStatus == 0
+}
+
+#[cfg(target_os = "macos")]
+pub fn on_battery_power() -> bool {
+    std::process::Command::new("pmset")
+        .args(["-g", "batt"])
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).contains("'Battery Power'"))
+        .unwrap_or(false)
+}
+
+#[cfg(target_os = "linux")]
+pub fn on_battery_power() -> bool {
+    let Ok(entries) = std::fs::read_dir("/sys/class/power_supply") else {
+        return false;
+    };
+
+    let mut has_battery = false;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let kind = std::fs::read_to_string(path.join("type")).unwrap_or_default();
+        match kind.trim() {
+            "Mains" | "USB" => {
+                let online = std::fs::read_to_string(path.join("online")).unwrap_or_default();
+                if online.trim() == "1" {
+                    return false;
+                }
+            }
+            "Battery" => has_battery = true,
+            _ => {}
+        }
+    }
+    has_battery
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+pub fn on_battery_power() -> bool {
+    false
+}
+
+pub fn battery_saver_active(config: &BatterySaverConfig) -> bool {
+    config.enabled && on_battery_power()
+}
+
+/// 最後の入力から `idle_after_secs` 以上経っているか。取得できない環境では `false`
+pub fn user_idle(config: &IdleConfig) -> bool {
+    config.enabled && idle::idle_secs().is_some_and(|secs| secs >= config.idle_after_secs)
+}
+
+/// API ホストに到達できるかを確認する。HTTP ステータスに関わらず応答があれば接続済みとみなす。
+pub async fn probe_connectivity(client: &reqwest::Client) -> bool {
+    client
+        .head(CONNECTIVITY_PROBE_URL)
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await
+        .is_ok()
+}
+
+/// 接続状態を更新し、変化した場合は `network-status` イベントを送信する。
+pub fn set_online(app: &tauri::AppHandle, online: bool) {
+    let control = app.state::<Arc<PollingControl>>();
+    let changed = control.online_tx.send_if_modified(|v| {
+        let changed = *v != online;
+        *v = online;
+        changed
+    });
+    if changed {
+        let _ = app.emit("network-status", if online { "online" } else { "offline" });
+    }
+}
+
+/// データが古いかどうかを判定する。起動時に復元しただけのデータも古いとみなす。
+pub fn is_data_stale(
+    state: &AppState,
+    interval_secs: u64,
+    now: chrono::DateTime<chrono::Utc>,
+) -> bool {
+    if state.usage_stale {
+        return true;
+    }
+    let Some(fetched_at) = state.last_fetched_at else {
+        return false;
+    };
+    let limit = (interval_secs as i64).saturating_mul(STALE_INTERVAL_MULTIPLIER);
+    (now - fetched_at).num_seconds() > limit
+}
+
+/// GitHub に認証を拒否されたかどうかを記録し、新たに拒否された場合は
+/// `copilot-auth-error` イベントを送信する。
+async fn set_copilot_auth_error(app: &tauri::AppHandle, error: Option<CopilotAuthError>) {
+    let state = app.state::<Arc<Mutex<AppState>>>();
+    let mut s = state.lock().await;
+    if s.copilot_auth_error == error {
+        return;
+    }
+    if let Some(error) = &error {
+        tracing::warn!(status = error.status, source = ?error.source, "GitHub token rejected");
+        let _ = app.emit("copilot-auth-error", error);
+    }
+    s.copilot_auth_error = error;
+}
+
+/// 前回送ったデータから表示が変わるか。使用量が変わらなくても、リセットまでの残り時間
+/// ("in 3h 12m") の表記が進んだ場合は送り直して、カウントダウンを止めないようにする
+fn usage_changed(prev: Option<&CombinedUsageData>, next: &CombinedUsageData) -> bool {
+    prev.is_none_or(|prev| {
+        prev.claude != next.claude
+            || prev.copilot != next.copilot
+            || prev.reset_times != next.reset_times
+            || prev.budget != next.budget
+            || prev.copilot_needs_reauth != next.copilot_needs_reauth
+            || prev.stale != next.stale
+    })
+}
+
+/// 取得の失敗を記録し、最後に送ったデータ (起動直後は前回終了時のデータ) に失敗の理由を
+/// 付けて返す。送れるデータがない場合は `None`
+fn mark_stale(
+    s: &mut AppState,
+    error: String,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Option<CombinedUsageData> {
+    s.last_fetch_error = Some(error.clone());
+    s.last_emitted_usage.as_mut().map(|data| {
+        data.stale = Some(StaleUsage {
+            reason: error,
+            failed_at: now,
+        });
+        data.clone()
+    })
+}
+
+/// 取得の失敗を記録し、最後に送ったデータを失敗の理由とともに `usage-update` で送り直す
+/// (stale-while-revalidate)。表示は最後に取得できた値のまま、古いことと理由が分かるようにする。
+///
+/// `revalidate` の場合、直前まで取得できていた (一時的なエラーの可能性が高い) ときは
+/// 通常の間隔を待たずに Claude を再取得する。失敗が続く間は通常の間隔に任せる
+pub async fn record_fetch_error(app: &tauri::AppHandle, error: String, revalidate: bool) {
+    let (stale, first_failure) = {
+        let state = app.state::<Arc<Mutex<AppState>>>();
+        let mut s = state.lock().await;
+        let first_failure = s.last_fetch_error.is_none();
+        (mark_stale(&mut s, error, chrono::Utc::now()), first_failure)
+    };
+    if let Some(data) = stale {
+        let _ = app.emit("usage-update", &data);
+    }
+
+    if revalidate && first_failure {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(REVALIDATE_DELAY_SECS)).await;
+            let control = app.state::<Arc<PollingControl>>();
+            // オフライン中は再接続した時点で取得するので、ここでは何もしない
+            if *control.online_tx.borrow() {
+                tracing::info!("Revalidating after fetch error");
+                control.request_refresh(Targets::only(health::Provider::Claude));
+            }
+        });
+    }
+}
+
+/// 次の取得までの待機時間 (秒) に、バッテリー駆動中・アイドル中の延長 (`multiplier`) と
+/// レート制限が解除される時刻 (`limited_until`) までの待機を反映する。0 は停止中のまま
+fn backoff_secs(
+    secs: u64,
+    multiplier: Option<u64>,
+    limited_until: Option<chrono::DateTime<chrono::Utc>>,
+    now: chrono::DateTime<chrono::Utc>,
+) -> u64 {
+    if secs == 0 {
+        return 0;
+    }
+    let secs = match multiplier {
+        Some(multiplier) => secs
+            .saturating_mul(multiplier)
+            .min(MAX_STRETCHED_INTERVAL_SECS),
+        None => secs,
+    };
+    match limited_until {
+        Some(until) => secs.max((until - now).num_seconds().max(0) as u64),
+        None => secs,
+    }
+}
+
+/// 各プロバイダーの使用量を取得し、結果をイベント・履歴・スナップショットに反映する
+#[tracing::instrument(name = "poll", skip_all, fields(?targets))]
+async fn do_fetch(app_handle: &tauri::AppHandle, mut targets: Targets) {
+    // レート制限中は retry-after / リセット時刻まで Claude の取得だけを見送り、
+    // 前回の値を使う。Copilot は通常どおり取得する
+    let limited = {
+        let state = app_handle.state::<Arc<Mutex<AppState>>>();
+        let mut s = state.lock().await;
+        s.last_attempt_at = Some(chrono::Utc::now());
+        s.rate_limit
+            .clone()
+            .filter(|r| targets.claude && r.is_limited(chrono::Utc::now()))
+    };
+    if let Some(rate_limit) = limited {
+        tracing::info!(
+            until = ?rate_limit.limited_until,
+            "Rate limited, skipping Claude fetch"
+        );
+        let _ = app_handle.emit("rate-limited", &rate_limit);
+        targets.claude = false;
+    }
+
+    let client = {
+        let state = app_handle.state::<Arc<Mutex<AppState>>>();
+        let s = state.lock().await;
+        s.http_client.clone()
+    };
+
+    // デモモードでは実際のプロバイダーの代わりに合成データを使う
+    let (claude_result, claude_rate_limit) = if !targets.claude {
+        // Claude を取得しない場合は前回の値とレート制限の状態をそのまま使う
+        let state = app_handle.state::<Arc<Mutex<AppState>>>();
+        let s = state.lock().await;
+        (
+            s.latest_usage
+                .clone()
+                .ok_or_else(|| "Claude usage has not been fetched yet".to_string()),
+            s.rate_limit.clone(),
+        )
+    } else if demo::is_enabled() {
+        (demo::usage(chrono::Utc::now()), None)
+    } else {
+        let token_info = match read_token_info() {
+            Ok(t) => t,
+            Err(e) => {
+                tracing::error!("Token error: {}", e);
+                let _ = app_handle.emit("token-status", "error");
+                app_handle
+                    .state::<health::HealthState>()
+                    .record_failure(health::Provider::Claude, &e);
+                record_fetch_error(app_handle, e, false).await;
+                return;
+            }
+        };
+
+        if is_token_expired(token_info.expires_at) {
+            tracing::warn!("Access token expired. Run Claude Code to refresh.");
+            app_handle
+                .state::<health::HealthState>()
+                .record_failure(health::Provider::Claude, "Access token expired");
+            record_fetch_error(app_handle, "Access token expired".to_string(), false).await;
+            let _ = app_handle.emit("token-status", "expired");
+            if let Some(alert) = app_handle.state::<alerts::AlertState>().on_token_expired() {
+                alerts::raise(app_handle, vec![alert]);
+            }
+            return;
+        }
+
+        fetch_usage(&client, &token_info.access_token).await
+    };
+    if targets.claude {
+        app_handle
+            .state::<health::HealthState>()
+            .set_rate_limit(health::Provider::Claude, claude_rate_limit.clone());
+        let state = app_handle.state::<Arc<Mutex<AppState>>>();
+        let mut s = state.lock().await;
+        s.rate_limit = claude_rate_limit.clone();
+    }
+
+    // GitHub 設定を読み込み。読めない (暗号化の鍵がないなど) 場合は既定値で取得を続けるが、
+    // 暗号化するかが分からないので履歴やファイルには書き込まない
+    let (app_config, config_readable) = match read_app_config() {
+        Ok(config) => (config, true),
+        Err(e) => {
+            tracing::warn!("Config error, not persisting usage: {}", e);
+            (AppConfig::default(), false)
+        }
+    };
+    let skip_copilot = !targets.copilot
+        || (app_config.battery_saver.skip_copilot
+            && battery_saver_active(&app_config.battery_saver));
+
+    // GitHub 使用量取得（gh CLI または PAT がある場合のみ）
+    // 取得対象外・バッテリー節約中は前回の値を使い回す
+    let github_candidates = if skip_copilot || demo::is_enabled() {
+        Vec::new()
+    } else {
+        github_auth::candidates(&client, app_config.github.as_ref()).await
+    };
+    let copilot_result = match github_candidates.first() {
+        _ if demo::is_enabled() => Some(demo::copilot(chrono::Utc::now())),
+        _ if skip_copilot => {
+            let state = app_handle.state::<Arc<Mutex<AppState>>>();
+            let s = state.lock().await;
+            s.latest_copilot.clone()
+        }
+        Some(first) => {
+            let state = app_handle.state::<Arc<Mutex<AppState>>>();
+            let mut plan = state.lock().await.copilot_plan.clone();
+            let monthly_limit = copilot_monthly_limit(&client, &first.config, &mut plan).await;
+            state.lock().await.copilot_plan = plan;
+
+            let result =
+                fetch_copilot_with_fallback(&client, &github_candidates, monthly_limit).await;
+            let copilot_health = app_handle.state::<health::HealthState>();
+            let auth_error = match &result {
+                Err(CopilotError::Auth(e)) => Some(e.clone()),
+                _ => None,
+            };
+            set_copilot_auth_error(app_handle, auth_error).await;
+            match result {
+                Ok(data) => {
+                    copilot_health.record_success(health::Provider::Copilot);
+                    Some(data)
+                }
+                Err(e) => {
+                    tracing::error!("Copilot API error: {}", e);
+                    copilot_health.record_failure(health::Provider::Copilot, &e.to_string());
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    if let Some(copilot_data) = &copilot_result {
+        let state = app_handle.state::<Arc<Mutex<AppState>>>();
+        let mut s = state.lock().await;
+        s.latest_copilot = Some(copilot_data.clone());
+    }
+
+    // 結果を結合して送信
+    match claude_result {
+        Ok(mut claude_data) => {
+            apply_extra_usage_cost(&mut claude_data, &app_config);
+            if targets.claude {
+                app_handle
+                    .state::<health::HealthState>()
+                    .record_success(health::Provider::Claude);
+            }
+            let fetched_at = chrono::Utc::now();
+            let reset_times = reset_time::for_usage(
+                &claude_data,
+                copilot_result.as_ref(),
+                chrono::Local::now(),
+                i18n::current(),
+            );
+            let budget = calculate_budget_usage(&claude_data, copilot_result.as_ref(), &app_config);
+            let (copilot_needs_reauth, stale) = {
+                let state = app_handle.state::<Arc<Mutex<AppState>>>();
+                let s = state.lock().await;
+                // Claude を取得していない場合は、前回の失敗の印を引き継ぐ
+                let stale = if targets.claude {
+                    None
+                } else {
+                    s.last_emitted_usage.as_ref().and_then(|d| d.stale.clone())
+                };
+                (s.copilot_auth_error.is_some(), stale)
+            };
+            let combined = CombinedUsageData {
+                claude: claude_data.clone(),
+                copilot: copilot_result,
+                fetched_at: Some(fetched_at),
+                reset_times,
+                budget,
+                rate_limit: claude_rate_limit.clone(),
+                copilot_needs_reauth,
+                stale,
+            };
+
+            let changed = {
+                let state = app_handle.state::<Arc<Mutex<AppState>>>();
+                let mut s = state.lock().await;
+                s.recent_samples.record(fetched_at, &combined);
+                // 前回終了時のデータから復元しただけの場合は、同じ値でも必ず送る
+                let changed =
+                    s.usage_stale || usage_changed(s.last_emitted_usage.as_ref(), &combined);
+                if changed {
+                    s.last_emitted_usage = Some(combined.clone());
+                }
+                changed
+            };
+            if changed {
+                let _ = app_handle.emit("usage-update", &combined);
+            }
+            if targets.claude {
+                let _ = app_handle.emit(
+                    "heartbeat",
+                    HeartbeatEvent {
+                        fetched_at,
+                        changed,
+                    },
+                );
+                let _ = app_handle.emit("token-status", "ok");
+            }
+            if let Some(rate_limit) = claude_rate_limit
+                .as_ref()
+                .filter(|r| r.is_limited(fetched_at))
+            {
+                let _ = app_handle.emit("rate-limited", rate_limit);
+            }
+
+            for milestone in app_handle
+                .state::<milestones::MilestoneState>()
+                .on_usage(&combined)
+            {
+                let _ = app_handle.emit("milestone-crossed", &milestone);
+            }
+
+            let new_alerts = app_handle
+                .state::<alerts::AlertState>()
+                .on_usage(&combined, &app_config.alerts);
+            alerts::raise(app_handle, new_alerts);
+
+            // デモモードの合成データは履歴やファイルに残さない
+            let persist = !demo::is_enabled() && config_readable;
+
+            if app_config.history.enabled && persist {
+                let sample = history::HistorySample::new(fetched_at, &combined);
+                if let Err(e) = history::append_sample(&sample, app_config.encryption.enabled) {
+                    tracing::warn!("History error: {}", e);
+                }
+            }
+
+            if app_config.dump.enabled && persist {
+                if let Err(e) = write_dump(&app_config.dump, &combined) {
+                    tracing::warn!("Dump error: {}", e);
+                }
+            }
+
+            if persist {
+                let snapshot = StoredSnapshot {
+                    fetched_at,
+                    data: combined,
+                };
+                if let Err(e) = write_snapshot(&snapshot, app_config.encryption.enabled) {
+                    tracing::warn!("Snapshot error: {}", e);
+                }
+            }
+
+            if targets.claude {
+                let state = app_handle.state::<Arc<Mutex<AppState>>>();
+                let mut s = state.lock().await;
+                s.latest_usage = Some(claude_data);
+                s.last_fetched_at = Some(fetched_at);
+                s.last_fetch_error = None;
+                s.usage_stale = false;
+            }
+        }
+        // Claude を取得していない (まだ一度も取得できていない) 場合は Copilot だけ送る
+        Err(_) if !targets.claude => {
+            if let Some(copilot_data) = copilot_result {
+                let _ = app_handle.emit("copilot-only-update", &copilot_data);
+            }
+        }
+        Err(e) => {
+            tracing::error!("Claude API error: {}", e);
+            let _ = app_handle.emit("token-status", "fetch_error");
+            if let Some(rate_limit) = claude_rate_limit
+                .as_ref()
+                .filter(|r| r.is_limited(chrono::Utc::now()))
+            {
+                tracing::warn!(
+                    until = ?rate_limit.limited_until,
+                    "Claude API rate limited"
+                );
+                let _ = app_handle.emit("rate-limited", rate_limit);
+            }
+            app_handle
+                .state::<health::HealthState>()
+                .record_failure(health::Provider::Claude, &e);
+            record_fetch_error(app_handle, e, true).await;
+
+            // ネットワーク断の場合は復旧するまでポーリングを止める
+            if !demo::is_enabled() && !probe_connectivity(&client).await {
+                tracing::warn!("Network appears to be offline, pausing polling");
+                set_online(app_handle, false);
+            }
+
+            // Claude 失敗時でも Copilot データは送信
+            if let Some(copilot_data) = copilot_result {
+                let _ = app_handle.emit("copilot-only-update", &copilot_data);
+            }
+        }
+    }
+}
+
+/// ポーリングループを開始する。最初の取得はすぐに行う
+pub fn spawn(
+    app_handle: tauri::AppHandle,
+    receivers: PollingReceivers,
+) -> tauri::async_runtime::JoinHandle<()> {
+    let PollingReceivers {
+        mut interval_rx,
+        mut background_interval_rx,
+        mut visible_rx,
+        mut online_rx,
+        mut idle_rx,
+    } = receivers;
+
+    tauri::async_runtime::spawn(async move {
+        let pc = Arc::clone(&*app_handle.state::<Arc<PollingControl>>());
+        let poll_health = app_handle.state::<health::HealthState>();
+        poll_health.set_polling_running();
+
+        // Immediate first fetch
+        do_fetch(&app_handle, Targets::ALL).await;
+
+        // Dynamic polling loop
+        loop {
+            let online = *online_rx.borrow();
+            let visible = *visible_rx.borrow();
+            let secs = if !online {
+                0
+            } else if visible {
+                let base = *interval_rx.borrow();
+                let latest = {
+                    let state = app_handle.state::<Arc<Mutex<AppState>>>();
+                    let s = state.lock().await;
+                    s.latest_usage.clone()
+                };
+                adaptive_interval(base, latest.as_ref(), chrono::Utc::now())
+            } else {
+                *background_interval_rx.borrow()
+            };
+
+            // バッテリー駆動中・アイドル中は間隔を延長し、レート制限中は制限が解除される時刻まで待つ
+            let app_config = read_app_config().unwrap_or_default();
+            let battery_multiplier = battery_saver_active(&app_config.battery_saver)
+                .then_some(app_config.battery_saver.interval_multiplier);
+            let idle_multiplier = (*idle_rx.borrow() && app_config.idle.enabled)
+                .then_some(app_config.idle.interval_multiplier);
+            let multiplier = battery_multiplier
+                .into_iter()
+                .chain(idle_multiplier)
+                .reduce(u64::saturating_mul);
+            let limited_until = {
+                let state = app_handle.state::<Arc<Mutex<AppState>>>();
+                let s = state.lock().await;
+                s.rate_limit.as_ref().and_then(|r| r.limited_until)
+            };
+            let secs = backoff_secs(secs, multiplier, limited_until, chrono::Utc::now());
+
+            poll_health.set_next_fire(
+                (secs != 0).then(|| chrono::Utc::now() + chrono::Duration::seconds(secs as i64)),
+            );
+
+            // オフライン中、または非表示中の間隔が 0 の場合は状態が変わるまで待機
+            let tick = async {
+                if secs == 0 {
+                    std::future::pending::<()>().await;
+                } else {
+                    tokio::time::sleep(Duration::from_secs(secs)).await;
+                }
+            };
+
+            tokio::select! {
+                _ = tick => {
+                    do_fetch(&app_handle, Targets::ALL).await;
+                }
+                _ = pc.refresh_notify.notified() => {
+                    do_fetch(&app_handle, pc.take_refresh_targets()).await;
+                }
+                Ok(_) = interval_rx.changed() => {
+                    continue;
+                }
+                Ok(_) = background_interval_rx.changed() => {
+                    continue;
+                }
+                Ok(_) = visible_rx.changed() => {
+                    // 再表示された時点で最新のデータを取得する
+                    if *visible_rx.borrow() {
+                        do_fetch(&app_handle, Targets::ALL).await;
+                    }
+                }
+                Ok(_) = online_rx.changed() => {
+                    // 再接続した時点で最新のデータを取得する
+                    if *online_rx.borrow() {
+                        do_fetch(&app_handle, Targets::ALL).await;
+                    }
+                }
+                Ok(_) = idle_rx.changed() => {
+                    // 操作が再開された時点で最新のデータを取得する
+                    if !*idle_rx.borrow() {
+                        do_fetch(&app_handle, Targets::ALL).await;
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// オフライン中は定期的に接続を確認し、API ホストに到達できた時点でポーリングを再開する
+pub fn spawn_connectivity_monitor(net_handle: tauri::AppHandle) {
+    let mut net_online_rx = net_handle
+        .state::<Arc<PollingControl>>()
+        .online_tx
+        .subscribe();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let online = *net_online_rx.borrow_and_update();
+            if online {
+                if net_online_rx.changed().await.is_err() {
+                    break;
+                }
+                continue;
+            }
+
+            tokio::time::sleep(Duration::from_secs(CONNECTIVITY_RETRY_SECS)).await;
+
+            let client = {
+                let state = net_handle.state::<Arc<Mutex<AppState>>>();
+                let s = state.lock().await;
+                s.http_client.clone()
+            };
+            if probe_connectivity(&client).await {
+                tracing::info!("Network connection restored, resuming polling");
+                set_online(&net_handle, true);
+            }
+        }
+    });
+}
+
+/// アイドル状態を定期的に確認し、変化した場合は `idle-status` イベントを送信する
+pub fn spawn_idle_monitor(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let config = read_app_config().map(|c| c.idle).unwrap_or_default();
+            let idle = tauri::async_runtime::spawn_blocking(move || user_idle(&config))
+                .await
+                .unwrap_or(false);
+
+            let changed = app
+                .state::<Arc<PollingControl>>()
+                .idle_tx
+                .send_if_modified(|v| {
+                    let changed = *v != idle;
+                    *v = idle;
+                    changed
+                });
+            if changed {
+                tracing::info!(idle, "User idle state changed");
+                let _ = app.emit("idle-status", if idle { "idle" } else { "active" });
+            }
+
+            tokio::time::sleep(Duration::from_secs(IDLE_CHECK_INTERVAL_SECS)).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::claude::UsageMeter;
+    use chrono::{TimeZone, Utc};
+
+    fn now() -> chrono::DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap()
+    }
+
+    fn usage(five_hour_reset_in: Option<i64>) -> UsageData {
+        let meter = |resets_in: Option<i64>| UsageMeter {
+            utilization: 10.0,
+            resets_at: resets_in.map(|secs| (now() + chrono::Duration::seconds(secs)).to_rfc3339()),
+        };
+        UsageData {
+            five_hour: meter(five_hour_reset_in),
+            seven_day: meter(None),
+            seven_day_oauth_apps: None,
+            seven_day_opus: None,
+            seven_day_sonnet: None,
+            seven_day_cowork: None,
+            iguana_necktie: None,
+            extra_usage: None,
+            extra_meters: Default::default(),
+            unknown_fields: Default::default(),
+        }
+    }
+
+    #[test]
+    fn adaptive_interval_uses_base_without_usage() {
+        assert_eq!(adaptive_interval(60, None, now()), 60);
+    }
+
+    #[test]
+    fn adaptive_interval_polls_quickly_around_reset() {
+        assert_eq!(adaptive_interval(60, Some(&usage(Some(120))), now()), 15);
+        assert_eq!(adaptive_interval(60, Some(&usage(Some(-120))), now()), 15);
+    }
+
+    #[test]
+    fn adaptive_interval_wakes_up_before_reset_window() {
+        // リセットの 5 分 20 秒前は、20 秒後に短間隔のウィンドウに入る
+        assert_eq!(adaptive_interval(60, Some(&usage(Some(320))), now()), 20);
+        assert_eq!(adaptive_interval(60, Some(&usage(Some(1800))), now()), 60);
+    }
+
+    #[test]
+    fn adaptive_interval_slows_down_when_idle() {
+        assert_eq!(adaptive_interval(60, Some(&usage(None)), now()), 120);
+        assert_eq!(
+            adaptive_interval(60, Some(&usage(Some(4 * 3600))), now()),
+            120
+        );
+        assert_eq!(adaptive_interval(600, Some(&usage(None)), now()), 600);
+    }
+
+    #[test]
+    fn backoff_keeps_paused_polling_paused() {
+        let until = Some(now() + chrono::Duration::seconds(300));
+        assert_eq!(backoff_secs(0, Some(3), until, now()), 0);
+    }
+
+    #[test]
+    fn backoff_extends_interval_on_battery() {
+        assert_eq!(backoff_secs(60, None, None, now()), 60);
+        assert_eq!(backoff_secs(60, Some(3), None, now()), 180);
+        assert_eq!(
+            backoff_secs(1800, Some(3), None, now()),
+            MAX_STRETCHED_INTERVAL_SECS
+        );
+    }
+
+    #[test]
+    fn backoff_waits_until_rate_limit_resets() {
+        let until = Some(now() + chrono::Duration::seconds(300));
+        assert_eq!(backoff_secs(60, None, until, now()), 300);
+        assert_eq!(backoff_secs(600, None, until, now()), 600);
+
+        let expired = Some(now() - chrono::Duration::seconds(30));
+        assert_eq!(backoff_secs(60, None, expired, now()), 60);
+    }
+
+    #[test]
+    fn merges_pending_refresh_targets() {
+        let (control, _receivers) = PollingControl::new();
+        assert_eq!(control.take_refresh_targets(), Targets::ALL);
+
+        let copilot = Targets::only(health::Provider::Copilot);
+        control.request_refresh(copilot);
+        assert_eq!(control.take_refresh_targets(), copilot);
+
+        control.request_refresh(Targets::only(health::Provider::Claude));
+        control.request_refresh(copilot);
+        assert_eq!(control.take_refresh_targets(), Targets::ALL);
+    }
+
+    #[test]
+    fn re_emits_when_the_reset_countdown_advances() {
+        let at = |minutes| {
+            let mut data = demo::sample_data(now());
+            data.reset_times = reset_time::for_usage(
+                &data.claude,
+                data.copilot.as_ref(),
+                (now() + chrono::Duration::minutes(minutes)).with_timezone(&chrono::Local),
+                i18n::Locale::En,
+            );
+            data
+        };
+        assert!(usage_changed(None, &at(0)));
+        assert!(!usage_changed(Some(&at(0)), &at(0)));
+        assert!(usage_changed(Some(&at(0)), &at(1)));
+    }
+
+    #[test]
+    fn marks_the_restored_snapshot_stale_when_the_first_fetch_fails() {
+        let snapshot = StoredSnapshot {
+            fetched_at: now() - chrono::Duration::hours(8),
+            data: demo::sample_data(now() - chrono::Duration::hours(8)),
+        };
+        let mut state = AppState::restored(Some(snapshot.clone()), reqwest::Client::new());
+        // 最初の取得が終わるまでは、前回終了時のデータであることを `stale` で伝える
+        let restored = state.last_emitted_usage.as_ref().unwrap();
+        assert_eq!(
+            restored.stale.as_ref().map(|s| s.reason.as_str()),
+            Some(crate::RESTORED_REASON)
+        );
+
+        let stale = mark_stale(&mut state, "Network error".to_string(), now()).unwrap();
+        assert_eq!(stale.claude, snapshot.data.claude);
+        assert_eq!(
+            stale.stale,
+            Some(StaleUsage {
+                reason: "Network error".to_string(),
+                failed_at: now(),
+            })
+        );
+        assert_eq!(state.last_fetch_error.as_deref(), Some("Network error"));
+        assert!(is_data_stale(&state, 60, now()));
+
+        // 前回終了時のデータもない場合は送り直すものがない
+        let mut state = AppState::restored(None, reqwest::Client::new());
+        assert_eq!(
+            mark_stale(&mut state, "Network error".to_string(), now()),
+            None
+        );
+        assert!(state.last_fetch_error.is_some());
+    }
+}
@@ -0,0 +1,353 @@
+//! トレイアイコンを左クリックしたときに出す小さなポップアップ。
+//!
+//! バッテリーや音量のフライアウトと同じく、タスクバー (メニューバー) の内側で
+//! アイコンの隣に表示し、フォーカスが外れたら隠す。タスクバーの位置は
+//! モニター全体と作業領域の差から判定する。トレイメニューから開いた場合は
+//! アイコンの位置が分からないので、代わりにマウスカーソルの位置を基準にする。
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager, PhysicalPosition};
+use tokio::sync::Mutex as AsyncMutex;
+
+use crate::{build_frameless_window, set_window_visibility, AppState};
+
+pub const POPUP_LABEL: &str = "popup";
+/// ポップアップの大きさ (論理ピクセル)
+const SIZE: (f64, f64) = (260.0, 150.0);
+/// アイコン・タスクバーとの間隔 (論理ピクセル)
+const GAP: f64 = 8.0;
+/// トレイアイコンのクリックでフォーカスが外れて隠れた直後は、同じクリックで再表示しない
+const REOPEN_GUARD_MS: u64 = 300;
+
+/// フォーカスが外れて隠した時刻
+#[derive(Default)]
+pub struct PopupState {
+    hidden_at: Mutex<Option<Instant>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Area {
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+}
+
+impl Area {
+    fn right(&self) -> i32 {
+        self.x + self.width
+    }
+
+    fn bottom(&self) -> i32 {
+        self.y + self.height
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+/// タスクバーのある辺。作業領域が欠けていない場合 (自動で隠すタスクバーや
+/// 一部の Linux のパネル) はアイコンに最も近いモニターの辺とみなす
+fn taskbar_edge(icon: Area, monitor: Area, work: Area) -> Edge {
+    if work.y > monitor.y {
+        Edge::Top
+    } else if work.bottom() < monitor.bottom() {
+        Edge::Bottom
+    } else if work.x > monitor.x {
+        Edge::Left
+    } else if work.right() < monitor.right() {
+        Edge::Right
+    } else {
+        let cx = icon.x + icon.width / 2;
+        let cy = icon.y + icon.height / 2;
+        [
+            (cy - monitor.y, Edge::Top),
+            (monitor.bottom() - cy, Edge::Bottom),
+            (cx - monitor.x, Edge::Left),
+            (monitor.right() - cx, Edge::Right),
+        ]
+        .into_iter()
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, edge)| edge)
+        .unwrap_or(Edge::Bottom)
+    }
+}
+
+/// ポップアップの左上の位置。タスクバー側の辺に沿ってアイコンの中央に揃え、
+/// 作業領域からはみ出さないようにする
+fn place(icon: Area, size: (i32, i32), monitor: Area, work: Area, gap: i32) -> (i32, i32) {
+    let (width, height) = size;
+    let (x, y) = match taskbar_edge(icon, monitor, work) {
+        Edge::Top => (
+            icon.x + icon.width / 2 - width / 2,
+            work.y.max(icon.bottom()) + gap,
+        ),
+        Edge::Bottom => (
+            icon.x + icon.width / 2 - width / 2,
+            work.bottom().min(icon.y) - gap - height,
+        ),
+        Edge::Left => (
+            work.x.max(icon.right()) + gap,
+            icon.y + icon.height / 2 - height / 2,
+        ),
+        Edge::Right => (
+            work.right().min(icon.x) - gap - width,
+            icon.y + icon.height / 2 - height / 2,
+        ),
+    };
+    let max_x = (work.right() - gap - width).max(work.x);
+    let max_y = (work.bottom() - gap - height).max(work.y);
+    (
+        x.clamp((work.x + gap).min(max_x), max_x),
+        y.clamp((work.y + gap).min(max_y), max_y),
+    )
+}
+
+fn icon_area(rect: &tauri::Rect, scale: f64) -> Area {
+    let position = rect.position.to_physical::<i32>(scale);
+    let size = rect.size.to_physical::<u32>(scale);
+    Area {
+        x: position.x,
+        y: position.y,
+        width: size.width as i32,
+        height: size.height as i32,
+    }
+}
+
+/// 基準にする位置。トレイアイコンの位置、なければマウスカーソルの位置 (物理ピクセル)
+fn anchor_point(window: &tauri::WebviewWindow, rect: Option<&tauri::Rect>) -> Option<(f64, f64)> {
+    match rect {
+        Some(rect) => {
+            let position = rect.position.to_physical::<f64>(1.0);
+            Some((position.x, position.y))
+        }
+        None => window.cursor_position().ok().map(|p| (p.x, p.y)),
+    }
+}
+
+/// メニューから開いた場合のアイコンの代わり。カーソルの位置が取れなければ、
+/// トレイの置かれることが多い作業領域の右上の角とする
+fn fallback_icon(cursor: Option<(f64, f64)>, work: Area) -> Area {
+    let (x, y) = cursor
+        .map(|(x, y)| (x.round() as i32, y.round() as i32))
+        .unwrap_or((work.right(), work.y));
+    Area {
+        x,
+        y,
+        width: 0,
+        height: 0,
+    }
+}
+
+fn position_near(window: &tauri::WebviewWindow, rect: Option<&tauri::Rect>) -> Result<(), String> {
+    // アイコンの位置は物理ピクセルなので、まずはそこにあるモニターを探す
+    let anchor = anchor_point(window, rect);
+    let monitor = anchor
+        .and_then(|(x, y)| window.monitor_from_point(x, y).ok().flatten())
+        .or_else(|| window.primary_monitor().ok().flatten())
+        .ok_or_else(|| "No monitor found for tray icon".to_string())?;
+
+    let scale = monitor.scale_factor();
+    let monitor_area = Area {
+        x: monitor.position().x,
+        y: monitor.position().y,
+        width: monitor.size().width as i32,
+        height: monitor.size().height as i32,
+    };
+    let work = monitor.work_area();
+    let work_area = Area {
+        x: work.position.x,
+        y: work.position.y,
+        width: work.size.width as i32,
+        height: work.size.height as i32,
+    };
+    let icon = match rect {
+        Some(rect) => icon_area(rect, scale),
+        None => fallback_icon(anchor, work_area),
+    };
+    let size = (
+        (SIZE.0 * scale).round() as i32,
+        (SIZE.1 * scale).round() as i32,
+    );
+    let (x, y) = place(
+        icon,
+        size,
+        monitor_area,
+        work_area,
+        (GAP * scale).round() as i32,
+    );
+    window
+        .set_position(PhysicalPosition::new(x, y))
+        .map_err(|e| format!("Failed to move popup: {}", e))
+}
+
+/// トレイアイコンの左クリックとトレイメニューの「Show Popup」。表示中なら隠し、
+/// そうでなければアイコン (`rect` がなければカーソル) の隣に表示する
+pub fn toggle(app: &tauri::AppHandle, rect: Option<tauri::Rect>) -> Result<(), String> {
+    if let Some(w) = app.get_webview_window(POPUP_LABEL) {
+        if w.is_visible().unwrap_or(false) {
+            return hide(app);
+        }
+    }
+    let recently_hidden = app
+        .state::<PopupState>()
+        .hidden_at
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .is_some_and(|at| at.elapsed() < Duration::from_millis(REOPEN_GUARD_MS));
+    if recently_hidden {
+        return Ok(());
+    }
+
+    let window = match app.get_webview_window(POPUP_LABEL) {
+        Some(w) => w,
+        None => build_frameless_window(
+            app,
+            POPUP_LABEL,
+            "popup.html",
+            "Claude Code Usage",
+            SIZE,
+            false,
+        )?,
+    };
+    position_near(&window, rect.as_ref())?;
+    window
+        .show()
+        .map_err(|e| format!("Failed to show popup: {}", e))?;
+    let _ = window.set_focus();
+    set_window_visibility(app, POPUP_LABEL, true);
+
+    // 非表示の間は更新を受け取っていないので、最後の値を渡しておく
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<std::sync::Arc<AsyncMutex<AppState>>>();
+        let latest = state.lock().await.last_emitted_usage.clone();
+        if let Some(data) = latest {
+            let _ = app.emit_to(POPUP_LABEL, "usage-update", &data);
+        }
+    });
+    Ok(())
+}
+
+pub fn hide(app: &tauri::AppHandle) -> Result<(), String> {
+    if let Some(w) = app.get_webview_window(POPUP_LABEL) {
+        w.hide()
+            .map_err(|e| format!("Failed to hide popup: {}", e))?;
+    }
+    set_window_visibility(app, POPUP_LABEL, false);
+    Ok(())
+}
+
+/// フォーカスが外れたら (`WindowEvent::Focused(false)`) 隠す
+pub fn on_focus_lost(window: &tauri::Window) {
+    if window.label() != POPUP_LABEL {
+        return;
+    }
+    let app = window.app_handle();
+    *app.state::<PopupState>()
+        .hidden_at
+        .lock()
+        .unwrap_or_else(|e| e.into_inner()) = Some(Instant::now());
+    if let Err(e) = hide(app) {
+        tracing::warn!("Popup error: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MONITOR: Area = Area {
+        x: 0,
+        y: 0,
+        width: 1920,
+        height: 1080,
+    };
+
+    fn icon(x: i32, y: i32) -> Area {
+        Area {
+            x,
+            y,
+            width: 24,
+            height: 24,
+        }
+    }
+
+    #[test]
+    fn places_above_bottom_taskbar_and_below_top_menu_bar() {
+        // Windows: 下のタスクバー (高さ 48)。右端のアイコンでも画面内に収める
+        let work = Area {
+            height: 1032,
+            ..MONITOR
+        };
+        assert_eq!(
+            place(icon(1880, 1044), (260, 150), MONITOR, work, 8),
+            (1652, 874)
+        );
+
+        // macOS: 上のメニューバー (高さ 24)
+        let work = Area {
+            y: 24,
+            height: 1056,
+            ..MONITOR
+        };
+        assert_eq!(
+            place(icon(1500, 0), (260, 150), MONITOR, work, 8),
+            (1382, 32)
+        );
+    }
+
+    #[test]
+    fn places_beside_vertical_taskbar() {
+        let work = Area {
+            x: 60,
+            width: 1860,
+            ..MONITOR
+        };
+        assert_eq!(taskbar_edge(icon(18, 900), MONITOR, work), Edge::Left);
+        assert_eq!(
+            place(icon(18, 900), (260, 150), MONITOR, work, 8),
+            (68, 837)
+        );
+    }
+
+    #[test]
+    fn falls_back_to_nearest_edge_without_reserved_area() {
+        assert_eq!(taskbar_edge(icon(1880, 4), MONITOR, MONITOR), Edge::Top);
+        assert_eq!(
+            taskbar_edge(icon(900, 1050), MONITOR, MONITOR),
+            Edge::Bottom
+        );
+    }
+
+    #[test]
+    fn places_menu_opened_popup_near_cursor_or_top_right() {
+        // GNOME などの上のパネル (高さ 32) からメニューで開いた場合
+        let work = Area {
+            y: 32,
+            height: 1048,
+            ..MONITOR
+        };
+        let cursor = fallback_icon(Some((1700.4, 12.0)), work);
+        assert_eq!(
+            cursor,
+            Area {
+                x: 1700,
+                y: 12,
+                width: 0,
+                height: 0
+            }
+        );
+        assert_eq!(place(cursor, (260, 150), MONITOR, work, 8), (1570, 40));
+
+        // カーソルの位置が取れない場合は作業領域の右上に出す
+        let corner = fallback_icon(None, work);
+        assert_eq!(place(corner, (260, 150), MONITOR, work, 8), (1652, 40));
+    }
+}
@@ -0,0 +1,247 @@
+//! スリープからの復帰の検知。
+//!
+//! OS の電源イベント (Windows は `PowerRegisterSuspendResumeNotification` で
+//! WM_POWERBROADCAST と同じ通知、macOS は NSWorkspace の `didWakeNotification`、
+//! Linux は logind の `PrepareForSleep` シグナル) を受け取り、復帰したら
+//! `resumed` イベントを送ってすぐに取得し直す。通知を受け取れない環境
+//! (logind や `gdbus` のない Linux など) に限り、壁時計の飛びから復帰を推定する。
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tauri::{Emitter, Manager};
+
+use crate::PollingControl;
+
+/// 通知を受け取れない場合の確認間隔。壁時計がこれ以上に進んでいたら復帰とみなす
+const FALLBACK_CHECK_INTERVAL_SECS: u64 = 15;
+const FALLBACK_GAP_THRESHOLD_SECS: u64 = 60;
+
+/// スリープに入った時刻。`resumed` で眠っていた秒数を伝えるのに使う
+static SLEPT_AT: Mutex<Option<SystemTime>> = Mutex::new(None);
+
+fn on_sleep() {
+    *SLEPT_AT.lock().unwrap_or_else(|e| e.into_inner()) = Some(SystemTime::now());
+}
+
+/// 復帰したらすぐに取得し直す。`resumed` の値は眠っていた秒数 (不明な場合は 0)
+fn on_resume(app: &tauri::AppHandle) {
+    let slept_secs = SLEPT_AT
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take()
+        .and_then(|at| at.elapsed().ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    tracing::info!(
+        "Resumed from sleep ({}s), triggering refresh...",
+        slept_secs
+    );
+    let _ = app.emit("resumed", slept_secs);
+    app.state::<Arc<PollingControl>>()
+        .refresh_notify
+        .notify_one();
+}
+
+/// 電源イベントの監視を始める。使えない場合は壁時計による推定に切り替える
+pub fn spawn_resume_listener(app: tauri::AppHandle) {
+    if let Err(e) = listen(&app) {
+        tracing::warn!(
+            "Power notifications unavailable ({}), detecting resume from clock gaps",
+            e
+        );
+        spawn_clock_gap_fallback(app);
+    }
+}
+
+/// 壁時計の飛びによる推定。短いスリープは検知できず、長く処理が止まった場合にも
+/// 反応するため、電源イベントを受け取れない場合にだけ使う
+fn spawn_clock_gap_fallback(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut last = SystemTime::now();
+        loop {
+            tokio::time::sleep(Duration::from_secs(FALLBACK_CHECK_INTERVAL_SECS)).await;
+            let now = SystemTime::now();
+            let elapsed = now.duration_since(last).unwrap_or_default();
+            last = now;
+
+            if elapsed.as_secs() > FALLBACK_CHECK_INTERVAL_SECS + FALLBACK_GAP_THRESHOLD_SECS {
+                *SLEPT_AT.lock().unwrap_or_else(|e| e.into_inner()) = Some(last - elapsed);
+                on_resume(&app);
+            }
+        }
+    });
+}
+
+#[cfg(target_os = "windows")]
+fn listen(app: &tauri::AppHandle) -> Result<(), String> {
+    use std::sync::OnceLock;
+    use windows_sys::Win32::System::Power::{
+        PowerRegisterSuspendResumeNotification, DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        DEVICE_NOTIFY_CALLBACK, PBT_APMRESUMEAUTOMATIC, PBT_APMSUSPEND,
+    };
+
+    static APP: OnceLock<tauri::AppHandle> = OnceLock::new();
+
+    unsafe extern "system" fn callback(
+        _context: *const std::ffi::c_void,
+        event: u32,
+        _setting: *const std::ffi::c_void,
+    ) -> u32 {
+        match event {
+            PBT_APMSUSPEND => on_sleep(),
+            // 復帰時は必ず届く PBT_APMRESUMEAUTOMATIC だけを扱う
+            PBT_APMRESUMEAUTOMATIC => {
+                if let Some(app) = APP.get() {
+                    on_resume(app);
+                }
+            }
+            _ => {}
+        }
+        0
+    }
+
+    let _ = APP.set(app.clone());
+    // 登録はアプリの終了まで残すので、パラメーターは解放しない
+    let params = Box::leak(Box::new(DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS {
+        Callback: Some(callback),
+        Context: std::ptr::null_mut(),
+    }));
+    let mut handle = std::ptr::null_mut();
+    // SAFETY: params は 'static で、DEVICE_NOTIFY_CALLBACK の場合は recipient に
+    // DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS へのポインタを渡す仕様。
+    let status = unsafe {
+        PowerRegisterSuspendResumeNotification(
+            DEVICE_NOTIFY_CALLBACK,
+            params as *mut DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS as _,
+            &mut handle,
+        )
+    };
+    if status != 0 {
+        return Err(format!(
+            "PowerRegisterSuspendResumeNotification failed ({})",
+            status
+        ));
+    }
+    Ok(())
+}
+
+/// メインスレッド (`setup`) から呼ぶ。通知はメインスレッドで届く
+#[cfg(target_os = "macos")]
+fn listen(app: &tauri::AppHandle) -> Result<(), String> {
+    use block2::RcBlock;
+    use objc2_app_kit::{
+        NSWorkspace, NSWorkspaceDidWakeNotification, NSWorkspaceWillSleepNotification,
+    };
+    use objc2_foundation::NSNotification;
+    use std::ptr::NonNull;
+
+    let center = NSWorkspace::sharedWorkspace().notificationCenter();
+    let app = app.clone();
+    let on_wake = RcBlock::new(move |_: NonNull<NSNotification>| on_resume(&app));
+    let on_will_sleep = RcBlock::new(|_: NonNull<NSNotification>| on_sleep());
+    // SAFETY: 通知名は AppKit の定数で、ブロックはアプリの状態以外を参照しない。
+    // 監視はアプリの終了まで続けるので、戻り値のオブザーバーは解放しない。
+    unsafe {
+        std::mem::forget(center.addObserverForName_object_queue_usingBlock(
+            Some(NSWorkspaceDidWakeNotification),
+            None,
+            None,
+            &on_wake,
+        ));
+        std::mem::forget(center.addObserverForName_object_queue_usingBlock(
+            Some(NSWorkspaceWillSleepNotification),
+            None,
+            None,
+            &on_will_sleep,
+        ));
+    }
+    Ok(())
+}
+
+/// logind の `PrepareForSleep` シグナルを `gdbus monitor` で受け取る。
+/// 監視が途中で終わった場合は壁時計による推定に切り替える
+#[cfg(target_os = "linux")]
+fn listen(app: &tauri::AppHandle) -> Result<(), String> {
+    use std::io::{BufRead, BufReader};
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new("gdbus")
+        .args([
+            "monitor",
+            "--system",
+            "--dest",
+            "org.freedesktop.login1",
+            "--object-path",
+            "/org/freedesktop/login1",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to run gdbus: {}", e))?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| "gdbus has no stdout".to_string())?;
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            match parse_prepare_for_sleep(&line) {
+                Some(true) => on_sleep(),
+                Some(false) => on_resume(&app),
+                None => {}
+            }
+        }
+        let _ = child.wait();
+        tracing::warn!("logind monitor exited, detecting resume from clock gaps");
+        spawn_clock_gap_fallback(app);
+    });
+    Ok(())
+}
+
+/// `gdbus monitor` の出力の
+/// `/org/freedesktop/login1: org.freedesktop.login1.Manager.PrepareForSleep (true,)` を読む。
+/// `true` はスリープに入るとき、`false` は復帰したとき
+#[cfg(any(target_os = "linux", test))]
+fn parse_prepare_for_sleep(line: &str) -> Option<bool> {
+    let args = line
+        .split_once("org.freedesktop.login1.Manager.PrepareForSleep")?
+        .1
+        .trim();
+    match args.trim_start_matches('(').trim_end_matches(')') {
+        "true," | "true" => Some(true),
+        "false," | "false" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+fn listen(_app: &tauri::AppHandle) -> Result<(), String> {
+    Err("Unsupported platform".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_logind_prepare_for_sleep() {
+        let prefix = "/org/freedesktop/login1: org.freedesktop.login1.Manager.PrepareForSleep";
+        assert_eq!(
+            parse_prepare_for_sleep(&format!("{} (true,)", prefix)),
+            Some(true)
+        );
+        assert_eq!(
+            parse_prepare_for_sleep(&format!("{} (false,)", prefix)),
+            Some(false)
+        );
+        assert_eq!(
+            parse_prepare_for_sleep(
+                "/org/freedesktop/login1: org.freedesktop.login1.Manager.SessionNew ('3', objectpath '/org/freedesktop/login1/session/_33')"
+            ),
+            None
+        );
+    }
+}
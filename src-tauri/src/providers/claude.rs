@@ -0,0 +1,298 @@
+//! Claude の使用量 API (`/api/oauth/usage`)。
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Mutex;
+
+use crate::rate_limit::RateLimitInfo;
+
+pub const API_BASE: &str = "https://api.anthropic.com";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageMeter {
+    pub utilization: f64,
+    pub resets_at: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtraUsage {
+    pub is_enabled: bool,
+    pub monthly_limit: f64,
+    pub used_credits: f64,
+    pub utilization: f64,
+    /// `used_credits` から算出した超過料金の見積もり (USD)。API の応答には含まれない
+    #[serde(default)]
+    pub estimated_cost: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageData {
+    pub five_hour: UsageMeter,
+    pub seven_day: UsageMeter,
+    #[serde(default)]
+    pub seven_day_oauth_apps: Option<UsageMeter>,
+    #[serde(default)]
+    pub seven_day_opus: Option<UsageMeter>,
+    #[serde(default)]
+    pub seven_day_sonnet: Option<UsageMeter>,
+    #[serde(default)]
+    pub seven_day_cowork: Option<UsageMeter>,
+    #[serde(default)]
+    pub iguana_necktie: Option<serde_json::Value>,
+    #[serde(default)]
+    pub extra_usage: Option<ExtraUsage>,
+    /// 名前を知らないメーター (`utilization` を持つオブジェクト)。API に追加された新しい枠
+    #[serde(default)]
+    pub extra_meters: BTreeMap<String, UsageMeter>,
+    /// モデル化していないその他のフィールド
+    #[serde(flatten)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// 警告済みの未知のフィールド名。取得のたびに同じ警告を出さない
+static WARNED_FIELDS: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+fn warn_once(name: &str, message: &str) {
+    let mut warned = WARNED_FIELDS.lock().unwrap_or_else(|e| e.into_inner());
+    if warned
+        .get_or_insert_with(HashSet::new)
+        .insert(name.to_string())
+    {
+        tracing::warn!(field = name, "{}", message);
+    }
+}
+
+/// `utilization` を数値で持つオブジェクトをメーターとみなす
+fn as_meter(value: &serde_json::Value) -> Option<UsageMeter> {
+    value.get("utilization")?.as_f64()?;
+    serde_json::from_value(value.clone()).ok()
+}
+
+impl UsageData {
+    /// 未知のフィールドのうちメーターの形をしたものを `extra_meters` に移す
+    fn collect_extra_meters(&mut self) {
+        let names: Vec<String> = self.unknown_fields.keys().cloned().collect();
+        for name in names {
+            match as_meter(&self.unknown_fields[&name]) {
+                Some(meter) => {
+                    warn_once(&name, "Unknown usage meter in API response");
+                    self.unknown_fields.remove(&name);
+                    self.extra_meters.insert(name, meter);
+                }
+                None => warn_once(&name, "Unknown field in API response"),
+            }
+        }
+    }
+}
+
+/// 使用量を取得する。成功・失敗どちらの応答でもレート制限ヘッダーを併せて返す
+pub async fn fetch_usage(
+    client: &reqwest::Client,
+    token: &str,
+) -> (Result<UsageData, String>, Option<RateLimitInfo>) {
+    fetch_usage_from(client, API_BASE, token).await
+}
+
+/// `base_url` の API から取得する。テストではモックサーバーの URL を渡す
+#[tracing::instrument(name = "fetch_claude", skip_all)]
+pub async fn fetch_usage_from(
+    client: &reqwest::Client,
+    base_url: &str,
+    token: &str,
+) -> (Result<UsageData, String>, Option<RateLimitInfo>) {
+    let resp = match client
+        .get(format!("{}/api/oauth/usage", base_url))
+        .header("Authorization", format!("Bearer {}", token))
+        .header("Accept", "application/json")
+        .header("Content-Type", "application/json")
+        .header("anthropic-beta", "oauth-2025-04-20")
+        .send()
+        .await
+    {
+        Ok(resp) => resp,
+        // Avoid leaking token through reqwest error details
+        Err(e) => {
+            return (
+                Err(format!("HTTP request failed: {}", e.without_url())),
+                None,
+            )
+        }
+    };
+
+    let rate_limit = RateLimitInfo::from_headers(resp.headers(), chrono::Utc::now());
+    (read_usage_response(resp).await, rate_limit)
+}
+
+async fn read_usage_response(resp: reqwest::Response) -> Result<UsageData, String> {
+    let status = resp.status();
+    tracing::debug!(%status, "Claude API responded");
+    if !status.is_success() {
+        let body = resp.text().await.unwrap_or_else(|_| "<unreadable>".into());
+        return Err(format!("API returned status {}: {}", status, body));
+    }
+
+    let body = resp
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response body: {}", e))?;
+
+    let truncated: String = body.chars().take(500).collect();
+    let mut usage = tracing::debug_span!("parse")
+        .in_scope(|| serde_json::from_str::<UsageData>(&body))
+        .map_err(|e| format!("Failed to parse response: {}. Body: {}", e, truncated))?;
+    usage.collect_extra_meters();
+    Ok(usage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn usage_body() -> serde_json::Value {
+        json!({
+            "five_hour": { "utilization": 42.0, "resets_at": "2026-01-01T05:00:00+00:00" },
+            "seven_day": { "utilization": 12.5, "resets_at": null },
+            "seven_day_opus": { "utilization": 3.0, "resets_at": null },
+            "extra_usage": {
+                "is_enabled": true,
+                "monthly_limit": 5000.0,
+                "used_credits": 1250.0,
+                "utilization": 25.0
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn parses_usage_response() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/oauth/usage"))
+            .and(header("Authorization", "Bearer test-token"))
+            .and(header("anthropic-beta", "oauth-2025-04-20"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(usage_body()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let (result, rate_limit) = fetch_usage_from(&client, &server.uri(), "test-token").await;
+        let usage = result.unwrap();
+
+        assert_eq!(usage.five_hour.utilization, 42.0);
+        assert_eq!(usage.seven_day.resets_at, None);
+        assert_eq!(usage.seven_day_opus.map(|m| m.utilization), Some(3.0));
+        assert_eq!(usage.seven_day_sonnet, None);
+        let extra = usage.extra_usage.unwrap();
+        assert_eq!(extra.used_credits, 1250.0);
+        assert_eq!(extra.estimated_cost, None);
+        assert_eq!(rate_limit, None);
+        assert!(usage.extra_meters.is_empty());
+        assert!(usage.unknown_fields.is_empty());
+    }
+
+    #[tokio::test]
+    async fn captures_unknown_meters() {
+        let mut body = usage_body();
+        body["seven_day_haiku"] = json!({ "utilization": 7.5, "resets_at": null });
+        body["seven_day_labs"] = json!({ "utilization": 1.0 });
+        body["new_flag"] = json!({ "enabled": true });
+        body["iguana_necktie"] = json!(null);
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let (result, _) = fetch_usage_from(&client, &server.uri(), "token").await;
+        let usage = result.unwrap();
+
+        assert_eq!(
+            usage.extra_meters.keys().collect::<Vec<_>>(),
+            ["seven_day_haiku", "seven_day_labs"]
+        );
+        assert_eq!(usage.extra_meters["seven_day_haiku"].utilization, 7.5);
+        assert_eq!(usage.extra_meters["seven_day_labs"].resets_at, None);
+        assert_eq!(
+            usage.unknown_fields.keys().collect::<Vec<_>>(),
+            ["new_flag"]
+        );
+
+        // スナップショットとして保存・復元しても同じ内容になる
+        let restored: UsageData =
+            serde_json::from_str(&serde_json::to_string(&usage).unwrap()).unwrap();
+        assert_eq!(restored, usage);
+    }
+
+    #[tokio::test]
+    async fn maps_error_status_with_body() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(401).set_body_string("invalid token"))
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let (result, _) = fetch_usage_from(&client, &server.uri(), "expired").await;
+
+        assert_eq!(
+            result.unwrap_err(),
+            "API returned status 401 Unauthorized: invalid token"
+        );
+    }
+
+    #[tokio::test]
+    async fn maps_unexpected_body_to_parse_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("{\"five_hour\": null}"))
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let (result, _) = fetch_usage_from(&client, &server.uri(), "token").await;
+
+        let err = result.unwrap_err();
+        assert!(err.starts_with("Failed to parse response"), "{}", err);
+        assert!(err.ends_with("Body: {\"five_hour\": null}"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn reads_rate_limit_headers_from_429() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(429)
+                    .insert_header("retry-after", "120")
+                    .insert_header("anthropic-ratelimit-requests-limit", "50")
+                    .insert_header("anthropic-ratelimit-requests-remaining", "0")
+                    .set_body_string("rate limited"),
+            )
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let before = chrono::Utc::now();
+        let (result, rate_limit) = fetch_usage_from(&client, &server.uri(), "token").await;
+
+        assert!(result.unwrap_err().contains("429"));
+        let rate_limit = rate_limit.unwrap();
+        assert_eq!(rate_limit.retry_after_secs, Some(120));
+        assert_eq!(rate_limit.requests_remaining, Some(0));
+        assert!(rate_limit.is_limited(before + chrono::Duration::seconds(60)));
+        assert!(!rate_limit.is_limited(before + chrono::Duration::seconds(180)));
+    }
+
+    #[tokio::test]
+    async fn maps_connection_failure() {
+        let client = reqwest::Client::new();
+        let (result, rate_limit) = fetch_usage_from(&client, "http://127.0.0.1:9", "token").await;
+
+        assert!(result.unwrap_err().starts_with("HTTP request failed"));
+        assert_eq!(rate_limit, None);
+    }
+}
@@ -0,0 +1,525 @@
+//! GitHub Copilot のプレミアムリクエストの使用量とプランの判定。
+
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::github_auth::{GitHubAuthSource, GitHubCredentials};
+use crate::GitHubConfig;
+
+pub const API_BASE: &str = "https://api.github.com";
+
+/// トークンが失効している・スコープが足りないなど、GitHub に認証を拒否された (401 / 403)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CopilotAuthError {
+    pub status: u16,
+    /// 拒否されたトークンの取得元。個別の取得では `None`
+    pub source: Option<GitHubAuthSource>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CopilotError {
+    /// 再認証 (PAT の再設定や `gh auth refresh`) が必要
+    Auth(CopilotAuthError),
+    Other(String),
+}
+
+impl std::fmt::Display for CopilotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Auth(e) => write!(
+                f,
+                "GitHub authentication failed ({}): {}",
+                e.status, e.message
+            ),
+            Self::Other(e) => f.write_str(e),
+        }
+    }
+}
+
+impl From<String> for CopilotError {
+    fn from(e: String) -> Self {
+        Self::Other(e)
+    }
+}
+
+impl From<&str> for CopilotError {
+    fn from(e: &str) -> Self {
+        Self::Other(e.to_string())
+    }
+}
+
+/// Copilot のプランと月間プレミアムリクエスト数の上限
+#[derive(Debug, Clone, Serialize)]
+pub struct CopilotPlanInfo {
+    /// "free" / "pro" / "pro_plus" / "business" / "enterprise"。判定できなかった場合は `None`
+    pub plan: Option<String>,
+    pub monthly_limit: f64,
+}
+
+/// プランを判定できなかった場合の上限 (Pro / Business と同じ)
+pub const DEFAULT_COPILOT_MONTHLY_LIMIT: f64 = 300.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CopilotUsageItem {
+    pub model: String,
+    pub gross_quantity: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CopilotUsageData {
+    pub total_requests: f64,
+    pub monthly_limit: f64,
+    pub utilization: f64,
+    pub resets_at: String,
+    pub items: Vec<CopilotUsageItem>,
+}
+
+pub fn calculate_next_month_reset() -> String {
+    use chrono::{Datelike, TimeZone, Utc};
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let datetime = chrono::DateTime::<Utc>::from_timestamp(now as i64, 0).unwrap();
+
+    let next_month = if datetime.month() == 12 {
+        Utc.with_ymd_and_hms(datetime.year() + 1, 1, 1, 0, 0, 0)
+            .unwrap()
+    } else {
+        Utc.with_ymd_and_hms(datetime.year(), datetime.month() + 1, 1, 0, 0, 0)
+            .unwrap()
+    };
+
+    next_month.to_rfc3339()
+}
+
+#[tracing::instrument(name = "fetch_copilot", skip(client, base_url, token, monthly_limit))]
+async fn fetch_copilot_usage(
+    client: &reqwest::Client,
+    base_url: &str,
+    username: &str,
+    token: &str,
+    monthly_limit: f64,
+) -> Result<CopilotUsageData, CopilotError> {
+    let url = format!(
+        "{}/users/{}/settings/billing/premium_request/usage",
+        base_url, username
+    );
+
+    let resp = client
+        .get(&url)
+        .header("Authorization", format!("token {}", token))
+        .header("Accept", "application/vnd.github+json")
+        .header("X-GitHub-Api-Version", "2022-11-28")
+        .header("User-Agent", "tauri-usage-dashboard")
+        .send()
+        .await
+        .map_err(|e| format!("GitHub API request failed: {}", e.without_url()))?;
+
+    tracing::debug!(status = %resp.status(), "GitHub API responded");
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let body = resp.text().await.unwrap_or_else(|_| "<unreadable>".into());
+        if matches!(status.as_u16(), 401 | 403) {
+            return Err(CopilotError::Auth(CopilotAuthError {
+                status: status.as_u16(),
+                source: None,
+                message: body,
+            }));
+        }
+        return Err(format!("GitHub API status {}: {}", status, body).into());
+    }
+
+    let body = resp
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read GitHub response: {}", e))?;
+
+    let api_response: serde_json::Value = tracing::debug_span!("parse")
+        .in_scope(|| serde_json::from_str(&body))
+        .map_err(|e| format!("Failed to parse GitHub response: {}", e))?;
+
+    let items = api_response["usageItems"]
+        .as_array()
+        .ok_or("Missing usageItems array")?;
+
+    let mut total_requests = 0.0;
+    let mut usage_items = Vec::new();
+
+    for item in items {
+        if let Some(quantity) = item["grossQuantity"].as_f64() {
+            total_requests += quantity;
+            if let Some(model) = item["model"].as_str() {
+                usage_items.push(CopilotUsageItem {
+                    model: model.to_string(),
+                    gross_quantity: quantity,
+                });
+            }
+        }
+    }
+
+    let utilization = (total_requests / monthly_limit) * 100.0;
+    let resets_at = calculate_next_month_reset();
+
+    Ok(CopilotUsageData {
+        total_requests,
+        monthly_limit,
+        utilization,
+        resets_at,
+        items: usage_items,
+    })
+}
+
+/// 認証情報を順に試し、最初に成功した結果を返す。すべて失敗した場合は最後のエラー。
+pub async fn fetch_copilot_with_fallback(
+    client: &reqwest::Client,
+    candidates: &[GitHubCredentials],
+    monthly_limit: f64,
+) -> Result<CopilotUsageData, CopilotError> {
+    fetch_with_fallback_from(client, API_BASE, candidates, monthly_limit).await
+}
+
+/// `base_url` の API から取得する。テストではモックサーバーの URL を渡す
+async fn fetch_with_fallback_from(
+    client: &reqwest::Client,
+    base_url: &str,
+    candidates: &[GitHubCredentials],
+    monthly_limit: f64,
+) -> Result<CopilotUsageData, CopilotError> {
+    let mut last_error = CopilotError::from("No GitHub credentials available");
+    for credentials in candidates {
+        let gh = &credentials.config;
+        match fetch_copilot_usage(client, base_url, &gh.username, &gh.token, monthly_limit).await {
+            Ok(data) => return Ok(data),
+            Err(mut e) => {
+                tracing::debug!(source = ?credentials.source, "Copilot fetch failed: {}", e);
+                if let CopilotError::Auth(auth) = &mut e {
+                    auth.source = Some(credentials.source);
+                }
+                last_error = e;
+            }
+        }
+    }
+    Err(last_error)
+}
+
+/// GitHub の `copilot_plan` の値に対応するプラン名と月間プレミアムリクエスト数
+fn copilot_plan_limit(plan: &str) -> Option<(&'static str, f64)> {
+    match plan {
+        "free" => Some(("free", 50.0)),
+        "individual" => Some(("pro", 300.0)),
+        "individual_pro" => Some(("pro_plus", 1500.0)),
+        "business" => Some(("business", 300.0)),
+        "enterprise" => Some(("enterprise", 1000.0)),
+        _ => None,
+    }
+}
+
+#[tracing::instrument(name = "detect_copilot_plan", skip_all)]
+async fn detect_copilot_plan(
+    client: &reqwest::Client,
+    base_url: &str,
+    token: &str,
+) -> Result<CopilotPlanInfo, String> {
+    let resp = client
+        .get(format!("{}/copilot_internal/user", base_url))
+        .header("Authorization", format!("token {}", token))
+        .header("Accept", "application/json")
+        .header("User-Agent", "tauri-usage-dashboard")
+        .send()
+        .await
+        .map_err(|e| format!("GitHub API request failed: {}", e.without_url()))?;
+
+    if !resp.status().is_success() {
+        return Err(format!("GitHub API status {}", resp.status()));
+    }
+
+    let body: serde_json::Value = resp
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse Copilot plan: {}", e))?;
+
+    let plan = body["copilot_plan"].as_str();
+    let known = plan.and_then(copilot_plan_limit);
+    // 割り当て数が返ってくる場合はプラン名からの推定より優先する
+    let entitlement = body["quota_snapshots"]["premium_interactions"]["entitlement"]
+        .as_f64()
+        .filter(|e| *e > 0.0);
+    let monthly_limit = entitlement
+        .or(known.map(|(_, limit)| limit))
+        .ok_or_else(|| format!("Unknown Copilot plan: {}", plan.unwrap_or("none")))?;
+
+    Ok(CopilotPlanInfo {
+        plan: known
+            .map(|(name, _)| name.to_string())
+            .or(plan.map(String::from)),
+        monthly_limit,
+    })
+}
+
+/// 手動で設定した上限があればそれを、なければ判定したプランの上限を返す。
+/// 判定に失敗した場合は既定値を返すが、`cached` には残さず次の取得で判定し直す。
+pub async fn copilot_monthly_limit(
+    client: &reqwest::Client,
+    gh: &GitHubConfig,
+    cached: &mut Option<CopilotPlanInfo>,
+) -> f64 {
+    monthly_limit_from(client, API_BASE, gh, cached).await
+}
+
+async fn monthly_limit_from(
+    client: &reqwest::Client,
+    base_url: &str,
+    gh: &GitHubConfig,
+    cached: &mut Option<CopilotPlanInfo>,
+) -> f64 {
+    if let Some(limit) = gh.monthly_limit {
+        return limit;
+    }
+    if let Some(info) = cached {
+        return info.monthly_limit;
+    }
+
+    match detect_copilot_plan(client, base_url, &gh.token).await {
+        Ok(info) => cached.insert(info).monthly_limit,
+        Err(e) => {
+            tracing::warn!(
+                default_limit = DEFAULT_COPILOT_MONTHLY_LIMIT,
+                "Copilot plan detection failed, using the default monthly limit until it succeeds \
+                 (set the limit manually to override): {}",
+                e
+            );
+            DEFAULT_COPILOT_MONTHLY_LIMIT
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const USAGE_PATH: &str = "/users/octocat/settings/billing/premium_request/usage";
+
+    fn github_config(token: &str, monthly_limit: Option<f64>) -> GitHubConfig {
+        GitHubConfig {
+            username: "octocat".to_string(),
+            token: token.to_string(),
+            monthly_limit,
+            monthly_limit_override: monthly_limit.is_some(),
+        }
+    }
+
+    fn credentials(token: &str, source: GitHubAuthSource) -> GitHubCredentials {
+        GitHubCredentials {
+            config: github_config(token, None),
+            source,
+        }
+    }
+
+    #[tokio::test]
+    async fn parses_premium_request_usage() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(USAGE_PATH))
+            .and(header("Authorization", "token ghp_test"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "usageItems": [
+                    { "model": "Claude Sonnet 4", "grossQuantity": 120.0 },
+                    { "model": "GPT-4.1", "grossQuantity": 30.0 },
+                    { "grossQuantity": 6.0 },
+                    { "model": "o3" }
+                ]
+            })))
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let data = fetch_copilot_usage(&client, &server.uri(), "octocat", "ghp_test", 300.0)
+            .await
+            .unwrap();
+
+        assert_eq!(data.total_requests, 156.0);
+        assert_eq!(data.utilization, 52.0);
+        assert_eq!(data.items.len(), 2);
+        assert_eq!(data.items[0].model, "Claude Sonnet 4");
+    }
+
+    #[tokio::test]
+    async fn maps_error_status_and_missing_items() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(header("Authorization", "token bad"))
+            .respond_with(ResponseTemplate::new(403).set_body_string("Forbidden"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(header("Authorization", "token ok"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let err = fetch_copilot_usage(&client, &server.uri(), "octocat", "bad", 300.0)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "GitHub authentication failed (403): Forbidden"
+        );
+
+        let err = fetch_copilot_usage(&client, &server.uri(), "octocat", "ok", 300.0)
+            .await
+            .unwrap_err();
+        assert_eq!(err, CopilotError::from("Missing usageItems array"));
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_next_credentials() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(header("Authorization", "token gh-cli"))
+            .respond_with(ResponseTemplate::new(404).set_body_string("Not Found"))
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(header("Authorization", "token pat"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "usageItems": [{ "model": "GPT-4.1", "grossQuantity": 10.0 }]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let candidates = [
+            credentials("gh-cli", GitHubAuthSource::GhCli),
+            credentials("pat", GitHubAuthSource::Config),
+        ];
+        let data = fetch_with_fallback_from(&client, &server.uri(), &candidates, 300.0)
+            .await
+            .unwrap();
+        assert_eq!(data.total_requests, 10.0);
+
+        let err = fetch_with_fallback_from(&client, &server.uri(), &candidates[..1], 300.0).await;
+        assert!(matches!(err, Err(CopilotError::Other(_))));
+        let err = fetch_with_fallback_from(&client, &server.uri(), &[], 300.0).await;
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "No GitHub credentials available"
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_rejected_token_with_its_source() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(header("Authorization", "token gh-cli"))
+            .respond_with(ResponseTemplate::new(404).set_body_string("Not Found"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(header("Authorization", "token revoked"))
+            .respond_with(ResponseTemplate::new(401).set_body_string("Bad credentials"))
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let candidates = [
+            credentials("gh-cli", GitHubAuthSource::GhCli),
+            credentials("revoked", GitHubAuthSource::Config),
+        ];
+        let err = fetch_with_fallback_from(&client, &server.uri(), &candidates, 300.0)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err,
+            CopilotError::Auth(CopilotAuthError {
+                status: 401,
+                source: Some(GitHubAuthSource::Config),
+                message: "Bad credentials".to_string(),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn detects_plan_and_prefers_entitlement() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/copilot_internal/user"))
+            .and(header("Authorization", "token pro"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "copilot_plan": "individual"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/copilot_internal/user"))
+            .and(header("Authorization", "token business"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "copilot_plan": "business",
+                "quota_snapshots": { "premium_interactions": { "entitlement": 500.0 } }
+            })))
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let pro = detect_copilot_plan(&client, &server.uri(), "pro")
+            .await
+            .unwrap();
+        assert_eq!(pro.plan.as_deref(), Some("pro"));
+        assert_eq!(pro.monthly_limit, 300.0);
+
+        let business = detect_copilot_plan(&client, &server.uri(), "business")
+            .await
+            .unwrap();
+        assert_eq!(business.plan.as_deref(), Some("business"));
+        assert_eq!(business.monthly_limit, 500.0);
+    }
+
+    #[tokio::test]
+    async fn retries_detection_after_a_failure() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/copilot_internal/user"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "copilot_plan": "unknown_plan"
+            })))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let gh = github_config("token", None);
+        let mut cached = None;
+        let limit = monthly_limit_from(&client, &server.uri(), &gh, &mut cached).await;
+        assert_eq!(limit, DEFAULT_COPILOT_MONTHLY_LIMIT);
+        assert!(cached.is_none());
+
+        // 失敗は残さないので、次の取得で判定し直す
+        let limit = monthly_limit_from(&client, &server.uri(), &gh, &mut cached).await;
+        assert_eq!(limit, DEFAULT_COPILOT_MONTHLY_LIMIT);
+        assert!(cached.is_none());
+    }
+
+    #[tokio::test]
+    async fn manual_limit_skips_detection() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let gh = github_config("token", Some(1000.0));
+        let limit = monthly_limit_from(&client, &server.uri(), &gh, &mut None).await;
+        assert_eq!(limit, 1000.0);
+    }
+}
@@ -0,0 +1,6 @@
+//! 使用量を取得する各プロバイダーの API クライアント。
+//!
+//! 接続先は `API_BASE` で、テストではベース URL を差し替えてモックサーバーに向ける。
+
+pub mod claude;
+pub mod copilot;
@@ -0,0 +1,87 @@
+//! Anthropic API のレート制限ヘッダー (`anthropic-ratelimit-*` / `retry-after`) の解析。
+//!
+//! 使用量 API の応答 (エラーを含む) から読み取り、ポーリングの待機時間の決定と
+//! 「Rate limited until HH:MM」の表示に使う。
+
+use chrono::{DateTime, Duration, Utc};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RateLimitInfo {
+    pub requests_limit: Option<u64>,
+    pub requests_remaining: Option<u64>,
+    pub requests_reset: Option<DateTime<Utc>>,
+    pub tokens_limit: Option<u64>,
+    pub tokens_remaining: Option<u64>,
+    pub tokens_reset: Option<DateTime<Utc>>,
+    pub retry_after_secs: Option<u64>,
+    /// 次に取得を試みてよい時刻。制限されていない場合は `None`
+    pub limited_until: Option<DateTime<Utc>>,
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name)?.to_str().ok().map(str::trim)
+}
+
+fn header_u64(headers: &HeaderMap, name: &str) -> Option<u64> {
+    header_str(headers, name)?.parse().ok()
+}
+
+fn header_time(headers: &HeaderMap, name: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(header_str(headers, name)?)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+/// `retry-after` は秒数または HTTP 日付
+fn retry_after_secs(headers: &HeaderMap, now: DateTime<Utc>) -> Option<u64> {
+    let value = header_str(headers, "retry-after")?;
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(secs);
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?;
+    Some((at.with_timezone(&Utc) - now).num_seconds().max(0) as u64)
+}
+
+impl RateLimitInfo {
+    /// 応答ヘッダーから読み取る。レート制限に関するヘッダーが 1 つもなければ `None`
+    pub fn from_headers(headers: &HeaderMap, now: DateTime<Utc>) -> Option<Self> {
+        let mut info = RateLimitInfo {
+            requests_limit: header_u64(headers, "anthropic-ratelimit-requests-limit"),
+            requests_remaining: header_u64(headers, "anthropic-ratelimit-requests-remaining"),
+            requests_reset: header_time(headers, "anthropic-ratelimit-requests-reset"),
+            tokens_limit: header_u64(headers, "anthropic-ratelimit-tokens-limit"),
+            tokens_remaining: header_u64(headers, "anthropic-ratelimit-tokens-remaining"),
+            tokens_reset: header_time(headers, "anthropic-ratelimit-tokens-reset"),
+            retry_after_secs: retry_after_secs(headers, now),
+            limited_until: None,
+        };
+
+        if info == RateLimitInfo::default() {
+            return None;
+        }
+
+        // retry-after を優先し、なければ残数が 0 になった側のリセット時刻まで待つ
+        info.limited_until = info
+            .retry_after_secs
+            .map(|secs| now + Duration::seconds(secs as i64))
+            .or_else(|| {
+                [
+                    (info.requests_remaining, info.requests_reset),
+                    (info.tokens_remaining, info.tokens_reset),
+                ]
+                .into_iter()
+                .filter(|(remaining, _)| *remaining == Some(0))
+                .filter_map(|(_, reset)| reset)
+                .max()
+            })
+            .filter(|until| *until > now);
+        Some(info)
+    }
+
+    /// `now` の時点でまだ制限中か
+    pub fn is_limited(&self, now: DateTime<Utc>) -> bool {
+        self.limited_until.is_some_and(|until| until > now)
+    }
+}
@@ -0,0 +1,147 @@
+//! リセット時刻 (UTC の RFC3339 文字列) をシステムのタイムゾーンで表示用に整形する。
+//!
+//! フロントエンドの各ビューで同じ処理を書かずに済むよう、送信するデータに含める。
+
+use chrono::{DateTime, Datelike, Duration, Local, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::i18n::Locale;
+use crate::{CopilotUsageData, UsageData};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResetTime {
+    /// システムのタイムゾーンでの RFC3339 表記
+    pub local: String,
+    /// "today 18:00 +09:00" / "今日 18:00 +09:00" のような表記
+    pub display: String,
+    /// "in 3h 12m" / "あと 3時間12分" のような残り時間
+    pub relative: String,
+}
+
+pub fn format(resets_at: &str, now: DateTime<Local>, locale: Locale) -> Result<ResetTime, String> {
+    let reset = DateTime::parse_from_rfc3339(resets_at)
+        .map_err(|e| format!("Invalid reset time {}: {}", resets_at, e))?
+        .with_timezone(&Local);
+
+    Ok(ResetTime {
+        local: reset.to_rfc3339_opts(SecondsFormat::Secs, false),
+        display: format_display(reset, now, locale),
+        relative: format_relative(reset - now, locale),
+    })
+}
+
+const JA_WEEKDAYS: [&str; 7] = ["月曜", "火曜", "水曜", "木曜", "金曜", "土曜", "日曜"];
+
+fn format_display(reset: DateTime<Local>, now: DateTime<Local>, locale: Locale) -> String {
+    let days = (reset.date_naive() - now.date_naive()).num_days();
+    let day = match (days, locale) {
+        (0, Locale::En) => "today".to_string(),
+        (0, Locale::Ja) => "今日".to_string(),
+        (1, Locale::En) => "tomorrow".to_string(),
+        (1, Locale::Ja) => "明日".to_string(),
+        (2..=6, Locale::En) => reset.format("%a").to_string(),
+        (2..=6, Locale::Ja) => {
+            JA_WEEKDAYS[reset.weekday().num_days_from_monday() as usize].to_string()
+        }
+        _ => format!("{}/{}", reset.month(), reset.day()),
+    };
+    format!("{} {}", day, reset.format("%H:%M %:z"))
+}
+
+fn format_relative(remaining: Duration, locale: Locale) -> String {
+    let minutes = remaining.num_minutes();
+    if minutes <= 0 {
+        return match locale {
+            Locale::En => "now".to_string(),
+            Locale::Ja => "まもなく".to_string(),
+        };
+    }
+
+    let (days, hours, minutes) = (minutes / (24 * 60), minutes / 60 % 24, minutes % 60);
+    match locale {
+        Locale::En if days > 0 => format!("in {}d {}h", days, hours),
+        Locale::En if hours > 0 => format!("in {}h {}m", hours, minutes),
+        Locale::En => format!("in {}m", minutes),
+        Locale::Ja if days > 0 => format!("あと {}日{}時間", days, hours),
+        Locale::Ja if hours > 0 => format!("あと {}時間{}分", hours, minutes),
+        Locale::Ja => format!("あと {}分", minutes),
+    }
+}
+
+/// メーター ID ごとのリセット時刻。リセット時刻のないメーターは含めない。
+pub fn for_usage(
+    claude: &UsageData,
+    copilot: Option<&CopilotUsageData>,
+    now: DateTime<Local>,
+    locale: Locale,
+) -> BTreeMap<String, ResetTime> {
+    let meters = [
+        ("five_hour", Some(&claude.five_hour)),
+        ("seven_day", Some(&claude.seven_day)),
+        ("seven_day_opus", claude.seven_day_opus.as_ref()),
+        ("seven_day_sonnet", claude.seven_day_sonnet.as_ref()),
+        ("seven_day_oauth_apps", claude.seven_day_oauth_apps.as_ref()),
+        ("seven_day_cowork", claude.seven_day_cowork.as_ref()),
+    ];
+
+    meters
+        .into_iter()
+        .filter_map(|(id, meter)| Some((id, meter?.resets_at.as_deref()?)))
+        .chain(copilot.map(|c| ("copilot", c.resets_at.as_str())))
+        .filter_map(|(id, resets_at)| Some((id.to_string(), format(resets_at, now, locale).ok()?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn local(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        // 2025-03-10 は月曜日
+        Local
+            .with_ymd_and_hms(2025, 3, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn names_the_reset_day_relative_to_today() {
+        let now = local(10, 9, 0);
+        let offset = now.format("%:z").to_string();
+        let display = |day, locale| format_display(local(day, 18, 0), now, locale);
+
+        assert_eq!(display(10, Locale::En), format!("today 18:00 {}", offset));
+        assert_eq!(display(11, Locale::Ja), format!("明日 18:00 {}", offset));
+        assert_eq!(display(13, Locale::En), format!("Thu 18:00 {}", offset));
+        assert_eq!(display(13, Locale::Ja), format!("木曜 18:00 {}", offset));
+        assert_eq!(display(20, Locale::En), format!("3/20 18:00 {}", offset));
+    }
+
+    #[test]
+    fn formats_remaining_time() {
+        let relative = |minutes, locale| format_relative(Duration::minutes(minutes), locale);
+        assert_eq!(relative(0, Locale::En), "now");
+        assert_eq!(relative(-5, Locale::Ja), "まもなく");
+        assert_eq!(relative(5, Locale::En), "in 5m");
+        assert_eq!(relative(3 * 60 + 12, Locale::En), "in 3h 12m");
+        assert_eq!(relative(2 * 24 * 60 + 4 * 60, Locale::En), "in 2d 4h");
+        assert_eq!(relative(3 * 60 + 12, Locale::Ja), "あと 3時間12分");
+    }
+
+    #[test]
+    fn lists_only_meters_with_a_reset_time() {
+        let now = chrono::Utc::now();
+        let mut claude = crate::demo::sample_usage(now);
+        claude.seven_day_sonnet.as_mut().unwrap().resets_at = None;
+
+        let times = for_usage(&claude, None, now.with_timezone(&Local), Locale::En);
+        assert!(times.contains_key("five_hour"));
+        assert!(times.contains_key("seven_day_opus"));
+        assert!(!times.contains_key("seven_day_sonnet"));
+        assert!(!times.contains_key("seven_day_cowork"));
+        assert!(!times.contains_key("copilot"));
+
+        assert!(format("not a time", now.with_timezone(&Local), Locale::En).is_err());
+    }
+}
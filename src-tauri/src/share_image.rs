@@ -0,0 +1,238 @@
+//! 現在の使用量を共有用の PNG 画像に描画する。
+//!
+//! チャットなどにそのまま貼れるよう、各メーターのバーと使用率、取得時刻、
+//! Copilot のプランを 1 枚にまとめる。フォントを同梱しないよう、文字は
+//! 5x7 のビットマップフォント (ASCII の英大文字・数字・記号のみ) で描く。
+
+use chrono::{DateTime, Local};
+use tiny_skia::{Color, Paint, Pixmap, Rect, Transform};
+
+use crate::alerts::{meter_label, meter_utilizations};
+use crate::i18n::Locale;
+use crate::{CombinedUsageData, CopilotPlanInfo};
+
+const WIDTH: u32 = 480;
+const PADDING: f32 = 20.0;
+/// ビットマップフォントの拡大率 (1 ドット = SCALE px)
+const SCALE: f32 = 2.0;
+const ROW_HEIGHT: f32 = 44.0;
+const BAR_HEIGHT: f32 = 10.0;
+const HEADER_HEIGHT: f32 = 56.0;
+const FOOTER_HEIGHT: f32 = 36.0;
+
+type Rgb = (u8, u8, u8);
+
+const BACKGROUND: Rgb = (18, 18, 18);
+const TRACK: Rgb = (48, 48, 48);
+const TEXT: Rgb = (235, 235, 235);
+const MUTED: Rgb = (150, 150, 150);
+const CLAUDE: Rgb = (59, 130, 246);
+const COPILOT: Rgb = (16, 185, 129);
+const WARNING: Rgb = (245, 158, 11);
+const DANGER: Rgb = (239, 68, 68);
+
+/// ウィジェットと同じく 60% / 80% で色を変える
+fn bar_color(meter: &str, utilization: f64) -> Rgb {
+    if utilization >= 80.0 {
+        DANGER
+    } else if utilization >= 60.0 {
+        WARNING
+    } else if meter == "copilot" {
+        COPILOT
+    } else {
+        CLAUDE
+    }
+}
+
+fn plan_label(plan: Option<&CopilotPlanInfo>) -> Option<String> {
+    let plan = plan?;
+    let name = plan.plan.as_deref().unwrap_or("unknown").replace('_', " ");
+    Some(format!(
+        "Copilot plan: {} ({:.0} req/month)",
+        name, plan.monthly_limit
+    ))
+}
+
+/// 使用量を PNG のバイト列として描画する
+pub fn render(
+    data: &CombinedUsageData,
+    copilot_plan: Option<&CopilotPlanInfo>,
+    fetched_at: DateTime<Local>,
+) -> Result<Vec<u8>, String> {
+    let meters = meter_utilizations(data);
+    let plan = plan_label(copilot_plan);
+    let height = HEADER_HEIGHT
+        + ROW_HEIGHT * meters.len() as f32
+        + FOOTER_HEIGHT
+        + if plan.is_some() { 20.0 } else { 0.0 };
+
+    let mut pixmap = Pixmap::new(WIDTH, height.ceil() as u32)
+        .ok_or_else(|| "Failed to allocate image".to_string())?;
+    let (r, g, b) = BACKGROUND;
+    pixmap.fill(Color::from_rgba8(r, g, b, 255));
+
+    let mut canvas = Canvas {
+        pixmap: &mut pixmap,
+    };
+    canvas.text(PADDING, PADDING, "USAGE DASHBOARD", TEXT);
+
+    let bar_width = WIDTH as f32 - PADDING * 2.0;
+    let mut y = HEADER_HEIGHT;
+    for (meter, utilization) in &meters {
+        let value = format!("{:.1}%", utilization);
+        canvas.text(PADDING, y, meter_label(meter, Locale::En), TEXT);
+        canvas.text(WIDTH as f32 - PADDING - text_width(&value), y, &value, TEXT);
+
+        let bar_y = y + 7.0 * SCALE + 8.0;
+        canvas.rect(PADDING, bar_y, bar_width, BAR_HEIGHT, TRACK);
+        let filled = bar_width * (utilization.clamp(0.0, 100.0) / 100.0) as f32;
+        if filled > 0.0 {
+            canvas.rect(
+                PADDING,
+                bar_y,
+                filled,
+                BAR_HEIGHT,
+                bar_color(meter, *utilization),
+            );
+        }
+        y += ROW_HEIGHT;
+    }
+
+    if let Some(plan) = &plan {
+        canvas.text(PADDING, y, plan, MUTED);
+        y += 20.0;
+    }
+    let timestamp = fetched_at.format("Fetched %Y-%m-%d %H:%M %:z").to_string();
+    canvas.text(PADDING, y + 8.0, &timestamp, MUTED);
+
+    pixmap
+        .encode_png()
+        .map_err(|e| format!("Failed to encode PNG: {}", e))
+}
+
+struct Canvas<'a> {
+    pixmap: &'a mut Pixmap,
+}
+
+impl Canvas<'_> {
+    fn rect(&mut self, x: f32, y: f32, w: f32, h: f32, (r, g, b): Rgb) {
+        let Some(rect) = Rect::from_xywh(x, y, w, h) else {
+            return;
+        };
+        let mut paint = Paint::default();
+        paint.set_color_rgba8(r, g, b, 255);
+        self.pixmap
+            .fill_rect(rect, &paint, Transform::identity(), None);
+    }
+
+    fn text(&mut self, x: f32, y: f32, text: &str, color: Rgb) {
+        let mut cursor = x;
+        for c in text.chars() {
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for col in 0..5 {
+                    if bits & (0b10000 >> col) != 0 {
+                        self.rect(
+                            cursor + col as f32 * SCALE,
+                            y + row as f32 * SCALE,
+                            SCALE,
+                            SCALE,
+                            color,
+                        );
+                    }
+                }
+            }
+            cursor += 6.0 * SCALE;
+        }
+    }
+}
+
+fn text_width(text: &str) -> f32 {
+    text.chars().count() as f32 * 6.0 * SCALE - SCALE
+}
+
+/// 5x7 のビットマップ。各行の下位 5 ビットが左から右のドット。
+/// 英小文字は大文字で描き、対応していない文字は `?` にする
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        ' ' => [0, 0, 0, 0, 0, 0, 0],
+        'A' => [14, 17, 17, 31, 17, 17, 17],
+        'B' => [30, 17, 17, 30, 17, 17, 30],
+        'C' => [14, 17, 16, 16, 16, 17, 14],
+        'D' => [30, 17, 17, 17, 17, 17, 30],
+        'E' => [31, 16, 16, 30, 16, 16, 31],
+        'F' => [31, 16, 16, 30, 16, 16, 16],
+        'G' => [14, 17, 16, 23, 17, 17, 15],
+        'H' => [17, 17, 17, 31, 17, 17, 17],
+        'I' => [14, 4, 4, 4, 4, 4, 14],
+        'J' => [7, 2, 2, 2, 2, 18, 12],
+        'K' => [17, 18, 20, 24, 20, 18, 17],
+        'L' => [16, 16, 16, 16, 16, 16, 31],
+        'M' => [17, 27, 21, 21, 17, 17, 17],
+        'N' => [17, 17, 25, 21, 19, 17, 17],
+        'O' => [14, 17, 17, 17, 17, 17, 14],
+        'P' => [30, 17, 17, 30, 16, 16, 16],
+        'Q' => [14, 17, 17, 17, 21, 18, 13],
+        'R' => [30, 17, 17, 30, 20, 18, 17],
+        'S' => [15, 16, 16, 14, 1, 1, 30],
+        'T' => [31, 4, 4, 4, 4, 4, 4],
+        'U' => [17, 17, 17, 17, 17, 17, 14],
+        'V' => [17, 17, 17, 17, 17, 10, 4],
+        'W' => [17, 17, 17, 21, 21, 21, 10],
+        'X' => [17, 17, 10, 4, 10, 17, 17],
+        'Y' => [17, 17, 10, 4, 4, 4, 4],
+        'Z' => [31, 1, 2, 4, 8, 16, 31],
+        '0' => [14, 17, 19, 21, 25, 17, 14],
+        '1' => [4, 12, 4, 4, 4, 4, 14],
+        '2' => [14, 17, 1, 2, 4, 8, 31],
+        '3' => [31, 2, 4, 2, 1, 17, 14],
+        '4' => [2, 6, 10, 18, 31, 2, 2],
+        '5' => [31, 16, 30, 1, 1, 17, 14],
+        '6' => [6, 8, 16, 30, 17, 17, 14],
+        '7' => [31, 1, 2, 4, 8, 8, 8],
+        '8' => [14, 17, 17, 14, 17, 17, 14],
+        '9' => [14, 17, 17, 15, 1, 2, 12],
+        '.' => [0, 0, 0, 0, 0, 12, 12],
+        ',' => [0, 0, 0, 0, 12, 4, 8],
+        ':' => [0, 12, 12, 0, 12, 12, 0],
+        '-' => [0, 0, 0, 31, 0, 0, 0],
+        '+' => [0, 4, 4, 31, 4, 4, 0],
+        '/' => [0, 1, 2, 4, 8, 16, 0],
+        '%' => [24, 25, 2, 4, 8, 19, 3],
+        '(' => [2, 4, 8, 8, 8, 4, 2],
+        ')' => [8, 4, 2, 2, 2, 4, 8],
+        _ => [14, 17, 1, 2, 4, 0, 4],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_png_with_one_row_per_meter() {
+        let now = chrono::Utc::now();
+        let mut data = crate::demo::sample_data(now);
+        data.copilot = Some(crate::demo::copilot(now));
+        let plan = CopilotPlanInfo {
+            plan: Some("pro_plus".to_string()),
+            monthly_limit: 1500.0,
+        };
+
+        let png = render(&data, Some(&plan), Local::now()).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+
+        let image = Pixmap::decode_png(&png).unwrap();
+        let rows = meter_utilizations(&data).len() as f32;
+        let expected = HEADER_HEIGHT + ROW_HEIGHT * rows + FOOTER_HEIGHT + 20.0;
+        assert_eq!(image.width(), WIDTH);
+        assert_eq!(image.height(), expected.ceil() as u32);
+    }
+
+    #[test]
+    fn colors_bars_by_threshold() {
+        assert_eq!(bar_color("five_hour", 10.0), CLAUDE);
+        assert_eq!(bar_color("copilot", 10.0), COPILOT);
+        assert_eq!(bar_color("copilot", 65.0), WARNING);
+        assert_eq!(bar_color("seven_day", 95.0), DANGER);
+    }
+}
@@ -0,0 +1,398 @@
+//! ウィジェットの画面端へのスナップとドッキング。
+//!
+//! ドラッグを終えた位置が作業領域の端から `SnapConfig.margin` 以内であれば端に吸着させ、
+//! 吸着した辺・角をモニターごとに `config.json` に保存する。次回起動時や解像度の変更後は
+//! 保存した辺・角に合わせて位置を直す。
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tauri::{Manager, PhysicalPosition};
+
+use crate::AppConfig;
+
+const WINDOW_LABEL: &str = "main";
+/// 移動イベントが途切れてからスナップするまでの時間。ドラッグ中は吸着させない
+const SETTLE_MS: u64 = 300;
+/// 解像度・作業領域の変更を確認する間隔
+const DISPLAY_CHECK_INTERVAL_SECS: u64 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Horizontal {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Vertical {
+    Top,
+    Bottom,
+}
+
+/// 吸着している辺。両方ある場合は角
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dock {
+    #[serde(default)]
+    pub horizontal: Option<Horizontal>,
+    #[serde(default)]
+    pub vertical: Option<Vertical>,
+}
+
+impl Dock {
+    fn is_docked(&self) -> bool {
+        self.horizontal.is_some() || self.vertical.is_some()
+    }
+}
+
+/// 移動イベントの通し番号。最後のイベントから `SETTLE_MS` 経った場合のみスナップする
+#[derive(Default)]
+pub struct SnapState {
+    moves: AtomicU64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Area {
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+}
+
+/// ウィンドウとモニターの位置・大きさ (物理ピクセル)
+struct Geometry {
+    monitor: String,
+    area: Area,
+    scale: f64,
+    position: (i32, i32),
+    size: (i32, i32),
+}
+
+fn geometry(window: &tauri::WebviewWindow) -> Option<Geometry> {
+    let monitor = window.current_monitor().ok().flatten()?;
+    let work_area = monitor.work_area();
+    let position = window.outer_position().ok()?;
+    let size = window.outer_size().ok()?;
+    Some(Geometry {
+        monitor: monitor
+            .name()
+            .cloned()
+            .unwrap_or_else(|| "default".to_string()),
+        area: Area {
+            x: work_area.position.x,
+            y: work_area.position.y,
+            width: work_area.size.width as i32,
+            height: work_area.size.height as i32,
+        },
+        scale: monitor.scale_factor(),
+        position: (position.x, position.y),
+        size: (size.width as i32, size.height as i32),
+    })
+}
+
+/// 作業領域の端から `margin` 以内にある辺を吸着先とする
+fn snap(position: (i32, i32), size: (i32, i32), area: &Area, margin: i32) -> Dock {
+    let right = area.x + area.width - size.0;
+    let bottom = area.y + area.height - size.1;
+    Dock {
+        horizontal: if (position.0 - area.x).abs() <= margin {
+            Some(Horizontal::Left)
+        } else if (right - position.0).abs() <= margin {
+            Some(Horizontal::Right)
+        } else {
+            None
+        },
+        vertical: if (position.1 - area.y).abs() <= margin {
+            Some(Vertical::Top)
+        } else if (bottom - position.1).abs() <= margin {
+            Some(Vertical::Bottom)
+        } else {
+            None
+        },
+    }
+}
+
+/// 吸着先に合わせた位置。吸着していない軸は作業領域内に収める
+fn docked_position(position: (i32, i32), size: (i32, i32), area: &Area, dock: Dock) -> (i32, i32) {
+    let max_x = (area.x + area.width - size.0).max(area.x);
+    let max_y = (area.y + area.height - size.1).max(area.y);
+    let x = match dock.horizontal {
+        Some(Horizontal::Left) => area.x,
+        Some(Horizontal::Right) => max_x,
+        None => position.0.clamp(area.x, max_x),
+    };
+    let y = match dock.vertical {
+        Some(Vertical::Top) => area.y,
+        Some(Vertical::Bottom) => max_y,
+        None => position.1.clamp(area.y, max_y),
+    };
+    (x, y)
+}
+
+fn move_to(window: &tauri::WebviewWindow, geometry: &Geometry, target: (i32, i32)) {
+    if target == geometry.position {
+        return;
+    }
+    if let Err(e) = window.set_position(PhysicalPosition::new(target.0, target.1)) {
+        tracing::warn!("Failed to move widget: {}", e);
+    }
+}
+
+fn remember(mut config: AppConfig, monitor: String, dock: Dock) {
+    let changed = if dock.is_docked() {
+        config.snap.docked.insert(monitor, dock) != Some(dock)
+    } else {
+        config.snap.docked.remove(&monitor).is_some()
+    };
+    if changed {
+        if let Err(e) = crate::write_app_config(&config) {
+            tracing::warn!("Failed to save docked position: {}", e);
+        }
+    }
+}
+
+fn snap_window(app: &tauri::AppHandle) {
+    // 読めない設定を既定値で上書きしないよう、読み込みに失敗した場合は何もしない
+    let config = match crate::read_app_config() {
+        Ok(config) => config,
+        Err(e) => {
+            tracing::warn!("Failed to read config for snapping: {}", e);
+            return;
+        }
+    };
+    if !config.snap.enabled {
+        return;
+    }
+    let Some(window) = app.get_webview_window(WINDOW_LABEL) else {
+        return;
+    };
+    let Some(geometry) = geometry(&window) else {
+        return;
+    };
+
+    let margin = (config.snap.margin as f64 * geometry.scale).round() as i32;
+    let dock = snap(geometry.position, geometry.size, &geometry.area, margin);
+    move_to(
+        &window,
+        &geometry,
+        docked_position(geometry.position, geometry.size, &geometry.area, dock),
+    );
+    remember(config, geometry.monitor, dock);
+}
+
+/// ウィジェットが移動したとき (`WindowEvent::Moved`)。移動が落ち着いてからスナップする
+pub fn on_moved(window: &tauri::Window) {
+    if window.label() != WINDOW_LABEL {
+        return;
+    }
+    let app = window.app_handle().clone();
+    let generation = app
+        .state::<SnapState>()
+        .moves
+        .fetch_add(1, Ordering::SeqCst)
+        + 1;
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(SETTLE_MS)).await;
+        if app.state::<SnapState>().moves.load(Ordering::SeqCst) == generation {
+            snap_window(&app);
+        }
+    });
+}
+
+/// 現在のモニターに保存した吸着先に合わせて位置を直す。
+/// 吸着していない場合も作業領域からはみ出していれば内側に戻す
+pub fn restore(app: &tauri::AppHandle) {
+    let config = crate::read_app_config().unwrap_or_default();
+    if !config.snap.enabled {
+        return;
+    }
+    let Some(window) = app.get_webview_window(WINDOW_LABEL) else {
+        return;
+    };
+    let Some(geometry) = geometry(&window) else {
+        return;
+    };
+
+    let dock = config
+        .snap
+        .docked
+        .get(&geometry.monitor)
+        .copied()
+        .unwrap_or_default();
+    move_to(
+        &window,
+        &geometry,
+        docked_position(geometry.position, geometry.size, &geometry.area, dock),
+    );
+}
+
+/// 同じモニターのまま作業領域 (解像度・タスクバー) が変わった場合に位置を直す
+pub fn spawn_display_watcher(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut last: Option<(String, Area)> = None;
+        loop {
+            tokio::time::sleep(Duration::from_secs(DISPLAY_CHECK_INTERVAL_SECS)).await;
+            let Some(geometry) = app
+                .get_webview_window(WINDOW_LABEL)
+                .and_then(|w| geometry(&w))
+            else {
+                continue;
+            };
+
+            let changed = last.as_ref().is_some_and(|(monitor, area)| {
+                *monitor == geometry.monitor && *area != geometry.area
+            });
+            if changed {
+                tracing::info!(monitor = %geometry.monitor, "Display changed, repositioning widget");
+                restore(&app);
+            }
+            last = Some((geometry.monitor, geometry.area));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1920x1040 の作業領域 (下端にタスクバー)
+    fn primary() -> Area {
+        Area {
+            x: 0,
+            y: 0,
+            width: 1920,
+            height: 1040,
+        }
+    }
+
+    /// `primary` の右に並べた、縦長のサブモニター
+    fn secondary() -> Area {
+        Area {
+            x: 1920,
+            y: -200,
+            width: 1080,
+            height: 1920,
+        }
+    }
+
+    const SIZE: (i32, i32) = (300, 200);
+
+    fn dock(horizontal: Option<Horizontal>, vertical: Option<Vertical>) -> Dock {
+        Dock {
+            horizontal,
+            vertical,
+        }
+    }
+
+    #[test]
+    fn snaps_to_edges_within_the_margin() {
+        let area = primary();
+        assert_eq!(
+            snap((12, 400), SIZE, &area, 16),
+            dock(Some(Horizontal::Left), None)
+        );
+        assert_eq!(
+            snap((1610, 400), SIZE, &area, 16),
+            dock(Some(Horizontal::Right), None)
+        );
+        assert_eq!(
+            snap((800, 850), SIZE, &area, 16),
+            dock(None, Some(Vertical::Bottom))
+        );
+        assert_eq!(snap((17, 400), SIZE, &area, 16), Dock::default());
+        assert!(!snap((800, 400), SIZE, &area, 16).is_docked());
+    }
+
+    #[test]
+    fn snaps_to_corners() {
+        let area = primary();
+        assert_eq!(
+            snap((-5, 3), SIZE, &area, 16),
+            dock(Some(Horizontal::Left), Some(Vertical::Top))
+        );
+        assert_eq!(
+            snap((1625, 845), SIZE, &area, 16),
+            dock(Some(Horizontal::Right), Some(Vertical::Bottom))
+        );
+    }
+
+    #[test]
+    fn snaps_relative_to_the_current_monitor() {
+        let area = secondary();
+        assert_eq!(
+            snap((1930, -195), SIZE, &area, 16),
+            dock(Some(Horizontal::Left), Some(Vertical::Top))
+        );
+        assert_eq!(
+            snap((2700, 1520), SIZE, &area, 16),
+            dock(Some(Horizontal::Right), Some(Vertical::Bottom))
+        );
+        // 主モニターの右端の座標はサブモニターの左端として扱う
+        assert_eq!(snap((1610, 400), SIZE, &area, 16), Dock::default());
+    }
+
+    #[test]
+    fn moves_to_the_docked_edge_or_corner() {
+        let area = primary();
+        assert_eq!(
+            docked_position(
+                (1610, 400),
+                SIZE,
+                &area,
+                dock(Some(Horizontal::Right), None)
+            ),
+            (1620, 400)
+        );
+        assert_eq!(
+            docked_position(
+                (5, 5),
+                SIZE,
+                &area,
+                dock(Some(Horizontal::Left), Some(Vertical::Top))
+            ),
+            (0, 0)
+        );
+
+        // 解像度が変わっても保存した角に付け直す
+        let area = secondary();
+        assert_eq!(
+            docked_position(
+                (1610, 840),
+                SIZE,
+                &area,
+                dock(Some(Horizontal::Right), Some(Vertical::Bottom))
+            ),
+            (2700, 1520)
+        );
+    }
+
+    #[test]
+    fn keeps_undocked_windows_inside_the_work_area() {
+        let area = primary();
+        assert_eq!(
+            docked_position((800, 400), SIZE, &area, Dock::default()),
+            (800, 400)
+        );
+        assert_eq!(
+            docked_position((2500, 1200), SIZE, &area, Dock::default()),
+            (1620, 840)
+        );
+        assert_eq!(
+            docked_position((-400, 500), SIZE, &area, dock(None, Some(Vertical::Top))),
+            (0, 0)
+        );
+
+        // 作業領域よりウィンドウが大きい場合は左上に合わせる
+        let small = Area {
+            x: 100,
+            y: 50,
+            width: 200,
+            height: 100,
+        };
+        assert_eq!(
+            docked_position((0, 0), SIZE, &small, dock(Some(Horizontal::Right), None)),
+            (100, 50)
+        );
+    }
+}
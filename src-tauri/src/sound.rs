@@ -0,0 +1,150 @@
+//! アラートの効果音。
+//!
+//! 内蔵のチャイム (正弦波の組み合わせ) か、ユーザーが指定した音声ファイル
+//! (WAV / MP3 / FLAC / OGG) を再生する。設定は `AppConfig.alerts.sound`。
+
+use std::io::BufReader;
+use std::path::Path;
+use std::time::Duration;
+
+use rodio::source::SineWave;
+use rodio::{Decoder, OutputStream, Sink, Source};
+
+use crate::alerts::Alert;
+use crate::SoundConfig;
+
+/// 内蔵のチャイムの名前
+pub const CHIMES: &[&str] = &["chime", "bell", "ping"];
+
+/// 内蔵のチャイムの音 (周波数 Hz, 長さ ms)。無音は周波数 0
+fn chime_tones(name: &str) -> &'static [(f32, u64)] {
+    match name {
+        "bell" => &[(1046.5, 500)],
+        "ping" => &[(1760.0, 120), (0.0, 60), (1760.0, 120)],
+        _ => &[(659.3, 180), (880.0, 180), (1318.5, 320)],
+    }
+}
+
+pub fn validate(config: &SoundConfig) -> Result<(), String> {
+    if !CHIMES.contains(&config.chime.as_str()) {
+        return Err(format!("Unknown chime: {}", config.chime));
+    }
+    if !(0.0..=1.0).contains(&config.volume) {
+        return Err("Volume must be between 0 and 1".to_string());
+    }
+    if let Some(file) = &config.file {
+        if !Path::new(file).is_file() {
+            return Err(format!("Sound file not found: {}", file));
+        }
+    }
+    Ok(())
+}
+
+/// このアラートで音を鳴らすか。まとめの通知は中身のいずれかが対象なら鳴らす
+pub fn wants_sound(config: &SoundConfig, alert: &Alert) -> bool {
+    match alert {
+        Alert::ThresholdCrossed { .. }
+        | Alert::ExtraUsageCrossed { .. }
+        | Alert::BudgetCrossed { .. } => config.on_threshold,
+        Alert::QuotaReset { .. } => config.on_reset,
+        Alert::TokenExpired => false,
+        Alert::QuietHoursSummary(alerts) => alerts.iter().any(|a| wants_sound(config, a)),
+    }
+}
+
+/// 別スレッドで再生する。再生の失敗はログに出すだけにする
+pub fn play(config: &SoundConfig) {
+    let config = config.clone();
+    std::thread::spawn(move || {
+        if let Err(e) = play_blocking(&config) {
+            tracing::warn!("Sound playback error: {}", e);
+        }
+    });
+}
+
+fn play_blocking(config: &SoundConfig) -> Result<(), String> {
+    // OutputStream は Send ではないので、再生するスレッドで開く
+    let (_stream, handle) =
+        OutputStream::try_default().map_err(|e| format!("No audio output device: {}", e))?;
+    let sink = Sink::try_new(&handle).map_err(|e| format!("Failed to open audio sink: {}", e))?;
+    sink.set_volume(config.volume);
+
+    match &config.file {
+        Some(file) => {
+            let reader = std::fs::File::open(file)
+                .map_err(|e| format!("Failed to open sound file: {}", e))?;
+            let source = Decoder::new(BufReader::new(reader))
+                .map_err(|e| format!("Failed to decode sound file: {}", e))?;
+            sink.append(source);
+        }
+        None => {
+            for &(freq, ms) in chime_tones(&config.chime) {
+                let duration = Duration::from_millis(ms);
+                let amplitude = if freq > 0.0 { 0.3 } else { 0.0 };
+                sink.append(
+                    SineWave::new(freq.max(1.0))
+                        .take_duration(duration)
+                        .amplify(amplitude),
+                );
+            }
+        }
+    }
+    sink.sleep_until_end();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn threshold_alert() -> Alert {
+        Alert::ThresholdCrossed {
+            meter: "five_hour",
+            threshold: 80.0,
+            utilization: 81.0,
+        }
+    }
+
+    fn reset_alert() -> Alert {
+        Alert::QuotaReset {
+            meter: "five_hour",
+            utilization: 0.0,
+        }
+    }
+
+    #[test]
+    fn selects_sound_per_event() {
+        let config = SoundConfig {
+            on_reset: false,
+            ..SoundConfig::default()
+        };
+        assert!(wants_sound(&config, &threshold_alert()));
+        assert!(!wants_sound(&config, &reset_alert()));
+        assert!(!wants_sound(&config, &Alert::TokenExpired));
+        assert!(wants_sound(
+            &config,
+            &Alert::QuietHoursSummary(vec![reset_alert(), threshold_alert()])
+        ));
+        assert!(!wants_sound(
+            &config,
+            &Alert::QuietHoursSummary(vec![reset_alert()])
+        ));
+    }
+
+    #[test]
+    fn rejects_unknown_chime_and_missing_file() {
+        assert!(validate(&SoundConfig::default()).is_ok());
+        let unknown = SoundConfig {
+            chime: "gong".to_string(),
+            ..SoundConfig::default()
+        };
+        assert!(validate(&unknown).is_err());
+        let missing = SoundConfig {
+            file: Some("/nonexistent/alert.wav".to_string()),
+            ..SoundConfig::default()
+        };
+        assert!(validate(&missing)
+            .unwrap_err()
+            .starts_with("Sound file not found"));
+    }
+}
@@ -0,0 +1,95 @@
+//! ウィジェットのスパークライン用に、直近の使用率をメモリ上に保持する。
+//!
+//! 履歴 (`history` モジュール) の記録が無効でも使えるよう、ファイルには書かず
+//! メーターごとのリングバッファに取得のたびに追加する。
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+
+use crate::alerts::meter_utilizations;
+use crate::CombinedUsageData;
+
+/// メーターごとに保持するサンプル数。リセット前後の 15 秒間隔でも約 1 時間分
+pub const CAPACITY: usize = 240;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RecentSample {
+    pub timestamp: DateTime<Utc>,
+    pub utilization: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct RecentSamples {
+    meters: HashMap<&'static str, VecDeque<RecentSample>>,
+}
+
+impl RecentSamples {
+    pub fn record(&mut self, timestamp: DateTime<Utc>, data: &CombinedUsageData) {
+        for (meter, utilization) in meter_utilizations(data) {
+            let samples = self.meters.entry(meter).or_default();
+            if samples.len() == CAPACITY {
+                samples.pop_front();
+            }
+            samples.push_back(RecentSample {
+                timestamp,
+                utilization,
+            });
+        }
+    }
+
+    /// 直近 `count` 件を古い順に返す
+    pub fn latest(&self, meter: &str, count: usize) -> Vec<RecentSample> {
+        let Some(samples) = self.meters.get(meter) else {
+            return Vec::new();
+        };
+        let skip = samples.len().saturating_sub(count);
+        samples.iter().skip(skip).cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::demo;
+
+    fn data_with(five_hour: f64) -> CombinedUsageData {
+        let mut data = demo::sample_data(Utc::now());
+        data.claude.five_hour.utilization = five_hour;
+        data
+    }
+
+    #[test]
+    fn returns_latest_samples_oldest_first() {
+        let mut recent = RecentSamples::default();
+        let start = Utc::now();
+        for i in 0..5 {
+            let at = start + chrono::Duration::minutes(i);
+            recent.record(at, &data_with(i as f64 * 10.0));
+        }
+
+        let latest: Vec<f64> = recent
+            .latest("five_hour", 3)
+            .iter()
+            .map(|s| s.utilization)
+            .collect();
+        assert_eq!(latest, [20.0, 30.0, 40.0]);
+        assert_eq!(recent.latest("five_hour", 100).len(), 5);
+        assert!(recent.latest("copilot", 3).is_empty());
+    }
+
+    #[test]
+    fn drops_oldest_samples_beyond_capacity() {
+        let mut recent = RecentSamples::default();
+        let start = Utc::now();
+        for i in 0..CAPACITY + 10 {
+            let at = start + chrono::Duration::seconds(i as i64);
+            recent.record(at, &data_with(i as f64));
+        }
+
+        let all = recent.latest("five_hour", usize::MAX);
+        assert_eq!(all.len(), CAPACITY);
+        assert_eq!(all[0].utilization, 10.0);
+        assert_eq!(all[CAPACITY - 1].utilization, (CAPACITY + 9) as f64);
+    }
+}
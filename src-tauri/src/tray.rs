@@ -0,0 +1,179 @@
+//! システムトレイのアイコンとメニュー。
+//!
+//! Linux のトレイは AppIndicator (StatusNotifierItem) で表示するため、
+//! StatusNotifier のホストがないデスクトップ (拡張機能のない GNOME など) では
+//! アイコンが出ない。その場合はメインウィンドウを隠さずタスクバーに出して、
+//! トレイなしでも操作できるようにする。
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::menu::{MenuBuilder, MenuItemBuilder, SubmenuBuilder};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::Manager;
+
+use crate::{alerts, hide_main_window, i18n, popup, set_window_visibility, toggle_overlay};
+
+/// トレイアイコンが表示されているか (表示先が見つからなかった場合は `false`)
+static AVAILABLE: AtomicBool = AtomicBool::new(true);
+
+pub fn is_available() -> bool {
+    AVAILABLE.load(Ordering::Relaxed)
+}
+
+/// StatusNotifierWatcher にホスト (パネル) が登録されているか。
+/// `gdbus` がない場合など判定できないときは `None`
+#[cfg(target_os = "linux")]
+fn status_notifier_host() -> Option<bool> {
+    let output = std::process::Command::new("gdbus")
+        .args([
+            "call",
+            "--session",
+            "--dest",
+            "org.kde.StatusNotifierWatcher",
+            "--object-path",
+            "/StatusNotifierWatcher",
+            "--method",
+            "org.freedesktop.DBus.Properties.Get",
+            "org.kde.StatusNotifierWatcher",
+            "IsStatusNotifierHostRegistered",
+        ])
+        .output()
+        .ok()?;
+    // Watcher 自体がいない場合は呼び出しが失敗する
+    if !output.status.success() {
+        return Some(false);
+    }
+    parse_host_registered(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// `gdbus` の出力 `(<true>,)` を読む
+#[cfg(any(target_os = "linux", test))]
+fn parse_host_registered(output: &str) -> Option<bool> {
+    match output
+        .trim()
+        .trim_start_matches('(')
+        .trim_end_matches(')')
+        .trim_end_matches(',')
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+    {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+pub fn build(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(target_os = "linux")]
+    if status_notifier_host() == Some(false) {
+        tracing::warn!("No StatusNotifier host found, the tray icon may not be shown");
+        AVAILABLE.store(false, Ordering::Relaxed);
+    }
+
+    let locale = i18n::current();
+    let label = |text| i18n::text(locale, text);
+    let toggle = MenuItemBuilder::with_id("toggle", label(i18n::Text::TrayShowHide)).build(app)?;
+    let overlay =
+        MenuItemBuilder::with_id("overlay", label(i18n::Text::TrayShowHideOverlay)).build(app)?;
+    // Linux (AppIndicator) はアイコンのクリックが届かないため、メニューからも開けるようにする
+    let popup = MenuItemBuilder::with_id("popup", label(i18n::Text::TrayShowPopup)).build(app)?;
+    let snooze = SubmenuBuilder::new(app, label(i18n::Text::TraySnooze))
+        .text("snooze-30", label(i18n::Text::TraySnooze30))
+        .text("snooze-60", label(i18n::Text::TraySnooze60))
+        .text("snooze-240", label(i18n::Text::TraySnooze240))
+        .separator()
+        .text("snooze-0", label(i18n::Text::TraySnoozeOff))
+        .build()?;
+    let quit = MenuItemBuilder::with_id("quit", label(i18n::Text::TrayQuit)).build(app)?;
+    let menu = MenuBuilder::new(app)
+        .item(&toggle)
+        .item(&overlay)
+        .item(&popup)
+        .item(&snooze)
+        .item(&quit)
+        .build()?;
+
+    let builder = TrayIconBuilder::new();
+    // AppIndicator はアイコンを一時ファイルに書き出して渡すため、Flatpak などの
+    // サンドボックスでもパネルから読めるランタイムディレクトリに置く
+    #[cfg(target_os = "linux")]
+    let builder = match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => builder.temp_dir_path(std::path::PathBuf::from(dir).join("usage-dashboard")),
+        None => builder,
+    };
+
+    builder
+        .icon(
+            app.default_window_icon()
+                .ok_or("Default window icon not found")?
+                .clone(),
+        )
+        .menu(&menu)
+        // 左クリックはポップアップ、メニューは右クリックで出す
+        .show_menu_on_left_click(false)
+        .tooltip(label(i18n::Text::AppTitle))
+        .on_menu_event(move |app, event| match event.id().as_ref() {
+            "toggle" => {
+                if let Some(w) = app.get_webview_window("main") {
+                    if w.is_visible().unwrap_or(false) && !w.is_minimized().unwrap_or(false) {
+                        hide_main_window(app);
+                    } else {
+                        let _ = w.unminimize();
+                        let _ = w.show();
+                        let _ = w.set_focus();
+                        set_window_visibility(app, "main", true);
+                    }
+                }
+            }
+            "overlay" => {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = toggle_overlay(&app) {
+                        tracing::error!("Overlay error: {}", e);
+                    }
+                });
+            }
+            "popup" => {
+                if let Err(e) = popup::toggle(app, None) {
+                    tracing::error!("Popup error: {}", e);
+                }
+            }
+            "quit" => {
+                app.exit(0);
+            }
+            id => {
+                let minutes = id.strip_prefix("snooze-").and_then(|m| m.parse().ok());
+                if let Some(minutes) = minutes {
+                    if let Err(e) = alerts::snooze(app, minutes) {
+                        tracing::error!("Snooze error: {}", e);
+                    }
+                }
+            }
+        })
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                rect,
+                ..
+            } = event
+            {
+                if let Err(e) = popup::toggle(tray.app_handle(), Some(rect)) {
+                    tracing::error!("Popup error: {}", e);
+                }
+            }
+        })
+        .build(app)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_status_notifier_host_property() {
+        assert_eq!(parse_host_registered("(<true>,)\n"), Some(true));
+        assert_eq!(parse_host_registered("(<false>,)"), Some(false));
+        assert_eq!(parse_host_registered(""), None);
+    }
+}
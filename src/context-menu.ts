@@ -1,13 +1,16 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 
 export interface Settings {
   opacity: number;
   bgEffect: "transparent" | "mica" | "acrylic";
   alwaysOnTop: boolean;
   pollingInterval: number;
+  backgroundPollingInterval: number;
   showClaudeMeters: boolean;
   showCopilotMeter: boolean;
   autostartEnabled: boolean;
+  showOverlay: boolean;
 }
 
 const STORAGE_KEY = "widget-settings";
@@ -17,9 +20,11 @@ const DEFAULTS: Settings = {
   bgEffect: "mica",
   alwaysOnTop: true,
   pollingInterval: 60,
+  backgroundPollingInterval: 300,
   showClaudeMeters: true,
   showCopilotMeter: true,
   autostartEnabled: false,
+  showOverlay: false,
 };
 
 function getEl(id: string): HTMLElement {
@@ -44,13 +49,43 @@ function saveSettings(settings: Settings): void {
   localStorage.setItem(STORAGE_KEY, JSON.stringify(settings));
 }
 
+type BackgroundEffectResult =
+  | { status: "applied" }
+  | { status: "unsupported"; reason: string };
+
+// ぼかしが使えない環境では背景が透けすぎて読みにくいため、不透明度に下限を設ける
+const MIN_OPACITY_WITHOUT_BLUR = 85;
+let blurUnsupported = false;
+
 function applyOpacity(opacity: number): void {
   const widget = document.querySelector(".widget") as HTMLElement;
   if (widget) {
-    widget.style.background = `rgba(18, 18, 18, ${opacity / 100})`;
+    const effective = blurUnsupported ? Math.max(opacity, MIN_OPACITY_WITHOUT_BLUR) : opacity;
+    widget.style.background = `rgba(18, 18, 18, ${effective / 100})`;
   }
 }
 
+async function applyBackgroundEffect(settings: Settings): Promise<void> {
+  try {
+    const result = await invoke<BackgroundEffectResult>("set_background_effect", {
+      effect: settings.bgEffect,
+    });
+    blurUnsupported = result.status === "unsupported" && settings.bgEffect !== "transparent";
+    if (result.status === "unsupported") {
+      console.info("Background effect unsupported:", result.reason);
+    }
+    // ぼかしが使えない理由 (Linux では KDE の X11 セッションのみ対応) をメニューに表示する
+    const label = document.getElementById("bg-effect-label");
+    if (label) {
+      label.textContent = blurUnsupported ? "Background (blur unavailable)" : "Background";
+      label.title = result.status === "unsupported" ? result.reason : "";
+    }
+  } catch (e) {
+    console.warn("Failed to set background effect:", e);
+  }
+  applyOpacity(settings.opacity);
+}
+
 function applyMeterVisibility(settings: Settings): void {
   const sessionMeter = document.querySelector('[data-meter-type="claude-session"]');
   const weeklyMeter = document.querySelector('[data-meter-type="claude-weekly"]');
@@ -69,11 +104,7 @@ async function applyAllSettings(settings: Settings): Promise<void> {
   applyOpacity(settings.opacity);
   applyMeterVisibility(settings);
 
-  try {
-    await invoke("set_background_effect", { effect: settings.bgEffect });
-  } catch (e) {
-    console.warn("Failed to set background effect:", e);
-  }
+  await applyBackgroundEffect(settings);
 
   try {
     await invoke("set_always_on_top", { enabled: settings.alwaysOnTop });
@@ -86,6 +117,22 @@ async function applyAllSettings(settings: Settings): Promise<void> {
   } catch (e) {
     console.warn("Failed to set polling interval:", e);
   }
+
+  try {
+    await invoke("set_background_polling_interval", {
+      seconds: settings.backgroundPollingInterval,
+    });
+  } catch (e) {
+    console.warn("Failed to set background polling interval:", e);
+  }
+
+  if (settings.showOverlay) {
+    try {
+      await invoke("show_overlay");
+    } catch (e) {
+      console.warn("Failed to show overlay:", e);
+    }
+  }
 }
 
 export function initContextMenu(): void {
@@ -140,6 +187,13 @@ export function initContextMenu(): void {
       );
     });
 
+    document.querySelectorAll<HTMLElement>("[data-bg-interval]").forEach((btn) => {
+      btn.classList.toggle(
+        "active",
+        parseInt(btn.dataset.bgInterval!) === settings.backgroundPollingInterval,
+      );
+    });
+
     // Sync visibility toggle checkmarks
     const claudeMetersCheck = document.getElementById("claude-meters-check");
     const copilotMeterCheck = document.getElementById("copilot-meter-check");
@@ -155,6 +209,8 @@ export function initContextMenu(): void {
     if (autostartCheck) {
       autostartCheck.textContent = settings.autostartEnabled ? "\u2713" : "";
     }
+
+    loadDetachableMeters();
   }
 
   applyAllSettings(settings);
@@ -164,6 +220,21 @@ export function initContextMenu(): void {
   loadAutostartStatus();
 
   // Right-click to open
+  // usage-dashboard://settings/<section> で開かれた場合は該当セクションまでスクロールする
+  listen<string>("open-settings", (event) => {
+    syncMenuUI();
+    showMenu(4, 4);
+    const section = document.querySelector<HTMLElement>(
+      `[data-settings="${event.payload}"]`,
+    );
+    if (section) {
+      requestAnimationFrame(() => {
+        section.scrollIntoView({ block: "start" });
+        section.querySelector<HTMLElement>("input, button")?.focus();
+      });
+    }
+  });
+
   document.addEventListener("contextmenu", (e) => {
     e.preventDefault();
     if (isMenuVisible()) {
@@ -207,11 +278,7 @@ export function initContextMenu(): void {
       btn.classList.add("active");
       settings.bgEffect = effect;
       saveSettings(settings);
-      try {
-        await invoke("set_background_effect", { effect });
-      } catch (e) {
-        console.warn("Failed to set background effect:", e);
-      }
+      await applyBackgroundEffect(settings);
     });
   });
 
@@ -249,6 +316,65 @@ export function initContextMenu(): void {
     applyMeterVisibility(settings);
   });
 
+  // Overlay toggle
+  const toggleOverlay = getEl("toggle-overlay");
+  const overlayCheck = getEl("overlay-check");
+  overlayCheck.textContent = settings.showOverlay ? "\u2713" : "";
+
+  toggleOverlay.addEventListener("click", async () => {
+    settings.showOverlay = !settings.showOverlay;
+    saveSettings(settings);
+    overlayCheck.textContent = settings.showOverlay ? "\u2713" : "";
+    try {
+      await invoke(settings.showOverlay ? "show_overlay" : "hide_overlay");
+    } catch (e) {
+      console.warn("Failed to toggle overlay:", e);
+    }
+  });
+
+  // Overlay click-through / opacity (backend config)
+  loadOverlayConfig();
+
+  const toggleOverlayClickThrough = getEl("toggle-overlay-click-through");
+  const overlayClickThroughCheck = getEl("overlay-click-through-check");
+
+  toggleOverlayClickThrough.addEventListener("click", async () => {
+    const enabled = overlayClickThroughCheck.textContent === "";
+    try {
+      await invoke("set_overlay_click_through", { enabled });
+      overlayClickThroughCheck.textContent = enabled ? "\u2713" : "";
+    } catch (e) {
+      console.warn("Failed to set overlay click-through:", e);
+    }
+  });
+
+  const overlayOpacitySlider = getEl("overlay-opacity-slider") as HTMLInputElement;
+  const overlayOpacityValue = getEl("overlay-opacity-value");
+
+  overlayOpacitySlider.addEventListener("input", () => {
+    overlayOpacityValue.textContent = `${overlayOpacitySlider.value}%`;
+  });
+
+  overlayOpacitySlider.addEventListener("change", async () => {
+    try {
+      await invoke("set_overlay_opacity", { opacity: parseInt(overlayOpacitySlider.value) });
+    } catch (e) {
+      console.warn("Failed to set overlay opacity:", e);
+    }
+  });
+
+  // Detach individual meters into their own windows
+  document.querySelectorAll<HTMLElement>("[data-detach]").forEach((btn) => {
+    btn.addEventListener("click", async () => {
+      try {
+        await invoke("spawn_meter_window", { meterId: btn.dataset.detach });
+      } catch (e) {
+        console.warn("Failed to detach meter:", e);
+      }
+      hideMenu();
+    });
+  });
+
   // Polling interval buttons
   document.querySelectorAll<HTMLElement>("[data-interval]").forEach((btn) => {
     btn.addEventListener("click", async () => {
@@ -267,6 +393,512 @@ export function initContextMenu(): void {
     });
   });
 
+  // Background (hidden) polling interval buttons
+  document.querySelectorAll<HTMLElement>("[data-bg-interval]").forEach((btn) => {
+    btn.addEventListener("click", async () => {
+      const seconds = parseInt(btn.dataset.bgInterval!);
+      document
+        .querySelectorAll("[data-bg-interval]")
+        .forEach((b) => b.classList.remove("active"));
+      btn.classList.add("active");
+      settings.backgroundPollingInterval = seconds;
+      saveSettings(settings);
+      try {
+        await invoke("set_background_polling_interval", { seconds });
+      } catch (e) {
+        console.warn("Failed to set background polling interval:", e);
+      }
+    });
+  });
+
+  // Edge snapping (backend config)
+  loadSnapConfig();
+
+  getEl("toggle-snap").addEventListener("click", async () => {
+    try {
+      const config = await invoke<SnapConfig>("get_snap_config");
+      await invoke("save_snap_config", { enabled: !config.enabled, margin: config.margin });
+      await loadSnapConfig();
+    } catch (e) {
+      console.warn("Failed to toggle edge snapping:", e);
+    }
+  });
+
+  document.querySelectorAll<HTMLElement>("[data-snap-margin]").forEach((btn) => {
+    btn.addEventListener("click", async () => {
+      try {
+        const config = await invoke<SnapConfig>("get_snap_config");
+        await invoke("save_snap_config", {
+          enabled: config.enabled,
+          margin: parseInt(btn.dataset.snapMargin!),
+        });
+        await loadSnapConfig();
+      } catch (e) {
+        console.warn("Failed to set snap distance:", e);
+      }
+    });
+  });
+
+  // Battery saver (backend config)
+  loadBatterySaverConfig();
+
+  const toggleBatterySaver = getEl("toggle-battery-saver");
+  const batterySaverCheck = getEl("battery-saver-check");
+
+  toggleBatterySaver.addEventListener("click", async () => {
+    try {
+      const config = await invoke<BatterySaverConfig>("get_battery_saver_config");
+      const enabled = !config.enabled;
+      await invoke("save_battery_saver_config", {
+        enabled,
+        intervalMultiplier: config.interval_multiplier,
+        skipCopilot: config.skip_copilot,
+      });
+      batterySaverCheck.textContent = enabled ? "\u2713" : "";
+    } catch (e) {
+      console.warn("Failed to toggle battery saver:", e);
+    }
+  });
+
+  // Idle throttling (backend config)
+  loadIdleConfig();
+
+  const saveIdle = async (changes: Partial<IdleConfig>) => {
+    const config = { ...(await invoke<IdleConfig>("get_idle_config")), ...changes };
+    await invoke("save_idle_config", {
+      enabled: config.enabled,
+      idleAfterSecs: config.idle_after_secs,
+      intervalMultiplier: config.interval_multiplier,
+    });
+    await loadIdleConfig();
+  };
+
+  getEl("toggle-idle-throttle").addEventListener("click", async () => {
+    try {
+      const config = await invoke<IdleConfig>("get_idle_config");
+      await saveIdle({ enabled: !config.enabled });
+    } catch (e) {
+      console.warn("Failed to toggle idle throttling:", e);
+    }
+  });
+
+  document.querySelectorAll<HTMLElement>("[data-idle-after]").forEach((btn) => {
+    btn.addEventListener("click", async () => {
+      try {
+        await saveIdle({ idle_after_secs: parseInt(btn.dataset.idleAfter!) });
+      } catch (e) {
+        console.warn("Failed to set idle time:", e);
+      }
+    });
+  });
+
+  // Export history (save location chosen via dialog)
+  document.querySelectorAll<HTMLElement>("[data-export]").forEach((btn) => {
+    btn.addEventListener("click", async () => {
+      hideMenu();
+      try {
+        const path = await invoke<string | null>("export_history", {
+          format: btn.dataset.export,
+          range: "all",
+        });
+        if (path) alert(`Exported to ${path}`);
+      } catch (e) {
+        alert(`Failed to export history: ${e}`);
+      }
+    });
+  });
+
+  // Shareable PNG of the current meters
+  getEl("export-snapshot").addEventListener("click", async () => {
+    hideMenu();
+    try {
+      const path = await invoke<string | null>("export_snapshot");
+      if (path) alert(`Exported to ${path}`);
+    } catch (e) {
+      alert(`Failed to export snapshot: ${e}`);
+    }
+  });
+
+  // Alert webhooks (backend config)
+  loadAlertConfig();
+
+  const readAlertInputs = () => {
+    const split = (id: string) =>
+      (getEl(id) as HTMLInputElement).value
+        .split(",")
+        .map((v) => v.trim())
+        .filter((v) => v.length > 0);
+    return {
+      thresholds: split("alert-thresholds").map(Number),
+      webhookUrls: split("alert-webhook-urls"),
+      extraUsageThresholds: split("extra-usage-thresholds").map(Number),
+    };
+  };
+
+  getEl("save-alert-config").addEventListener("click", async () => {
+    try {
+      const current = await invoke<AlertConfig>("get_alert_config");
+      await invoke("save_alert_config", {
+        ...readAlertInputs(),
+        desktopNotifications: current.desktop_notifications,
+        notifyOnReset: current.notify_on_reset,
+      });
+      alert("Alert settings saved!");
+    } catch (e) {
+      alert(`Failed to save alert settings: ${e}`);
+    }
+  });
+
+  const toggleAlertOption = async (key: "desktop_notifications" | "notify_on_reset") => {
+    try {
+      const config = await invoke<AlertConfig>("get_alert_config");
+      config[key] = !config[key];
+      await invoke("save_alert_config", {
+        thresholds: config.thresholds,
+        webhookUrls: config.webhook_urls,
+        desktopNotifications: config.desktop_notifications,
+        notifyOnReset: config.notify_on_reset,
+        extraUsageThresholds: config.extra_usage_thresholds,
+      });
+      applyAlertChecks(config);
+    } catch (e) {
+      alert(`Failed to update alert settings: ${e}`);
+    }
+  };
+
+  getEl("toggle-desktop-notifications").addEventListener("click", () =>
+    toggleAlertOption("desktop_notifications"),
+  );
+  getEl("toggle-notify-on-reset").addEventListener("click", () =>
+    toggleAlertOption("notify_on_reset"),
+  );
+
+  getEl("test-webhook").addEventListener("click", async () => {
+    const { webhookUrls } = readAlertInputs();
+    if (webhookUrls.length === 0) {
+      alert("Enter a webhook URL first");
+      return;
+    }
+    try {
+      for (const url of webhookUrls) {
+        await invoke("test_webhook", { url });
+      }
+      alert("Test message sent!");
+    } catch (e) {
+      alert(`Failed to send test message: ${e}`);
+    }
+  });
+
+  // Alert sound (backend config)
+  loadSoundConfig();
+
+  const readSoundInputs = () => ({
+    chime:
+      document.querySelector<HTMLElement>("[data-chime].active")?.dataset.chime ?? "chime",
+    file: (getEl("sound-file") as HTMLInputElement).value.trim() || null,
+  });
+
+  const saveSound = async (changes: Partial<SoundConfig>) => {
+    const current = await invoke<SoundConfig>("get_sound_config");
+    const config = { ...current, ...changes };
+    await invoke("save_sound_config", {
+      enabled: config.enabled,
+      onThreshold: config.on_threshold,
+      onReset: config.on_reset,
+      chime: config.chime,
+      file: config.file,
+      volume: config.volume,
+    });
+    applySoundConfig(config);
+  };
+
+  getEl("toggle-alert-sound").addEventListener("click", async () => {
+    try {
+      const current = await invoke<SoundConfig>("get_sound_config");
+      await saveSound({ enabled: !current.enabled });
+    } catch (e) {
+      alert(`Failed to toggle alert sound: ${e}`);
+    }
+  });
+
+  getEl("toggle-sound-on-threshold").addEventListener("click", async () => {
+    try {
+      const current = await invoke<SoundConfig>("get_sound_config");
+      await saveSound({ on_threshold: !current.on_threshold });
+    } catch (e) {
+      alert(`Failed to update alert sound: ${e}`);
+    }
+  });
+
+  getEl("toggle-sound-on-reset").addEventListener("click", async () => {
+    try {
+      const current = await invoke<SoundConfig>("get_sound_config");
+      await saveSound({ on_reset: !current.on_reset });
+    } catch (e) {
+      alert(`Failed to update alert sound: ${e}`);
+    }
+  });
+
+  document.querySelectorAll<HTMLElement>("[data-chime]").forEach((btn) => {
+    btn.addEventListener("click", () => {
+      document
+        .querySelectorAll("[data-chime]")
+        .forEach((b) => b.classList.remove("active"));
+      btn.classList.add("active");
+    });
+  });
+
+  getEl("choose-sound-file").addEventListener("click", async () => {
+    try {
+      const path = await invoke<string | null>("choose_sound_file");
+      if (path) (getEl("sound-file") as HTMLInputElement).value = path;
+    } catch (e) {
+      alert(`Failed to choose sound file: ${e}`);
+    }
+  });
+
+  getEl("save-sound-config").addEventListener("click", async () => {
+    try {
+      await saveSound(readSoundInputs());
+      alert("Alert sound saved!");
+    } catch (e) {
+      alert(`Failed to save alert sound: ${e}`);
+    }
+  });
+
+  getEl("test-sound").addEventListener("click", async () => {
+    try {
+      const current = await invoke<SoundConfig>("get_sound_config");
+      await invoke("test_sound", { ...readSoundInputs(), volume: current.volume });
+    } catch (e) {
+      alert(`Failed to play sound: ${e}`);
+    }
+  });
+
+  // Snooze alerts (backend config, also from the tray menu and deep links)
+  loadAlertSnooze();
+  listen<string | null>("alerts-snoozed", (event) => showAlertSnooze(event.payload));
+
+  document.querySelectorAll<HTMLElement>("#snooze-group .menu-seg-btn").forEach((btn) => {
+    btn.addEventListener("click", async () => {
+      try {
+        await invoke("snooze_alerts", { durationMinutes: Number(btn.dataset.snooze) });
+      } catch (e) {
+        alert(`Failed to snooze alerts: ${e}`);
+      }
+    });
+  });
+
+  // Quiet hours (backend config)
+  loadQuietHoursConfig();
+
+  const saveQuietHours = async (changes: Partial<QuietHoursConfig>) => {
+    const current = await invoke<QuietHoursConfig>("get_quiet_hours_config");
+    const config = { ...current, ...changes };
+    await invoke("save_quiet_hours_config", { ...config });
+    getEl("quiet-hours-check").textContent = config.enabled ? "\u2713" : "";
+    getEl("quiet-weekends-check").textContent = config.weekends ? "\u2713" : "";
+  };
+
+  getEl("toggle-quiet-hours").addEventListener("click", async () => {
+    try {
+      const current = await invoke<QuietHoursConfig>("get_quiet_hours_config");
+      await saveQuietHours({ enabled: !current.enabled });
+    } catch (e) {
+      alert(`Failed to toggle quiet hours: ${e}`);
+    }
+  });
+
+  getEl("toggle-quiet-weekends").addEventListener("click", async () => {
+    try {
+      const current = await invoke<QuietHoursConfig>("get_quiet_hours_config");
+      await saveQuietHours({ weekends: !current.weekends });
+    } catch (e) {
+      alert(`Failed to toggle weekend quiet hours: ${e}`);
+    }
+  });
+
+  getEl("save-quiet-hours").addEventListener("click", async () => {
+    try {
+      await saveQuietHours({
+        start: (getEl("quiet-hours-start") as HTMLInputElement).value,
+        end: (getEl("quiet-hours-end") as HTMLInputElement).value,
+      });
+      alert("Quiet hours saved!");
+    } catch (e) {
+      alert(`Failed to save quiet hours: ${e}`);
+    }
+  });
+
+  // Local REST API (backend config)
+  loadApiConfig();
+
+  const toggleLocalApi = getEl("toggle-local-api");
+  const localApiCheck = getEl("local-api-check");
+
+  toggleLocalApi.addEventListener("click", async () => {
+    try {
+      const config = await invoke<ApiConfig>("get_api_config");
+      const updated = await invoke<ApiConfig>("save_api_config", {
+        enabled: !config.enabled,
+        port: config.port,
+      });
+      localApiCheck.textContent = updated.enabled ? "\u2713" : "";
+    } catch (e) {
+      alert(`Failed to toggle local API: ${e}`);
+    }
+  });
+
+  // Usage file for Polybar / Waybar / Rainmeter (backend config)
+  loadEncryptionConfig();
+  getEl("toggle-encryption").addEventListener("click", async () => {
+    try {
+      const config = await invoke<EncryptionConfig>("get_encryption_config");
+      await invoke("set_encryption_enabled", { enabled: !config.enabled });
+      await loadEncryptionConfig();
+    } catch (e) {
+      alert(`Failed to toggle encryption: ${e}`);
+    }
+  });
+
+  loadDumpConfig();
+  const saveDumpConfig = async (enabled: boolean) => {
+    const path = (getEl("dump-path") as HTMLInputElement).value.trim();
+    await invoke("save_dump_config", { enabled, path: path || null });
+    await loadDumpConfig();
+  };
+
+  getEl("toggle-dump").addEventListener("click", async () => {
+    try {
+      const config = await invoke<DumpConfig>("get_dump_config");
+      await saveDumpConfig(!config.enabled);
+    } catch (e) {
+      alert(`Failed to toggle usage file: ${e}`);
+    }
+  });
+
+  getEl("save-dump-path").addEventListener("click", async () => {
+    try {
+      const config = await invoke<DumpConfig>("get_dump_config");
+      await saveDumpConfig(config.enabled);
+      alert("Usage file path saved!");
+    } catch (e) {
+      alert(`Failed to save usage file path: ${e}`);
+    }
+  });
+
+  getEl("copy-api-token").addEventListener("click", async () => {
+    hideMenu();
+    try {
+      const config = await invoke<ApiConfig>("get_api_config");
+      if (!config.token) {
+        alert("Enable the local API first to generate a token");
+        return;
+      }
+      await navigator.clipboard.writeText(config.token);
+    } catch (e) {
+      console.warn("Failed to copy API token:", e);
+    }
+  });
+
+  // Monthly budget across providers (backend config)
+  loadBudgetConfig();
+  getEl("save-budget-config").addEventListener("click", async () => {
+    const value = (id: string) => (getEl(id) as HTMLInputElement).value.trim();
+    const amount = value("budget-amount");
+    const price = value("budget-copilot-price");
+    try {
+      await invoke("save_budget_config", {
+        monthlyBudget: amount ? Number(amount) : null,
+        copilotOveragePrice: price ? Number(price) : 0.04,
+        budgetThresholds: value("budget-thresholds")
+          .split(",")
+          .map((v) => v.trim())
+          .filter((v) => v.length > 0)
+          .map(Number),
+      });
+      alert("Budget saved!");
+    } catch (e) {
+      alert(`Failed to save budget: ${e}`);
+    }
+  });
+
+  // Backend language (notifications, tray, reset time text)
+  loadLocale();
+  document.querySelectorAll<HTMLElement>("[data-locale]").forEach((btn) => {
+    btn.addEventListener("click", async () => {
+      try {
+        await invoke("set_locale", { locale: btn.dataset.locale || null });
+        applyLocaleButtons(btn.dataset.locale ?? "");
+      } catch (e) {
+        alert(`Failed to set language: ${e}`);
+      }
+    });
+  });
+
+  // Health report (why data stopped updating)
+  getEl("show-health").addEventListener("click", async () => {
+    hideMenu();
+    try {
+      alert(formatHealthReport(await invoke<HealthReport>("get_health")));
+    } catch (e) {
+      alert(`Failed to get status: ${e}`);
+    }
+  });
+
+  // Per-model weekly quota breakdown
+  getEl("show-model-breakdown").addEventListener("click", async () => {
+    hideMenu();
+    try {
+      const breakdown = await invoke<ModelBreakdown>("get_model_breakdown", { range: "7d" });
+      alert(formatModelBreakdown(breakdown));
+    } catch (e) {
+      alert(`Failed to get model breakdown: ${e}`);
+    }
+  });
+
+  // Recent logs (troubleshooting)
+  getEl("show-logs").addEventListener("click", async () => {
+    hideMenu();
+    try {
+      const lines = await invoke<string[]>("get_recent_logs", { lines: 40 });
+      alert(lines.length > 0 ? lines.join("\n") : "No logs yet");
+    } catch (e) {
+      alert(`Failed to read logs: ${e}`);
+    }
+  });
+
+  // External pages (URLs are whitelisted in the backend)
+  document.querySelectorAll<HTMLElement>("[data-external]").forEach((btn) => {
+    btn.addEventListener("click", async () => {
+      hideMenu();
+      try {
+        await invoke("open_external", { target: btn.dataset.external });
+      } catch (e) {
+        alert(`Failed to open page: ${e}`);
+      }
+    });
+  });
+
+  // Config folder / credentials file (support and debugging)
+  getEl("open-config-dir").addEventListener("click", async () => {
+    hideMenu();
+    try {
+      await invoke("open_config_dir");
+    } catch (e) {
+      alert(`Failed to open config folder: ${e}`);
+    }
+  });
+
+  getEl("open-credentials-file").addEventListener("click", async () => {
+    hideMenu();
+    try {
+      await invoke("open_credentials_file");
+    } catch (e) {
+      alert(`Failed to show credentials file: ${e}`);
+    }
+  });
+
   // Force refresh
   forceRefresh.addEventListener("click", async () => {
     try {
@@ -307,6 +939,38 @@ export function initContextMenu(): void {
     }
   });
 
+  // Anthropic Admin API (organization-wide usage)
+  loadAdminApiConfig();
+  getEl("save-admin-api-key").addEventListener("click", async () => {
+    const keyEl = getEl("admin-api-key") as HTMLInputElement;
+    try {
+      await invoke("save_admin_api_config", { apiKey: keyEl.value.trim() });
+      keyEl.value = "";
+      await loadAdminApiConfig();
+      alert("Admin API key saved!");
+    } catch (e) {
+      alert(`Failed to save Admin API key: ${e}`);
+    }
+  });
+
+  getEl("show-org-breakdown").addEventListener("click", async () => {
+    hideMenu();
+    try {
+      alert(formatOrgBreakdown(await invoke<OrgBreakdown>("get_org_breakdown")));
+    } catch (e) {
+      alert(`Failed to get org breakdown: ${e}`);
+    }
+  });
+
+  getEl("show-team-usage").addEventListener("click", async () => {
+    hideMenu();
+    try {
+      alert(formatTeamUsage(await invoke<TeamUsage>("get_team_usage")));
+    } catch (e) {
+      alert(`Failed to get team usage: ${e}`);
+    }
+  });
+
   // GitHub 設定の読み込み
   loadGitHubConfig();
 
@@ -316,7 +980,8 @@ export function initContextMenu(): void {
     const username = (getEl("github-username") as HTMLInputElement).value.trim();
     const token = (getEl("github-token") as HTMLInputElement).value.trim();
     const limitStr = (getEl("monthly-limit") as HTMLInputElement).value.trim();
-    const monthlyLimit = parseFloat(limitStr) || 300;
+    // 空欄の場合はプランから自動判定する
+    const monthlyLimit = parseFloat(limitStr) || null;
 
     if (!username || !token) {
       alert("Username and Token are required");
@@ -330,13 +995,85 @@ export function initContextMenu(): void {
         monthlyLimit,
       });
       alert("Settings saved successfully!");
-      await invoke("force_refresh");
+      await invoke("refresh_provider", { id: "copilot" });
     } catch (e) {
       alert(`Failed to save settings: ${e}`);
     }
   });
 }
 
+interface OrgBreakdown {
+  since: string;
+  total_cost_usd: number;
+  workspaces: { workspace_id: string | null; name: string | null; cost_usd: number }[];
+  api_keys: {
+    api_key_id: string | null;
+    name: string | null;
+    workspace_id: string | null;
+    input_tokens: number;
+    output_tokens: number;
+  }[];
+}
+
+function formatOrgBreakdown(breakdown: OrgBreakdown): string {
+  const tokens = (n: number) => (n >= 1_000_000 ? `${(n / 1_000_000).toFixed(1)}M` : `${Math.round(n / 1000)}k`);
+  const workspaces = breakdown.workspaces.map(
+    (w) => `  ${w.name ?? w.workspace_id ?? "Default"}: $${w.cost_usd.toFixed(2)}`,
+  );
+  const keys = breakdown.api_keys
+    .slice(0, 10)
+    .map(
+      (k) =>
+        `  ${k.name ?? k.api_key_id ?? "Console"}: ${tokens(k.input_tokens)} in / ${tokens(k.output_tokens)} out`,
+    );
+  return [
+    `Since ${new Date(breakdown.since).toLocaleDateString()}: $${breakdown.total_cost_usd.toFixed(2)}`,
+    "Workspaces:",
+    ...workspaces,
+    "API keys:",
+    ...keys,
+  ].join("\n");
+}
+
+interface TeamUsage {
+  since: string;
+  total_cost_usd: number;
+  members: {
+    user_id: string | null;
+    email: string;
+    name: string | null;
+    role: string | null;
+    sessions: number;
+    input_tokens: number;
+    output_tokens: number;
+    cost_usd: number;
+    share: number;
+  }[];
+}
+
+function formatTeamUsage(usage: TeamUsage): string {
+  const members = usage.members.slice(0, 20).map((m) => {
+    // 他のメンバーより突出して使っている人に印を付ける
+    const mark = m.share >= 50 ? "\u26A0 " : "";
+    return `  ${mark}${m.name ?? m.email}: ${m.share.toFixed(0)}% ($${m.cost_usd.toFixed(2)}, ${m.sessions} sessions)`;
+  });
+  return [
+    `Claude Code since ${new Date(usage.since).toLocaleDateString()}: $${usage.total_cost_usd.toFixed(2)}`,
+    "Members (share of team tokens):",
+    ...members,
+  ].join("\n");
+}
+
+async function loadAdminApiConfig() {
+  try {
+    const configured = await invoke<boolean>("get_admin_api_config");
+    const keyEl = document.getElementById("admin-api-key") as HTMLInputElement | null;
+    if (keyEl) keyEl.placeholder = configured ? "Configured (enter to replace)" : "Admin API Key (sk-ant-admin...)";
+  } catch (e) {
+    console.error("Failed to load Admin API config:", e);
+  }
+}
+
 async function loadGitHubConfig() {
   try {
     const config = await invoke("get_github_config") as any;
@@ -344,14 +1081,363 @@ async function loadGitHubConfig() {
       const usernameEl = document.getElementById("github-username") as HTMLInputElement;
       const limitEl = document.getElementById("monthly-limit") as HTMLInputElement;
       if (usernameEl) usernameEl.value = config.username || "";
-      if (limitEl) limitEl.value = String(config.monthly_limit || 300);
+      if (limitEl) limitEl.value = config.monthly_limit ? String(config.monthly_limit) : "";
       // トークンは表示しない（セキュリティ上の理由）
     }
+
+    const plan = await invoke<{ plan: string | null; monthly_limit: number } | null>("get_copilot_plan");
+    const limitEl = document.getElementById("monthly-limit") as HTMLInputElement | null;
+    if (limitEl && plan) {
+      limitEl.placeholder = `Monthly Limit (auto: ${plan.plan ?? "unknown"} ${plan.monthly_limit})`;
+    }
   } catch (e) {
     console.error("Failed to load GitHub config:", e);
   }
 }
 
+async function loadOverlayConfig() {
+  try {
+    const config = await invoke("get_overlay_config") as { click_through: boolean; opacity: number };
+    const clickThroughCheck = document.getElementById("overlay-click-through-check");
+    const slider = document.getElementById("overlay-opacity-slider") as HTMLInputElement;
+    const value = document.getElementById("overlay-opacity-value");
+    if (clickThroughCheck) clickThroughCheck.textContent = config.click_through ? "\u2713" : "";
+    if (slider) slider.value = String(config.opacity);
+    if (value) value.textContent = `${config.opacity}%`;
+  } catch (e) {
+    console.error("Failed to load overlay config:", e);
+  }
+}
+
+interface BatterySaverConfig {
+  enabled: boolean;
+  interval_multiplier: number;
+  skip_copilot: boolean;
+}
+
+interface IdleConfig {
+  enabled: boolean;
+  idle_after_secs: number;
+  interval_multiplier: number;
+}
+
+async function loadIdleConfig() {
+  try {
+    const config = await invoke<IdleConfig>("get_idle_config");
+    const check = document.getElementById("idle-throttle-check");
+    if (check) check.textContent = config.enabled ? "\u2713" : "";
+    document.querySelectorAll<HTMLElement>("[data-idle-after]").forEach((btn) => {
+      btn.classList.toggle("active", parseInt(btn.dataset.idleAfter!) === config.idle_after_secs);
+    });
+  } catch (e) {
+    console.error("Failed to load idle config:", e);
+  }
+}
+
+interface SnapConfig {
+  enabled: boolean;
+  margin: number;
+}
+
+async function loadSnapConfig() {
+  try {
+    const config = await invoke<SnapConfig>("get_snap_config");
+    const check = document.getElementById("snap-check");
+    if (check) check.textContent = config.enabled ? "\u2713" : "";
+    document.querySelectorAll<HTMLElement>("[data-snap-margin]").forEach((btn) => {
+      btn.classList.toggle("active", parseInt(btn.dataset.snapMargin!) === config.margin);
+    });
+  } catch (e) {
+    console.error("Failed to load snap config:", e);
+  }
+}
+
+async function loadBatterySaverConfig() {
+  try {
+    const config = await invoke<BatterySaverConfig>("get_battery_saver_config");
+    const check = document.getElementById("battery-saver-check");
+    if (check) check.textContent = config.enabled ? "\u2713" : "";
+  } catch (e) {
+    console.error("Failed to load battery saver config:", e);
+  }
+}
+
+interface AlertConfig {
+  thresholds: number[];
+  webhook_urls: string[];
+  desktop_notifications: boolean;
+  notify_on_reset: boolean;
+  extra_usage_thresholds: number[];
+  budget_thresholds: number[];
+}
+
+function applyAlertChecks(config: AlertConfig) {
+  const notificationsCheck = document.getElementById("desktop-notifications-check");
+  const resetCheck = document.getElementById("notify-on-reset-check");
+  if (notificationsCheck) notificationsCheck.textContent = config.desktop_notifications ? "\u2713" : "";
+  if (resetCheck) resetCheck.textContent = config.notify_on_reset ? "\u2713" : "";
+}
+
+async function loadAlertConfig() {
+  try {
+    const config = await invoke<AlertConfig>("get_alert_config");
+    const thresholdsEl = document.getElementById("alert-thresholds") as HTMLInputElement;
+    const urlsEl = document.getElementById("alert-webhook-urls") as HTMLInputElement;
+    if (thresholdsEl) thresholdsEl.value = config.thresholds.join(", ");
+    if (urlsEl) urlsEl.value = config.webhook_urls.join(", ");
+    const extraEl = document.getElementById("extra-usage-thresholds") as HTMLInputElement;
+    if (extraEl) extraEl.value = config.extra_usage_thresholds.join(", ");
+    applyAlertChecks(config);
+  } catch (e) {
+    console.error("Failed to load alert config:", e);
+  }
+}
+
+interface SoundConfig {
+  enabled: boolean;
+  on_threshold: boolean;
+  on_reset: boolean;
+  chime: string;
+  file: string | null;
+  volume: number;
+}
+
+function applySoundConfig(config: SoundConfig) {
+  const check = (id: string, on: boolean) => {
+    const el = document.getElementById(id);
+    if (el) el.textContent = on ? "\u2713" : "";
+  };
+  check("alert-sound-check", config.enabled);
+  check("sound-on-threshold-check", config.on_threshold);
+  check("sound-on-reset-check", config.on_reset);
+  document.querySelectorAll<HTMLElement>("[data-chime]").forEach((b) => {
+    b.classList.toggle("active", b.dataset.chime === config.chime);
+  });
+  const fileEl = document.getElementById("sound-file") as HTMLInputElement;
+  if (fileEl) fileEl.value = config.file ?? "";
+}
+
+async function loadSoundConfig() {
+  try {
+    applySoundConfig(await invoke<SoundConfig>("get_sound_config"));
+  } catch (e) {
+    console.error("Failed to load sound config:", e);
+  }
+}
+
+interface QuietHoursConfig {
+  enabled: boolean;
+  start: string;
+  end: string;
+  weekends: boolean;
+}
+
+function showAlertSnooze(until: string | null) {
+  const statusEl = document.getElementById("snooze-status");
+  if (!statusEl) return;
+  statusEl.textContent = until
+    ? `Snoozed until ${new Date(until).toLocaleTimeString([], { hour: "2-digit", minute: "2-digit" })}`
+    : "Snooze Alerts";
+}
+
+async function loadAlertSnooze() {
+  try {
+    showAlertSnooze(await invoke<string | null>("get_alert_snooze"));
+  } catch (e) {
+    console.error("Failed to load alert snooze:", e);
+  }
+}
+
+async function loadQuietHoursConfig() {
+  try {
+    const config = await invoke<QuietHoursConfig>("get_quiet_hours_config");
+    const enabledCheck = document.getElementById("quiet-hours-check");
+    const weekendsCheck = document.getElementById("quiet-weekends-check");
+    const startEl = document.getElementById("quiet-hours-start") as HTMLInputElement;
+    const endEl = document.getElementById("quiet-hours-end") as HTMLInputElement;
+    if (enabledCheck) enabledCheck.textContent = config.enabled ? "\u2713" : "";
+    if (weekendsCheck) weekendsCheck.textContent = config.weekends ? "\u2713" : "";
+    if (startEl) startEl.value = config.start;
+    if (endEl) endEl.value = config.end;
+  } catch (e) {
+    console.error("Failed to load quiet hours config:", e);
+  }
+}
+
+interface ProviderHealth {
+  last_success_at: string | null;
+  last_failure_at: string | null;
+  last_error: string | null;
+}
+
+interface HealthReport {
+  polling: { running: boolean; last_error: string | null; next_fire_at: string | null };
+  credentials_watcher:
+    | { status: "starting" }
+    | { status: "active"; path: string }
+    | { status: "failed"; error: string };
+  claude: ProviderHealth;
+  copilot: ProviderHealth;
+}
+
+function formatHealthReport(report: HealthReport): string {
+  const time = (t: string | null) => (t ? new Date(t).toLocaleString() : "-");
+  const provider = (name: string, p: ProviderHealth) =>
+    `${name}: last success ${time(p.last_success_at)}` +
+    (p.last_error ? `\n  last error (${time(p.last_failure_at)}): ${p.last_error}` : "");
+  const watcher = report.credentials_watcher;
+
+  return [
+    `Polling: ${report.polling.running ? "running" : "stopped"}` +
+      (report.polling.running ? `, next ${time(report.polling.next_fire_at)}` : ""),
+    report.polling.last_error ? `  ${report.polling.last_error}` : "",
+    `Credentials watcher: ${watcher.status}` + (watcher.status === "failed" ? ` (${watcher.error})` : ""),
+    provider("Claude", report.claude),
+    provider("Copilot", report.copilot),
+  ]
+    .filter((line) => line.length > 0)
+    .join("\n");
+}
+
+interface MeterInfo {
+  id: string;
+  label: string;
+  present: boolean;
+  utilization: number | null;
+}
+
+// 現在のプランで返されているメーターだけを切り離せるようにする
+async function loadDetachableMeters() {
+  try {
+    const meters = await invoke<MeterInfo[]>("get_all_meters");
+    const present = new Map(meters.filter((m) => m.present).map((m) => [m.id, m]));
+    // まだ取得していない間はすべて表示しておく
+    if (present.size === 0) return;
+    document.querySelectorAll<HTMLElement>("[data-detach]").forEach((btn) => {
+      const meter = present.get(btn.dataset.detach!);
+      btn.classList.toggle("hidden", !meter);
+      if (meter) btn.title = meter.label;
+    });
+  } catch (e) {
+    console.error("Failed to load meters:", e);
+  }
+}
+
+interface ModelSeries {
+  meter: string;
+  points: { timestamp: string; utilization: number }[];
+  latest: number;
+  peak: number;
+  consumed: number;
+}
+
+interface ModelBreakdown {
+  since: string | null;
+  models: ModelSeries[];
+}
+
+const MODEL_LABELS: Record<string, string> = {
+  seven_day_opus: "Opus",
+  seven_day_sonnet: "Sonnet",
+  seven_day_oauth_apps: "OAuth Apps",
+  seven_day_cowork: "Cowork",
+};
+
+function formatModelBreakdown(breakdown: ModelBreakdown): string {
+  if (breakdown.models.length === 0) return "No per-model history yet";
+  return breakdown.models
+    .map(
+      (m) =>
+        `${MODEL_LABELS[m.meter] ?? m.meter}: now ${m.latest.toFixed(1)}%, ` +
+        `peak ${m.peak.toFixed(1)}%, used +${m.consumed.toFixed(1)}%`,
+    )
+    .join("\n");
+}
+
+interface BudgetConfig {
+  monthly_budget: number | null;
+  copilot_overage_price: number;
+}
+
+async function loadBudgetConfig() {
+  try {
+    const config = await invoke<BudgetConfig>("get_budget_config");
+    const alerts = await invoke<AlertConfig>("get_alert_config");
+    (getEl("budget-amount") as HTMLInputElement).value =
+      config.monthly_budget !== null ? String(config.monthly_budget) : "";
+    (getEl("budget-copilot-price") as HTMLInputElement).value = String(config.copilot_overage_price);
+    (getEl("budget-thresholds") as HTMLInputElement).value = alerts.budget_thresholds.join(", ");
+  } catch (e) {
+    console.error("Failed to load budget config:", e);
+  }
+}
+
+function applyLocaleButtons(locale: string) {
+  document.querySelectorAll<HTMLElement>("[data-locale]").forEach((b) => {
+    b.classList.toggle("active", (b.dataset.locale ?? "") === locale);
+  });
+}
+
+async function loadLocale() {
+  try {
+    const info = await invoke<{ setting: string | null }>("get_locale");
+    applyLocaleButtons(info.setting ?? "");
+  } catch (e) {
+    console.error("Failed to load locale:", e);
+  }
+}
+
+interface DumpConfig {
+  enabled: boolean;
+  path: string | null;
+  resolved_path: string;
+}
+
+async function loadDumpConfig() {
+  try {
+    const config = await invoke<DumpConfig>("get_dump_config");
+    const check = document.getElementById("dump-check");
+    const pathEl = document.getElementById("dump-path") as HTMLInputElement | null;
+    if (check) check.textContent = config.enabled ? "\u2713" : "";
+    if (pathEl) {
+      pathEl.value = config.path ?? "";
+      pathEl.placeholder = config.resolved_path;
+    }
+  } catch (e) {
+    console.error("Failed to load usage file config:", e);
+  }
+}
+
+interface EncryptionConfig {
+  enabled: boolean;
+}
+
+async function loadEncryptionConfig() {
+  try {
+    const config = await invoke<EncryptionConfig>("get_encryption_config");
+    const check = document.getElementById("encryption-check");
+    if (check) check.textContent = config.enabled ? "\u2713" : "";
+  } catch (e) {
+    console.error("Failed to load encryption config:", e);
+  }
+}
+
+interface ApiConfig {
+  enabled: boolean;
+  port: number;
+  token: string;
+}
+
+async function loadApiConfig() {
+  try {
+    const config = await invoke<ApiConfig>("get_api_config");
+    const check = document.getElementById("local-api-check");
+    if (check) check.textContent = config.enabled ? "\u2713" : "";
+  } catch (e) {
+    console.error("Failed to load local API config:", e);
+  }
+}
+
 async function loadAutostartStatus() {
   try {
     const isEnabled = await invoke("is_autostart_enabled") as boolean;
@@ -1,7 +1,18 @@
 import { listen } from "@tauri-apps/api/event";
 import { invoke } from "@tauri-apps/api/core";
 import { getCurrentWindow } from "@tauri-apps/api/window";
-import { updateWidget, isExpired, type CombinedUsageData, type CopilotUsageData } from "./widget";
+import {
+  updateWidget,
+  isExpired,
+  type CombinedUsageData,
+  type BudgetUsage,
+  type CopilotAuthError,
+  type CopilotUsageData,
+  type RateLimitInfo,
+  type ResetTime,
+  type StaleUsage,
+  type UsageData,
+} from "./widget";
 import { initContextMenu } from "./context-menu";
 
 let latestData: CombinedUsageData | null = null;
@@ -20,17 +31,53 @@ async function initDrag() {
   }
 }
 
+interface CachedUsage extends UsageData {
+  copilot: CopilotUsageData | null;
+  fetched_at: string | null;
+  reset_times: Record<string, ResetTime>;
+  budget: BudgetUsage | null;
+  rate_limit: RateLimitInfo | null;
+  stale: StaleUsage | null;
+}
+
+// レート制限中は解除される時刻を表示する
+function showRateLimited(rateLimit: RateLimitInfo | null | undefined) {
+  const statusEl = document.getElementById("token-status");
+  if (!statusEl || !rateLimit?.limited_until) return;
+  const until = new Date(rateLimit.limited_until);
+  if (until.getTime() <= Date.now()) return;
+
+  const time = until.toLocaleTimeString([], { hour: "2-digit", minute: "2-digit" });
+  statusEl.textContent = `⏳ Rate limited until ${time}`;
+  statusEl.className = "token-status warning";
+  statusEl.title = "API のレート制限に達しました。\n解除される時刻まで取得を控えます。";
+}
+
 async function fetchInitialData() {
   try {
-    const data = await invoke<CombinedUsageData>("get_usage");
-    // get_usage returns only Claude data, wrap it in CombinedUsageData format
-    if (data && "five_hour" in data) {
-      latestData = { claude: data as any, copilot: null };
-    } else {
-      latestData = data;
-    }
+    const data = await invoke<CachedUsage>("get_usage");
+    // get_usage returns Claude data at the top level, wrap it in CombinedUsageData format
+    latestData = {
+      claude: data,
+      copilot: data.copilot,
+      fetched_at: data.fetched_at,
+      reset_times: data.reset_times,
+      budget: data.budget,
+      rate_limit: data.rate_limit,
+      stale: data.stale,
+    };
     refreshTriggered = false;
-    if (latestData) updateWidget(latestData);
+    updateWidget(latestData);
+    showRateLimited(data.rate_limit);
+
+    // 前回終了時のデータや取得に失敗して古いままのデータの場合は、次の取得が終わるまでその旨を表示する
+    const statusEl = document.getElementById("token-status");
+    if (data.stale && statusEl && !statusEl.textContent) {
+      const fetchedAt = data.fetched_at ? new Date(data.fetched_at).toLocaleString() : "unknown";
+      statusEl.textContent = "⏳ Cached";
+      statusEl.className = "token-status warning";
+      statusEl.title = `${data.stale.reason} (${fetchedAt} 取得)。\n最新のデータを取得中です。`;
+    }
   } catch {
     // Will be updated via events once API connects
   }
@@ -40,10 +87,62 @@ window.addEventListener("DOMContentLoaded", async () => {
   initDrag();
   initContextMenu();
 
+  const markFresh = () => {
+    refreshTriggered = false;
+    const widget = document.querySelector<HTMLElement>(".widget");
+    if (widget) {
+      widget.classList.remove("stale");
+      widget.title = "";
+    }
+  };
+
+  // 最後の取得に成功した時刻と、取得できなかった理由を表示して数値をグレーアウトする
+  const markStale = (fetchedAt: string | null | undefined, lastError: string | null) => {
+    const widget = document.querySelector<HTMLElement>(".widget");
+    if (!widget) return;
+    widget.classList.add("stale");
+    const time = fetchedAt ? new Date(fetchedAt).toLocaleString() : "unknown";
+    widget.title = `Last updated: ${time}` + (lastError ? `\n${lastError}` : "");
+  };
+
+  // GitHub に認証を拒否されている間は Claude の取得が成功しても警告を残す
+  let copilotAuthError: CopilotAuthError | null = null;
+  const showCopilotAuthError = (error: CopilotAuthError) => {
+    const statusEl = document.getElementById("token-status");
+    if (!statusEl) return;
+    statusEl.textContent = "⚠ GitHub token invalid";
+    statusEl.className = "token-status warning";
+    statusEl.title = error.source === "gh_cli"
+      ? "gh CLI のトークンが拒否されました。\ngh auth refresh -s user を実行してください。"
+      : "GitHub PAT が無効か、copilot 権限がありません。\nContext Menu から PAT を設定し直してください。";
+  };
+
+  await listen<CopilotAuthError>("copilot-auth-error", (event) => {
+    copilotAuthError = event.payload;
+    showCopilotAuthError(event.payload);
+  });
+
   await listen<CombinedUsageData>("usage-update", (event) => {
+    if (!event.payload.copilot_needs_reauth) copilotAuthError = null;
     latestData = event.payload;
-    refreshTriggered = false;
     updateWidget(event.payload);
+    // 取得に失敗した間は最後に取得できた値が理由付きで届く
+    if (event.payload.stale) {
+      markStale(event.payload.fetched_at, event.payload.stale.reason);
+    } else {
+      markFresh();
+    }
+  });
+
+  // usage-update はデータが変化した場合のみ届くため、取得の成功は heartbeat で確認する
+  await listen<{ fetched_at: string; changed: boolean }>("heartbeat", (event) => {
+    if (latestData) latestData.fetched_at = event.payload.fetched_at;
+    markFresh();
+  });
+
+  // 最後の取得成功から時間が経ちすぎた場合は数値をグレーアウトする
+  await listen<{ fetched_at: string | null; last_error: string | null }>("data-stale", (event) => {
+    markStale(event.payload.fetched_at, event.payload.last_error);
   });
 
   await listen<CopilotUsageData>("copilot-only-update", (event) => {
@@ -74,6 +173,10 @@ window.addEventListener("DOMContentLoaded", async () => {
         statusEl.title = "API からデータを取得できませんでした。\nネットワーク接続を確認してください。";
         break;
       case "ok":
+        if (copilotAuthError) {
+          showCopilotAuthError(copilotAuthError);
+          break;
+        }
         statusEl.textContent = "";
         statusEl.className = "token-status";
         statusEl.title = "";
@@ -81,6 +184,65 @@ window.addEventListener("DOMContentLoaded", async () => {
     }
   });
 
+  // 使用率が 25/50/75/90/100% の境界をまたいだメーターを一時的に強調する
+  const milestoneSections: Record<string, string> = {
+    five_hour: "claude-session",
+    seven_day: "claude-weekly",
+    copilot: "copilot",
+  };
+  await listen<{ meter: string; direction: "up" | "down" }>("milestone-crossed", (event) => {
+    const type = milestoneSections[event.payload.meter];
+    const section = type && document.querySelector<HTMLElement>(`[data-meter-type="${type}"]`);
+    if (!section) return;
+    const cls = `milestone-${event.payload.direction}`;
+    section.classList.remove("milestone-up", "milestone-down");
+    void section.offsetWidth; // アニメーションを最初から再生する
+    section.classList.add(cls);
+    section.addEventListener("animationend", () => section.classList.remove(cls), { once: true });
+  });
+
+  await listen<RateLimitInfo>("rate-limited", (event) => {
+    showRateLimited(event.payload);
+  });
+
+  await listen<{ remaining_secs: number }>("token-expiring", (event) => {
+    const statusEl = document.getElementById("token-status");
+    if (!statusEl) return;
+
+    const minutes = Math.max(1, Math.round(event.payload.remaining_secs / 60));
+    statusEl.textContent = `⏳ Token expires in ${minutes}m`;
+    statusEl.className = "token-status warning";
+    statusEl.title = "アクセストークンの有効期限がまもなく切れます。\nターミナルで claude コマンドを実行すると更新されます。";
+  });
+
+  await listen<string>("network-status", (event) => {
+    const statusEl = document.getElementById("token-status");
+    if (!statusEl) return;
+
+    if (event.payload === "offline") {
+      statusEl.textContent = "⚠ Offline";
+      statusEl.className = "token-status warning";
+      statusEl.title = "ネットワークに接続できません。\n接続が回復すると自動的に更新されます。";
+    }
+  });
+
+  await listen<{ version: string }>("update-downloaded", async (event) => {
+    const restart = confirm(
+      `新しいバージョン ${event.payload.version} をダウンロードしました。\n再起動して更新しますか？`,
+    );
+    if (restart) {
+      try {
+        await invoke("install_update");
+      } catch (e) {
+        alert(`Failed to install update: ${e}`);
+      }
+    }
+  });
+
+  invoke<boolean>("is_demo_mode")
+    .then((demo) => document.getElementById("demo-badge")?.classList.toggle("hidden", !demo))
+    .catch(() => {});
+
   await fetchInitialData();
 
   setInterval(() => {
@@ -0,0 +1,104 @@
+import { listen } from "@tauri-apps/api/event";
+import { invoke } from "@tauri-apps/api/core";
+import { getCurrentWindow } from "@tauri-apps/api/window";
+import {
+  isExpired,
+  type CombinedUsageData,
+  type CopilotUsageData,
+  type UsageData,
+  type UsageMeter,
+} from "./widget";
+
+type ClaudeMeterId =
+  | "five_hour"
+  | "seven_day"
+  | "seven_day_opus"
+  | "seven_day_sonnet"
+  | "seven_day_oauth_apps"
+  | "seven_day_cowork";
+
+const METER_LABELS: Record<string, string> = {
+  five_hour: "Claude Current Session",
+  seven_day: "Claude Weekly Limit",
+  seven_day_opus: "Claude Weekly (Opus)",
+  seven_day_sonnet: "Claude Weekly (Sonnet)",
+  seven_day_oauth_apps: "Claude Weekly (OAuth Apps)",
+  seven_day_cowork: "Claude Weekly (Cowork)",
+  copilot: "GitHub Copilot (Monthly)",
+};
+
+// ウィンドウラベル "meter-<id>" から表示対象のメーターを決める
+const meterId = getCurrentWindow().label.replace(/^meter-/, "");
+
+function getEl(id: string): HTMLElement {
+  const el = document.getElementById(id);
+  if (!el) throw new Error(`Required DOM element #${id} not found`);
+  return el;
+}
+
+function thresholdClass(percent: number): string {
+  if (percent >= 80) return " critical";
+  if (percent >= 60) return " warning";
+  return "";
+}
+
+function render(meter: UsageMeter | null | undefined) {
+  const bar = getEl("meter-bar");
+  const detail = getEl("meter-detail");
+  const baseClass = meterId === "copilot" ? "meter-window-bar copilot" : "meter-window-bar";
+
+  if (!meter) {
+    bar.style.width = "0%";
+    bar.className = baseClass;
+    detail.textContent = "Not available";
+    return;
+  }
+
+  const percent = isExpired(meter.resets_at) ? 0 : meter.utilization;
+  bar.style.width = `${Math.min(100, percent)}%`;
+  bar.className = baseClass + thresholdClass(percent);
+  detail.textContent = `${Math.round(percent)}% used`;
+}
+
+function renderClaude(data: UsageData) {
+  if (meterId === "copilot") return;
+  render(data[meterId as ClaudeMeterId]);
+}
+
+function renderCopilot(copilot: CopilotUsageData | null | undefined) {
+  if (meterId !== "copilot") return;
+  render(copilot ? { utilization: copilot.utilization, resets_at: copilot.resets_at } : null);
+}
+
+window.addEventListener("DOMContentLoaded", async () => {
+  getEl("meter-label").textContent = METER_LABELS[meterId] ?? meterId;
+
+  getEl("meter-window").addEventListener("mousedown", async (e) => {
+    if (e.button !== 0) return;
+    if ((e.target as HTMLElement).closest("button")) return;
+    await getCurrentWindow().startDragging();
+  });
+
+  getEl("meter-close").addEventListener("click", async () => {
+    try {
+      await invoke("close_meter_window", { meterId });
+    } catch (e) {
+      console.warn("Failed to close meter window:", e);
+    }
+  });
+
+  await listen<CombinedUsageData>("usage-update", (event) => {
+    renderClaude(event.payload.claude);
+    renderCopilot(event.payload.copilot);
+  });
+
+  await listen<CopilotUsageData>("copilot-only-update", (event) => {
+    renderCopilot(event.payload);
+  });
+
+  try {
+    renderClaude(await invoke<UsageData>("get_usage"));
+  } catch {
+    // Will be updated via events once API connects
+  }
+});
@@ -0,0 +1,68 @@
+import { listen } from "@tauri-apps/api/event";
+import { invoke } from "@tauri-apps/api/core";
+import { getCurrentWindow } from "@tauri-apps/api/window";
+import { isExpired, type CombinedUsageData, type UsageData, type UsageMeter } from "./widget";
+
+interface OverlayConfig {
+  click_through: boolean;
+  opacity: number;
+}
+
+function getEl(id: string): HTMLElement {
+  const el = document.getElementById(id);
+  if (!el) throw new Error(`Required DOM element #${id} not found`);
+  return el;
+}
+
+function thresholdClass(percent: number): string {
+  if (percent >= 80) return " critical";
+  if (percent >= 60) return " warning";
+  return "";
+}
+
+function updateRow(barId: string, valueId: string, baseClass: string, meter: UsageMeter) {
+  const bar = getEl(barId);
+  const value = getEl(valueId);
+  const percent = isExpired(meter.resets_at) ? 0 : meter.utilization;
+
+  bar.style.width = `${Math.min(100, percent)}%`;
+  bar.className = `overlay-bar ${baseClass}${thresholdClass(percent)}`;
+  value.textContent = `${Math.round(percent)}%`;
+}
+
+function updateOverlay(data: UsageData) {
+  updateRow("overlay-session-bar", "overlay-session-value", "session", data.five_hour);
+  updateRow("overlay-weekly-bar", "overlay-weekly-value", "weekly", data.seven_day);
+}
+
+function applyConfig(config: OverlayConfig) {
+  getEl("overlay").style.background = `rgba(18, 18, 18, ${config.opacity / 100})`;
+}
+
+window.addEventListener("DOMContentLoaded", async () => {
+  const overlay = getEl("overlay");
+  overlay.addEventListener("mousedown", async (e) => {
+    if (e.button !== 0) return;
+    await getCurrentWindow().startDragging();
+  });
+
+  await listen<CombinedUsageData>("usage-update", (event) => {
+    updateOverlay(event.payload.claude);
+  });
+
+  await listen<OverlayConfig>("overlay-config", (event) => {
+    applyConfig(event.payload);
+  });
+
+  try {
+    applyConfig(await invoke<OverlayConfig>("get_overlay_config"));
+  } catch (e) {
+    console.warn("Failed to load overlay config:", e);
+  }
+
+  try {
+    updateOverlay(await invoke<UsageData>("get_usage"));
+  } catch {
+    // Will be updated via events once API connects
+  }
+});
@@ -0,0 +1,74 @@
+import { listen } from "@tauri-apps/api/event";
+import { invoke } from "@tauri-apps/api/core";
+import { isExpired, type CombinedUsageData, type UsageMeter } from "./widget";
+
+function getEl(id: string): HTMLElement {
+  const el = document.getElementById(id);
+  if (!el) throw new Error(`Required DOM element #${id} not found`);
+  return el;
+}
+
+function thresholdClass(percent: number): string {
+  if (percent >= 80) return " critical";
+  if (percent >= 60) return " warning";
+  return "";
+}
+
+function row(label: string, percent: number, baseClass: string): HTMLElement {
+  const el = document.createElement("div");
+  el.className = "popup-row";
+
+  const name = document.createElement("span");
+  name.className = "popup-label";
+  name.textContent = label;
+
+  const track = document.createElement("div");
+  track.className = "popup-track";
+  const bar = document.createElement("div");
+  bar.className = `popup-bar ${baseClass}${thresholdClass(percent)}`;
+  bar.style.width = `${Math.min(100, percent)}%`;
+  track.appendChild(bar);
+
+  const value = document.createElement("span");
+  value.className = "popup-value";
+  value.textContent = `${Math.round(percent)}%`;
+
+  el.append(name, track, value);
+  return el;
+}
+
+function meterPercent(meter: UsageMeter): number {
+  return isExpired(meter.resets_at) ? 0 : meter.utilization;
+}
+
+function updatePopup(data: CombinedUsageData) {
+  const rows: HTMLElement[] = [
+    row("5h", meterPercent(data.claude.five_hour), "session"),
+    row("7d", meterPercent(data.claude.seven_day), "weekly"),
+  ];
+  if (data.copilot) {
+    rows.push(row("Copilot", data.copilot.utilization, "copilot"));
+  }
+  if (data.budget) {
+    rows.push(row("Budget", data.budget.utilization, "budget"));
+  }
+  getEl("popup-rows").replaceChildren(...rows);
+
+  getEl("popup-fetched").textContent = data.fetched_at
+    ? new Date(data.fetched_at).toLocaleTimeString([], { hour: "2-digit", minute: "2-digit" })
+    : "--";
+}
+
+window.addEventListener("DOMContentLoaded", async () => {
+  getEl("popup-open").addEventListener("click", async () => {
+    try {
+      await invoke("show_dashboard");
+    } catch (e) {
+      console.error("Failed to open dashboard:", e);
+    }
+  });
+
+  await listen<CombinedUsageData>("usage-update", (event) => {
+    updatePopup(event.payload);
+  });
+});
@@ -8,6 +8,7 @@ export interface ExtraUsage {
   monthly_limit: number;
   used_credits: number;
   utilization: number;
+  estimated_cost?: number | null;
 }
 
 export interface UsageData {
@@ -19,6 +20,7 @@ export interface UsageData {
   seven_day_cowork?: UsageMeter | null;
   iguana_necktie?: unknown;
   extra_usage?: ExtraUsage | null;
+  extra_meters?: Record<string, UsageMeter>;
 }
 
 export interface CopilotUsageItem {
@@ -34,9 +36,52 @@ export interface CopilotUsageData {
   items: CopilotUsageItem[];
 }
 
+export interface ResetTime {
+  local: string;
+  display: string;
+  relative: string;
+}
+
+export interface BudgetUsage {
+  monthly_budget: number;
+  spent: number;
+  utilization: number;
+  claude_extra_usage: number;
+  copilot_overage: number;
+  resets_at: string;
+}
+
+export interface RateLimitInfo {
+  requests_limit: number | null;
+  requests_remaining: number | null;
+  requests_reset: string | null;
+  tokens_limit: number | null;
+  tokens_remaining: number | null;
+  tokens_reset: string | null;
+  retry_after_secs: number | null;
+  limited_until: string | null;
+}
+
 export interface CombinedUsageData {
   claude: UsageData;
   copilot?: CopilotUsageData | null;
+  fetched_at?: string | null;
+  reset_times?: Record<string, ResetTime>;
+  budget?: BudgetUsage | null;
+  rate_limit?: RateLimitInfo | null;
+  copilot_needs_reauth?: boolean;
+  stale?: StaleUsage | null;
+}
+
+export interface StaleUsage {
+  reason: string;
+  failed_at: string;
+}
+
+export interface CopilotAuthError {
+  status: number;
+  source: "gh_cli" | "config" | null;
+  message: string;
 }
 
 interface BarElements {
@@ -202,6 +247,30 @@ function updateCopilotBar(copilot: CopilotUsageData) {
   );
 }
 
+function updateBudgetBar(budget: BudgetUsage | null | undefined) {
+  const section = document.querySelector('[data-meter-type="budget"]');
+  section?.classList.toggle("hidden", !budget);
+  if (!budget) return;
+
+  const budgetElements: BarElements = {
+    usageBar: getElement("budget-usage-bar"),
+    timeBar: getElement("budget-time-bar"),
+    excessBar: getElement("budget-excess-bar"),
+    detail: getElement("budget-detail"),
+  };
+
+  updateBar(
+    budgetElements,
+    budget.utilization,
+    calcMonthlyTimeElapsedPercent(budget.resets_at),
+    budget.resets_at,
+    "Awaiting reset",
+  );
+  budgetElements.detail.textContent =
+    `$${budget.spent.toFixed(2)} / $${budget.monthly_budget.toFixed(2)}` +
+    `  ·  ${Math.round(budget.utilization)}% used`;
+}
+
 export function updateWidget(data: CombinedUsageData) {
   const sessionElements: BarElements = {
     usageBar: getElement("session-usage-bar"),
@@ -240,4 +309,6 @@ export function updateWidget(data: CombinedUsageData) {
   if (data.copilot) {
     updateCopilotBar(data.copilot);
   }
+
+  updateBudgetBar(data.budget);
 }
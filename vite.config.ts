@@ -27,4 +27,15 @@ export default defineConfig(async () => ({
       ignored: ["**/src-tauri/**"],
     },
   },
+  // multi-page build: the main widget, the compact overlay, the tray popup and detached meter windows
+  build: {
+    rollupOptions: {
+      input: {
+        main: "index.html",
+        overlay: "overlay.html",
+        meter: "meter.html",
+        popup: "popup.html",
+      },
+    },
+  },
 }));